@@ -0,0 +1,189 @@
+use crate::pointerpath::is_module_range;
+use mem_analysis::data_interface::DataInterface;
+use mem_analysis::region::{MemType, Protection};
+use rangemap::RangeMap;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+/// What kind of memory an address is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegionClass {
+    /// A module section (see `is_module_range`).
+    Image,
+    Stack,
+    /// Private memory that is not a thread stack.
+    Heap,
+    /// A mapped file that is not a module.
+    Mapped,
+    /// Inside a lua_Page found by `LuaPageSearch`.
+    LuaPage,
+    Unknown,
+}
+
+impl Display for RegionClass {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let name = match self {
+            RegionClass::Image => "image",
+            RegionClass::Stack => "stack",
+            RegionClass::Heap => "heap",
+            RegionClass::Mapped => "mapped",
+            RegionClass::LuaPage => "lua_page",
+            RegionClass::Unknown => "unknown",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for RegionClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "image" | "module" => Ok(RegionClass::Image),
+            "stack" => Ok(RegionClass::Stack),
+            "heap" | "private" => Ok(RegionClass::Heap),
+            "mapped" => Ok(RegionClass::Mapped),
+            "lua_page" | "luapage" => Ok(RegionClass::LuaPage),
+            "unknown" => Ok(RegionClass::Unknown),
+            _ => Err(format!(
+                "Unknown region class: {} (expected image, stack, heap, mapped, lua_page or unknown)",
+                s
+            )),
+        }
+    }
+}
+
+/// The class of an address together with the section (the module name for images) and the
+/// protection of the memory range holding it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressClass {
+    pub class: RegionClass,
+    pub section: String,
+    pub perm: Protection,
+}
+
+/// Classifies the sources and sinks of pointers by the memory range they are in.
+#[derive(Debug)]
+pub struct Classifier<'a> {
+    di: &'a DataInterface,
+    lua_pages: RangeMap<u64, u64>,
+}
+
+impl<'a> Classifier<'a> {
+    pub fn new(di: &'a DataInterface) -> Self {
+        Classifier {
+            di,
+            lua_pages: RangeMap::new(),
+        }
+    }
+
+    /// Classify addresses inside these `(vaddr, size)` lua_Pages as `RegionClass::LuaPage`.
+    pub fn with_lua_pages(mut self, lua_pages: &[(u64, u64)]) -> Self {
+        for (vaddr, size) in lua_pages.iter() {
+            if *size > 0 {
+                self.lua_pages
+                    .insert(*vaddr..vaddr.saturating_add(*size), *vaddr);
+            }
+        }
+        self
+    }
+
+    pub fn classify(&self, vaddr: u64) -> AddressClass {
+        let mr = match self.di.mem_ranges.vmem_ranges.get(&vaddr) {
+            Some(mr) => mr,
+            None => {
+                return AddressClass {
+                    class: RegionClass::Unknown,
+                    section: String::new(),
+                    perm: Protection::NONE,
+                }
+            }
+        };
+        let class = if self.lua_pages.contains_key(&vaddr) {
+            RegionClass::LuaPage
+        } else if mr.is_stack() {
+            RegionClass::Stack
+        } else if is_module_range(mr) {
+            RegionClass::Image
+        } else {
+            match mr.mem_type {
+                MemType::Mapped => RegionClass::Mapped,
+                MemType::Private => RegionClass::Heap,
+                _ => RegionClass::Unknown,
+            }
+        };
+        AddressClass {
+            class,
+            section: mr.name.clone(),
+            perm: mr.perm,
+        }
+    }
+}
+
+/// Selects pointers by the class of their source and sink and the protection of the sink,
+/// parsed from `SOURCE->SINK[:PERM]`, e.g. `heap->image:r--` for vtable-like pointers.  `*`
+/// matches any class and `PERM` has to match the readable, writable and executable flags.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PointerFilter {
+    pub source: Option<RegionClass>,
+    pub sink: Option<RegionClass>,
+    pub sink_perm: Option<Protection>,
+}
+
+impl PointerFilter {
+    pub fn matches(&self, source: &AddressClass, sink: &AddressClass) -> bool {
+        let class_ok = |wanted: &Option<RegionClass>, class: &AddressClass| match wanted {
+            Some(wanted) => *wanted == class.class,
+            None => true,
+        };
+        let perm_ok = match self.sink_perm {
+            // ignore the copy on write and guard flags
+            Some(perm) => Protection::from_perm_str(&sink.perm.to_string()) == perm,
+            None => true,
+        };
+        class_ok(&self.source, source) && class_ok(&self.sink, sink) && perm_ok
+    }
+}
+
+impl FromStr for PointerFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (classes, o_perm) = match s.split_once(':') {
+            Some((classes, perm)) => (classes, Some(perm)),
+            None => (s, None),
+        };
+        let (source, sink) = match classes.split_once("->") {
+            Some((source, sink)) => (source.trim(), sink.trim()),
+            None => {
+                return Err(format!(
+                    "Invalid pointer filter: {} (expected SOURCE->SINK[:PERM])",
+                    s
+                ))
+            }
+        };
+        let parse_class = |class: &str| -> Result<Option<RegionClass>, String> {
+            if class == "*" || class.is_empty() {
+                Ok(None)
+            } else {
+                class.parse().map(Some)
+            }
+        };
+        let parse_perm = |perm: &str| -> Result<Protection, String> {
+            if perm.is_empty() || perm.chars().any(|c| !"rwx-".contains(c)) {
+                return Err(format!(
+                    "Invalid sink protection: {} (expected the flags r, w, x or -, e.g. r--)",
+                    perm
+                ));
+            }
+            Ok(Protection::from_perm_str(perm))
+        };
+        Ok(PointerFilter {
+            source: parse_class(source)?,
+            sink: parse_class(sink)?,
+            sink_perm: o_perm.map(|perm| parse_perm(perm.trim())).transpose()?,
+        })
+    }
+}
@@ -0,0 +1,85 @@
+use mem_analysis::error::MemError;
+use std::error::Error as StdErr;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::Error as IoError;
+use std::sync::PoisonError;
+
+/// Errors returned by the searches.
+#[derive(Debug)]
+pub enum SearchError {
+    /// Loading or reading the dump failed.
+    Mem(MemError),
+    Regex(regex::Error),
+    Io(IoError),
+    /// `pos` is past the end of the `len` bytes being searched.
+    OutOfRange {
+        pos: u64,
+        len: u64,
+    },
+    /// A lock shared with the search threads was poisoned by a thread that panicked.
+    Poisoned(String),
+    /// A search thread panicked.
+    Thread(String),
+    Unsupported(String),
+}
+
+impl SearchError {
+    pub fn unsupported<S: Into<String>>(msg: S) -> Self {
+        SearchError::Unsupported(msg.into())
+    }
+}
+
+impl Display for SearchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            SearchError::Mem(e) => write!(f, "{}", e),
+            SearchError::Regex(e) => write!(f, "Invalid regular expression: {}", e),
+            SearchError::Io(e) => write!(f, "I/O error: {}", e),
+            SearchError::OutOfRange { pos, len } => {
+                write!(
+                    f,
+                    "Position {:08x} is past the end of the buffer ({:08x})",
+                    pos, len
+                )
+            }
+            SearchError::Poisoned(msg) => write!(f, "Poisoned lock: {}", msg),
+            SearchError::Thread(msg) => write!(f, "Search thread failed: {}", msg),
+            SearchError::Unsupported(msg) => write!(f, "Unsupported: {}", msg),
+        }
+    }
+}
+
+impl StdErr for SearchError {
+    fn source(&self) -> Option<&(dyn StdErr + 'static)> {
+        match self {
+            SearchError::Mem(e) => Some(e),
+            SearchError::Regex(e) => Some(e),
+            SearchError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<MemError> for SearchError {
+    fn from(e: MemError) -> Self {
+        SearchError::Mem(e)
+    }
+}
+
+impl From<regex::Error> for SearchError {
+    fn from(e: regex::Error) -> Self {
+        SearchError::Regex(e)
+    }
+}
+
+impl From<IoError> for SearchError {
+    fn from(e: IoError) -> Self {
+        SearchError::Io(e)
+    }
+}
+
+impl<T> From<PoisonError<T>> for SearchError {
+    fn from(e: PoisonError<T>) -> Self {
+        SearchError::Poisoned(e.to_string())
+    }
+}
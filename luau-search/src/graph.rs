@@ -0,0 +1,218 @@
+use crate::error::SearchError;
+use mem_analysis::data_interface::DataInterface;
+use mem_analysis::pointers::XrefIndex;
+use rangemap::RangeMap;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use log::info;
+
+const GRAPHML_NS: &str = "http://graphml.graphdrawing.org/xmlns";
+
+// (id and name, element, type) of the GraphML attributes
+const GRAPHML_KEYS: [(&str, &str, &str); 5] = [
+    ("label", "node", "string"),
+    ("section", "node", "string"),
+    ("vaddr", "node", "string"),
+    ("size", "node", "long"),
+    ("weight", "edge", "long"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    MemRange,
+    Allocation,
+}
+
+/// A group of addresses collapsed into one node, either a whole `MemRange` or one
+/// allocation inside it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphNode {
+    pub kind: NodeKind,
+    pub vaddr: u64,
+    pub size: u64,
+    pub section: String,
+}
+
+impl GraphNode {
+    pub fn id(&self) -> String {
+        match self.kind {
+            NodeKind::MemRange => format!("r{:x}", self.vaddr),
+            NodeKind::Allocation => format!("a{:x}", self.vaddr),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self.kind {
+            NodeKind::MemRange => self.section.clone(),
+            NodeKind::Allocation => format!("{} {:08x}", self.section, self.vaddr),
+        }
+    }
+}
+
+/// The pointers of an `XrefIndex` between collapsed nodes, the weight of an edge is the number
+/// of pointers from one node into the other.
+#[derive(Debug, Clone, Default)]
+pub struct PointerGraph {
+    // keyed by `GraphNode::id`
+    pub nodes: BTreeMap<String, GraphNode>,
+    pub edges: BTreeMap<(String, String), u64>,
+}
+
+impl PointerGraph {
+    /// One node per `MemRange`.
+    pub fn from_mem_ranges(di: &DataInterface, xrefs: &XrefIndex) -> Self {
+        PointerGraph::from_allocations(di, xrefs, &[])
+    }
+
+    /// One node per `(vaddr, size)` allocation (e.g. the lua_Pages found by `LuaPageSearch`),
+    /// pointers from or to addresses outside of them are collapsed per `MemRange`.
+    pub fn from_allocations(
+        di: &DataInterface,
+        xrefs: &XrefIndex,
+        allocations: &[(u64, u64)],
+    ) -> Self {
+        let mut allocs: RangeMap<u64, u64> = RangeMap::new();
+        for (vaddr, size) in allocations.iter() {
+            if *size > 0 {
+                allocs.insert(*vaddr..vaddr.saturating_add(*size), *vaddr);
+            }
+        }
+        let mut graph = PointerGraph::default();
+        for (source, sink) in xrefs.pointers() {
+            let o_from = graph.add_node(di, &allocs, source);
+            let o_to = graph.add_node(di, &allocs, sink);
+            if let (Some(from), Some(to)) = (o_from, o_to) {
+                *graph.edges.entry((from, to)).or_insert(0) += 1;
+            }
+        }
+        info!(
+            "Collapsed {} pointers into {} nodes and {} edges.",
+            xrefs.len(),
+            graph.nodes.len(),
+            graph.edges.len()
+        );
+        graph
+    }
+
+    // the id of the node holding `vaddr`, `None` for addresses outside the memory ranges
+    fn add_node(
+        &mut self,
+        di: &DataInterface,
+        allocs: &RangeMap<u64, u64>,
+        vaddr: u64,
+    ) -> Option<String> {
+        let mr = di.mem_ranges.get_vaddr_range(vaddr)?;
+        let node = match allocs.get_key_value(&vaddr) {
+            Some((range, start)) => GraphNode {
+                kind: NodeKind::Allocation,
+                vaddr: *start,
+                size: range.end - *start,
+                section: mr.name.clone(),
+            },
+            None => GraphNode {
+                kind: NodeKind::MemRange,
+                vaddr: mr.vaddr_start,
+                size: mr.vsize,
+                section: mr.name.clone(),
+            },
+        };
+        let id = node.id();
+        self.nodes.entry(id.clone()).or_insert(node);
+        Some(id)
+    }
+
+    /// Write the graph in Graphviz DOT.
+    pub fn write_dot<W: Write>(&self, writer: &mut W) -> Result<(), SearchError> {
+        writeln!(writer, "digraph pointers {{")?;
+        for node in self.nodes.values() {
+            writeln!(
+                writer,
+                "  \"{}\" [label=\"{}\", vaddr=\"{:08x}\", size={}];",
+                node.id(),
+                escape_dot(&node.label()),
+                node.vaddr,
+                node.size
+            )?;
+        }
+        for ((from, to), count) in self.edges.iter() {
+            writeln!(
+                writer,
+                "  \"{}\" -> \"{}\" [weight={}, label=\"{}\"];",
+                from, to, count, count
+            )?;
+        }
+        writeln!(writer, "}}")?;
+        Ok(())
+    }
+
+    /// Write the graph as GraphML, e.g. for Gephi or Cytoscape.
+    pub fn write_graphml<W: Write>(&self, writer: &mut W) -> Result<(), SearchError> {
+        writeln!(writer, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        writeln!(writer, "<graphml xmlns=\"{}\">", GRAPHML_NS)?;
+        for (id, target, attr_type) in GRAPHML_KEYS.iter() {
+            writeln!(
+                writer,
+                "  <key id=\"{}\" for=\"{}\" attr.name=\"{}\" attr.type=\"{}\"/>",
+                id, target, id, attr_type
+            )?;
+        }
+        writeln!(writer, "  <graph id=\"pointers\" edgedefault=\"directed\">")?;
+        for node in self.nodes.values() {
+            writeln!(writer, "    <node id=\"{}\">", node.id())?;
+            writeln!(
+                writer,
+                "      <data key=\"label\">{}</data>",
+                escape_xml(&node.label())
+            )?;
+            writeln!(
+                writer,
+                "      <data key=\"section\">{}</data>",
+                escape_xml(&node.section)
+            )?;
+            writeln!(
+                writer,
+                "      <data key=\"vaddr\">{:08x}</data>",
+                node.vaddr
+            )?;
+            writeln!(writer, "      <data key=\"size\">{}</data>", node.size)?;
+            writeln!(writer, "    </node>")?;
+        }
+        for ((from, to), count) in self.edges.iter() {
+            writeln!(writer, "    <edge source=\"{}\" target=\"{}\">", from, to)?;
+            writeln!(writer, "      <data key=\"weight\">{}</data>", count)?;
+            writeln!(writer, "    </edge>")?;
+        }
+        writeln!(writer, "  </graph>")?;
+        writeln!(writer, "</graphml>")?;
+        Ok(())
+    }
+
+    pub fn save_dot(&self, path: &Path) -> Result<(), SearchError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_dot(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn save_graphml(&self, path: &Path) -> Result<(), SearchError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_graphml(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+fn escape_dot(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
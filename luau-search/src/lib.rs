@@ -1,5 +1,11 @@
 #![warn(missing_debug_implementations)]
+pub mod classify;
+pub mod error;
+pub mod graph;
 pub mod pointer;
+pub mod pointerpath;
 pub mod regexblock;
+pub mod rtti;
+pub mod scheduler;
 pub mod search;
 pub mod luapage;
\ No newline at end of file
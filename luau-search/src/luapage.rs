@@ -2,21 +2,20 @@ use std::fs::{File};
 use std::path::{PathBuf};
 use std::io::{BufWriter, Write};
 
-use std::collections::{BTreeMap, HashMap, VecDeque};
-use std::error::Error as StdErr;
-use bincode;
-use bincode::config::{Options};
+use std::collections::{BTreeMap, HashMap};
+use crate::error::SearchError;
+use crate::scheduler::{ChunkScheduler, ScanChunk};
+use binread::BinRead;
 use log::{debug, info, error};
 use std::sync::{Arc, RwLock};
-use std::thread;
-use chrono::{Utc, Duration};
 use serde_json::json;
 use serde::ser::{Serialize, Serializer, SerializeMap};
-use serde::Deserialize;
 
 use mem_analysis::memory::{MemRange};
+use mem_analysis::region::RegionFilter;
 
-use mem_analysis::data_interface::{DataInterface, ENDIAN};
+use mem_analysis::data_interface::DataInterface;
+use mem_analysis::error::MemError;
 
 use crate::search::*;
 
@@ -26,24 +25,28 @@ impl Search for LuaPageSearch {
         &mut self,
         _buffer: &[u8],
         _pos: u64,
-    ) -> Result<Option<SearchResult>, Box<dyn StdErr>> {
-        panic!("Need to know base address for buffer");
+    ) -> Result<Option<SearchResult>, SearchError> {
+        return Err(SearchError::unsupported(
+            "Lua page searches need the base address of the buffer",
+        ));
     }
-    fn search_buffer(&mut self, _buffer: &[u8]) -> Result<Vec<Box<SearchResult>>, Box<dyn StdErr>> {
-        panic!("Need to know base address for buffer");
+    fn search_buffer(&mut self, _buffer: &[u8]) -> Result<Vec<Box<SearchResult>>, SearchError> {
+        return Err(SearchError::unsupported(
+            "Lua page searches need the base address of the buffer",
+        ));
     }
     fn search_buffer_with_bases(
         &mut self,
         buffer: &[u8],
         phys_base: u64,
         virt_base: u64,
-    ) -> Result<Vec<Box<SearchResult>>, Box<dyn StdErr>> {
+    ) -> Result<Vec<Box<SearchResult>>, SearchError> {
         return self.perform_search_buffer_with_bases(buffer, phys_base, virt_base);
     }
     fn search_interface(
         &mut self,
         di_arw: Arc<RwLock<Box<DataInterface>>>,
-    ) -> Result<Vec<Box<SearchResult>>, Box<dyn StdErr>> {
+    ) -> Result<Vec<Box<SearchResult>>, SearchError> {
         return self.perform_search_with_interface_mt(di_arw);
     }
     fn search_interface_with_bases(
@@ -51,7 +54,7 @@ impl Search for LuaPageSearch {
         di_arw: Arc<RwLock<Box<DataInterface>>>,
         _phys_base: u64,
         virt_base: u64,
-    ) -> Result<Vec<Box<SearchResult>>, Box<dyn StdErr>> {
+    ) -> Result<Vec<Box<SearchResult>>, SearchError> {
         return self.perform_search_with_vaddr_start(di_arw, virt_base);
     }
 }
@@ -107,7 +110,7 @@ impl Serialize for Comment {
 }
 
 #[repr(C)]
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, BinRead, Clone)]
 pub struct LuaPageX64 {
     pub prev: u64,
     pub next: u64,
@@ -121,7 +124,7 @@ pub struct LuaPageX64 {
 }
 
 #[repr(C)]
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, BinRead, Clone)]
 pub struct LuaPageX32 {
     pub prev: u32,
     pub next: u32,
@@ -134,69 +137,31 @@ pub struct LuaPageX32 {
     pub busy_blocks: i32,
 }
 
-impl LuaPageX32 {
-    // impl LuaPage for LuaPageX32 {
+/// Header of a Luau `lua_Page`, implemented for the 32 and 64-bit layouts so the searches
+/// can follow the pointer width of the dump.
+pub trait LuaPage: BinRead<Args = ()> + Sized {
+    fn get_prev(&self) -> u64;
+    fn get_next(&self) -> u64;
+    fn get_gcolistprev(&self) -> u64;
+    fn get_gcolistnext(&self) -> u64;
+    fn get_page_size(&self) -> i32;
+    fn get_block_size(&self) -> i32;
+    fn get_free_list(&self) -> u64;
+    fn get_free_next(&self) -> i32;
+    fn get_busy_blocks(&self) -> i32;
 
-    fn load(buffer: &[u8], di_arw: Arc<RwLock<Box<DataInterface>>>) -> Option<Self> {
-        if buffer.len() < LuaPageX32::get_x32_size() as usize {
-            return None;
-        }
-        // #FIXME #TODO implement architecture specific deserialization
-        let di = di_arw.read().unwrap();
-        let o_page = match di.vmem_info.endian {
-            ENDIAN::BIG => {
-                let options = bincode::DefaultOptions::new().with_big_endian()
-                    .allow_trailing_bytes()
-                    .with_fixint_encoding()
-                    .with_no_limit();
-                options.deserialize(&buffer)
-            }
-            ENDIAN::LITTLE => {
-                let options = bincode::DefaultOptions::new().with_little_endian()
-                    .allow_trailing_bytes()
-                    .with_fixint_encoding()
-                    .with_no_limit();
-                options.deserialize(&buffer)
-            }
-        };
-        let page = o_page.unwrap();
-        return Some(page);
+    fn get_size() -> u64 {
+        return std::mem::size_of::<Self>() as u64;
     }
 
-    fn get_prev(&self) -> u64 {
-        return self.prev as u64;
-    }
-    fn get_next(&self) -> u64 {
-        return self.next as u64;
-    }
-    fn get_gcolistprev(&self) -> u64 {
-        return self.gcolistprev as u64;
-    }
-    fn get_gcolistnext(&self) -> u64 {
-        return self.gcolistnext as u64;
-    }
-    fn get_page_size(&self) -> i32 {
-        return self.page_size as i32;
-    }
-    fn get_block_size(&self) -> i32 {
-        return self.block_size as i32;
-    }
-    fn get_free_list(&self) -> u64 {
-        return self.free_list as u64;
-    }
-    fn get_free_next(&self) -> i32 {
-        return self.free_next;
-    }
-    fn get_busy_blocks(&self) -> i32 {
-        return self.busy_blocks;
-    }
+    /// Offset of `page_size`, it follows the four list pointers.
+    fn get_page_size_fld_offset() -> u64;
 
-    fn get_x32_size() -> u64 {
-        return std::mem::size_of::<LuaPageX32>() as u64;
-    }
-
-    fn get_x64_size() -> u64 {
-        return std::mem::size_of::<LuaPageX64>() as u64;
+    fn load(buffer: &[u8], di: &DataInterface) -> Option<Self> {
+        if buffer.len() < Self::get_size() as usize {
+            return None;
+        }
+        return di.parse_struct::<Self>(buffer);
     }
 
     fn get_comment(&self, vaddr: &u64, vaddr_base: &u64, paddr: &u64, paddr_base: &u64) -> Box<Comment> {
@@ -225,7 +190,7 @@ impl LuaPageX32 {
             (self.get_next() == 0 || di.is_vaddr_ptr(self.get_next())) &&
             (self.get_gcolistprev() == 0 || di.is_vaddr_ptr(self.get_gcolistprev())) &&
             (self.get_gcolistnext() == 0 || di.is_vaddr_ptr(self.get_gcolistnext())) &&
-            self.busy_blocks >= 0;
+            self.get_busy_blocks() >= 0;
 
         let block_size_check = match o_max_block_size {
             Some(bsz) => self.get_block_size() <= bsz as i32,
@@ -240,35 +205,7 @@ impl LuaPageX32 {
     }
 }
 
-impl LuaPageX64 {
-    // impl LuaPage for LuaPageX32 {
-
-    fn load(buffer: &[u8], di_arw: Arc<RwLock<Box<DataInterface>>>) -> Option<Self> {
-        if buffer.len() < LuaPageX64::get_x64_size() as usize {
-            return None;
-        }
-        // #FIXME #TODO implement architecture specific deserialization
-        let di = di_arw.read().unwrap();
-        let o_page = match di.vmem_info.endian {
-            ENDIAN::BIG => {
-                let options = bincode::DefaultOptions::new().with_big_endian()
-                    .allow_trailing_bytes()
-                    .with_fixint_encoding()
-                    .with_no_limit();
-                options.deserialize(&buffer)
-            }
-            ENDIAN::LITTLE => {
-                let options = bincode::DefaultOptions::new().with_little_endian()
-                    .allow_trailing_bytes()
-                    .with_fixint_encoding()
-                    .with_no_limit();
-                options.deserialize(&buffer)
-            }
-        };
-        let page = o_page.unwrap();
-        return Some(page);
-    }
-
+impl LuaPage for LuaPageX32 {
     fn get_prev(&self) -> u64 {
         return self.prev as u64;
     }
@@ -282,10 +219,10 @@ impl LuaPageX64 {
         return self.gcolistnext as u64;
     }
     fn get_page_size(&self) -> i32 {
-        return self.page_size as i32;
+        return self.page_size;
     }
     fn get_block_size(&self) -> i32 {
-        return self.block_size as i32;
+        return self.block_size;
     }
     fn get_free_list(&self) -> u64 {
         return self.free_list as u64;
@@ -296,52 +233,41 @@ impl LuaPageX64 {
     fn get_busy_blocks(&self) -> i32 {
         return self.busy_blocks;
     }
+    fn get_page_size_fld_offset() -> u64 {
+        return 4 * 4;
+    }
+}
 
-    fn get_x32_size() -> u64 {
-        return std::mem::size_of::<LuaPageX64>() as u64;
+impl LuaPage for LuaPageX64 {
+    fn get_prev(&self) -> u64 {
+        return self.prev;
     }
-    fn get_x64_size() -> u64 {
-        return std::mem::size_of::<LuaPageX64>() as u64;
+    fn get_next(&self) -> u64 {
+        return self.next;
     }
-
-    fn get_comment(&self, vaddr: &u64, vaddr_base: &u64, paddr: &u64, paddr_base: &u64) -> Box<Comment> {
-        return Box::new(Comment {
-            search: "lua_page".to_string(),
-            paddr: *paddr,
-            vaddr: *vaddr,
-            paddr_base: *paddr_base,
-            vaddr_base: *vaddr_base,
-            prev: self.get_prev(),
-            next: self.get_next(),
-            gcolistprev: self.get_gcolistprev(),
-            gcolistnext: self.get_gcolistnext(),
-            freelist: self.get_free_list(),
-
-            block_size: self.get_block_size() as u32,
-            page_size: self.get_page_size() as u32,
-            free_next: self.get_free_next(),
-            busy_blocks: self.get_busy_blocks() as u32,
-        });
+    fn get_gcolistprev(&self) -> u64 {
+        return self.gcolistprev;
     }
-
-    fn is_valid_header(&self, di: &DataInterface, o_max_block_size: Option<u32>, o_page_size: Option<u32>) -> bool {
-
-        let basic_constraints = (self.get_prev() == 0 || di.is_vaddr_ptr(self.get_prev())) &&
-            (self.get_next() == 0 || di.is_vaddr_ptr(self.get_next())) &&
-            (self.get_gcolistprev() == 0 || di.is_vaddr_ptr(self.get_gcolistprev())) &&
-            (self.get_gcolistnext() == 0 || di.is_vaddr_ptr(self.get_gcolistnext())) &&
-            self.busy_blocks >= 0;
-
-        let block_size_check = match o_max_block_size {
-            Some(bsz) => self.get_block_size() <= bsz as i32,
-            None => true,
-        };
-
-        let page_size_check = match o_page_size {
-            Some(psz) => self.get_page_size() == psz as i32,
-            None => true,
-        };
-        return basic_constraints && block_size_check && page_size_check;
+    fn get_gcolistnext(&self) -> u64 {
+        return self.gcolistnext;
+    }
+    fn get_page_size(&self) -> i32 {
+        return self.page_size;
+    }
+    fn get_block_size(&self) -> i32 {
+        return self.block_size;
+    }
+    fn get_free_list(&self) -> u64 {
+        return self.free_list;
+    }
+    fn get_free_next(&self) -> i32 {
+        return self.free_next;
+    }
+    fn get_busy_blocks(&self) -> i32 {
+        return self.busy_blocks;
+    }
+    fn get_page_size_fld_offset() -> u64 {
+        return 4 * 8;
     }
 }
 
@@ -358,6 +284,10 @@ pub struct LuaPageSearch {
     pub max_block_size: Option<u32>,
     pub shared_comments: Arc<RwLock<Box<BTreeMap<u64, Box<Comment>>>>>,
     pub max_threads: u64,
+    pub region_filter: RegionFilter,
+    /// Runs the threaded searches, started by the first one and restarted when `max_threads`
+    /// changes.
+    pub scheduler: Option<ChunkScheduler>,
 }
 
 pub fn perform_search_with_vaddr_start(
@@ -366,8 +296,42 @@ pub fn perform_search_with_vaddr_start(
     max_block_size: Option<u32>,
     shared_results: Arc<RwLock<Vec<Box<SearchResult>>>>,
     shared_comments: Arc<RwLock<Box<BTreeMap<u64, Box<Comment>>>>>,
-) -> Result<(), Box<dyn StdErr>> {
-    let di = di_arw.read().unwrap();
+) -> Result<(), SearchError> {
+    let di = di_arw.read()?;
+    return perform_search_chunk(&di, &ScanChunk::whole(mr), max_block_size, &shared_results, &shared_comments);
+}
+
+/// Search the offsets `[chunk.start, chunk.end)` of a memory range for lua_Page headers, a
+/// page starting in the chunk is read in full even when it extends into the next one.
+pub fn perform_search_chunk(
+    di: &DataInterface,
+    chunk: &ScanChunk,
+    max_block_size: Option<u32>,
+    shared_results: &RwLock<Vec<Box<SearchResult>>>,
+    shared_comments: &RwLock<Box<BTreeMap<u64, Box<Comment>>>>,
+) -> Result<(), SearchError> {
+    // collected per chunk and merged once, instead of taking the locks for every page
+    let (results, mut comments) = if di.vmem_info.word_sz == 4 {
+        search_chunk_for_pages::<LuaPageX32>(di, chunk, max_block_size)?
+    } else {
+        search_chunk_for_pages::<LuaPageX64>(di, chunk, max_block_size)?
+    };
+    shared_comments.write()?.append(&mut comments);
+    shared_results.write()?.extend(results.into_iter().map(Box::new));
+    Ok(())
+}
+
+// the pages found in a chunk and their comments
+type ChunkPages = (Vec<SearchResult>, BTreeMap<u64, Box<Comment>>);
+
+fn search_chunk_for_pages<P: LuaPage>(
+    di: &DataInterface,
+    chunk: &ScanChunk,
+    max_block_size: Option<u32>,
+) -> Result<ChunkPages, SearchError> {
+    let mr = &chunk.mr;
+    let mut results: Vec<SearchResult> = Vec::new();
+    let mut comments: BTreeMap<u64, Box<Comment>> = BTreeMap::new();
     let svaddr: u64 = mr.vaddr_start;
     let mut found = 0 as u64;
     let alignment: u64 = if di.vmem_info.alignment == 0 {
@@ -381,177 +345,148 @@ pub fn perform_search_with_vaddr_start(
         di.vmem_info.word_sz.into()
     };
 
-    let mut pos: u64 = 0;
-
-    let o_vaddr_base = di.get_vaddr_base(&svaddr);
-    if o_vaddr_base.is_none() {
-        return Ok(());
-    }
-
-    let phys_base = di.get_paddr_base_from_vaddr(&svaddr).unwrap();
-
-    let o_end = di.get_vaddr_end(svaddr);
-    if o_end.is_none() {
-        return Ok(());
-    }
-    let _end = o_end.unwrap();
-    let vaddr_base = o_vaddr_base.unwrap();
+    let mut pos: u64 = chunk.start;
+
+    // the scheduler logs the failed chunk and goes on with the others
+    let (vaddr_base, phys_base) = match (
+        di.get_vaddr_base(&svaddr),
+        di.get_paddr_base_from_vaddr(&svaddr),
+        di.get_vaddr_end(svaddr),
+    ) {
+        (Some(vaddr_base), Some(phys_base), Some(_end)) => (vaddr_base, phys_base),
+        _ => {
+            let size = chunk.end - chunk.start;
+            return Err(MemError::OutOfRange { addr: svaddr + chunk.start, size }.into());
+        }
+    };
     let virt_base = if svaddr != vaddr_base {
         svaddr % alignment
     } else {
         svaddr
     };
-    // there may be a 0-sized buffer in which case there is nothing to return
-    // so we need to accomodate the empty array gracefully here
-    let o_vaddr_buf = di.shared_buffer_vaddr(virt_base);
-    if o_vaddr_buf.is_none() {
-        return Ok(());
-    }
-    let vaddr_buf = o_vaddr_buf.unwrap();
     let hard_coded_page_value = 0x3fe8 as u64;
-    // let page_size_fld_offset = 4 * 8 as u64;
-    // using 8 byte word size vs 4
-    let page_size_fld_offset = 4 * 8 as u64;
-    while pos + incr - 1 < vaddr_buf.len() as u64 {
+    let page_size_fld_offset = P::get_page_size_fld_offset();
+    // only the chunk and the headers of the pages whose page_size field is in it, the header
+    // starts before the field and may end in the next chunk
+    let buf_start = chunk.start.saturating_sub(page_size_fld_offset);
+    let buf_size = chunk.end + P::get_size() - buf_start;
+    let o_chunk_buf = di.shared_buffer_vaddr_at(virt_base + buf_start, buf_size);
+    let chunk_buf = match o_chunk_buf {
+        Some(chunk_buf) => chunk_buf,
+        None => {
+            debug!(
+                "Skipping the lua page search of {} at offsets {:08x}-{:08x}, there is no data.",
+                mr.name,
+                chunk.start,
+                chunk.end
+            );
+            return Ok((results, comments));
+        }
+    };
+    let buf_end = buf_start + chunk_buf.len() as u64;
+    let chunk_end = chunk.end.min(buf_end);
+    // where a scan of the whole range would stop, a short slice ends the readable part
+    let range_end = if (chunk_buf.len() as u64) < buf_size {
+        buf_end
+    } else {
+        mr.vsize.min(mr.size)
+    };
+    while pos < chunk_end && pos + incr - 1 < buf_end {
         let vaddr = pos + virt_base;
         let paddr = pos + phys_base;
 
-        if pos + hard_coded_page_value > vaddr_buf.len() as u64 {
+        if pos + hard_coded_page_value > range_end {
             break;
         }
 
-        let value = di.read_u32(&vaddr_buf[pos as usize..], None).unwrap();
+        let value = di.read_u32(&chunk_buf[(pos - buf_start) as usize..], None).unwrap_or(0);
         if value as u64 != hard_coded_page_value {
             pos += incr;
             continue;
         }
+        if pos < page_size_fld_offset {
+            pos += incr;
+            continue;
+        }
         let lp_start_pos = pos - page_size_fld_offset;
-        let o_lp = LuaPageX64::load(&vaddr_buf[lp_start_pos as usize..], di_arw.clone());
+        let o_lp = P::load(&chunk_buf[(lp_start_pos - buf_start) as usize..], di);
         if o_lp.is_none() {
             pos += incr;
             continue;
         }
         let lp = o_lp.unwrap();
-        if !lp.is_valid_header(&di, max_block_size, Some(hard_coded_page_value as u32)) {
+        if !lp.is_valid_header(di, max_block_size, Some(hard_coded_page_value as u32)) {
             pos += incr;
             continue;
         }
         let lp_vaddr = vaddr - page_size_fld_offset;
         let lp_paddr = paddr - page_size_fld_offset;
 
-        let comment = lp.get_comment(&lp_vaddr, &lp_paddr, &virt_base, &phys_base);
-        let mut sr = Box::new(SearchResult::default());
+        let comment = lp.get_comment(&lp_vaddr, &virt_base, &lp_paddr, &phys_base);
+        let mut sr = SearchResult::default();
         sr.boundary_offset = lp_paddr as u64;
-        sr.size = LuaPageX64::get_x64_size() + lp.get_page_size() as u64;
+        sr.size = P::get_size() + lp.get_page_size() as u64;
         sr.vaddr = lp_vaddr;
         sr.paddr = lp_paddr;
         sr.digest = "".to_string();
         sr.section_name = mr.name.clone();
-        shared_comments.write().unwrap().insert(lp_vaddr, comment);
-        shared_results.write().unwrap().push(sr);
+        comments.insert(lp_vaddr, comment);
+        results.push(sr);
         found += 1;
 
         pos += incr;
     }
-    info!(
-            "Found {} results in perform_search_buffer_with_bases: paddr: {:08x} vaddr: {:08x} name: {}, total comments: {}, total results: {}",
+    debug!(
+            "Found {} results in perform_search_chunk: paddr: {:08x} vaddr: {:08x} name: {} offsets: {:08x}-{:08x}",
             found,
             phys_base,
             virt_base,
             mr.name,
-            shared_comments.read().unwrap().len(),
-            shared_results.read().unwrap().len()
+            chunk.start,
+            chunk_end
         );
-    return Ok(());
+    Ok((results, comments))
 }
 
 impl LuaPageSearch {
     pub fn perform_search_with_interface_mt(
         &mut self,
         di_arw: Arc<RwLock<Box<DataInterface>>>,
-    ) -> Result<Vec<Box<SearchResult>>, Box<dyn StdErr>> {
-        let di = di_arw.read().unwrap();
-        let mut thread_handles_ac: VecDeque<thread::JoinHandle<()>> = VecDeque::new();
+    ) -> Result<Vec<Box<SearchResult>>, SearchError> {
+        let di = di_arw.read()?;
         let shared_results: Arc<RwLock<Vec<Box<SearchResult>>>> = Arc::new(RwLock::new(Vec::new()));
         let shared_comments = Arc::clone(&self.shared_comments);
 
-        let v_mrs = di.mem_ranges.get_mem_ranges();
-        let mut wv_mrs = Vec::new();
-        for mr in v_mrs.iter() {
-            if mr.perm.find("w").is_some() {
-                wv_mrs.push(mr.clone());
+        let wv_mrs = di.mem_ranges.get_filtered_mem_ranges(&self.region_filter);
+        let incr = (di.vmem_info.word_sz as u64).max(1);
+        let max_block_size = self.max_block_size;
+        let scheduler = self.scheduler();
+        scheduler.run("lua page", &wv_mrs, incr, |chunk| {
+            perform_search_chunk(&di, chunk, max_block_size, &shared_results, &shared_comments)
+        })?;
+
+        let lshared_results = match Arc::try_unwrap(shared_results) {
+            Ok(lock) => lock,
+            Err(_) => {
+                let msg = "the results are still shared with a search thread".to_string();
+                return Err(SearchError::Thread(msg));
             }
-        }
-        let max_threads = self.max_threads.clone() as usize;
-
-        for mr in wv_mrs.iter() {
-            let bsr = Arc::clone(&shared_results);
-            let bsc = Arc::clone(&shared_comments);
-            let bmr = mr.clone();
-            let bs = Arc::new(self.clone());
-
-            let t = thread::spawn(move || {
-                let bbs = Arc::clone(&bs);
-                let _ = perform_search_with_vaddr_start(bbs.data_interface.clone(), &bmr, bbs.max_block_size.clone(), bsr.clone(), bsc.clone());
-            });
-            thread_handles_ac.push_back(t);
-            debug!(
-            "Searching Memory Range: {} of {} for pointers from starting at vaddr {:08x} and paddr {:08x}.",
-            mr.name, mr.vsize, mr.vaddr_start, mr.paddr_start);
-            // this loop structure is used to relieve memory pressure and ensure too many threads
-            // are not created at once.  I had a hard time using the thread pool to help men manage
-            // this
-            while thread_handles_ac.len() > max_threads {
-                let mut dt_enter = Utc::now();
-                let dt_exit = dt_enter.checked_add_signed(Duration::milliseconds(500)).unwrap();
-                // Join as many threads as we can in 500 ms
-                while dt_enter < dt_exit && thread_handles_ac.len() > 0 {
-                    let at = thread_handles_ac.pop_front().unwrap();
-                    debug!("joining the threas.");
-                    at.join();
-                    debug!("joining the threas.");
-                    dt_enter = Utc::now();
-                }
-                // want to push down the number of running threads further. Join as many threads as
-                // we can in the next 500 ms (not a sophisticated strategy)
-                if thread_handles_ac.len() > 0 && thread_handles_ac.len() > max_threads - 5 {
-                    let mut dt_enter = Utc::now();
-                    let dt_exit = dt_enter.checked_add_signed(Duration::milliseconds(500)).unwrap();
-                    while dt_enter < dt_exit && thread_handles_ac.len() > 0 {
-                        let at = thread_handles_ac.pop_front().unwrap();
-                        at.join();
-                        dt_enter = Utc::now();
-                    }
-                }
-            }
-        }
-
-        thread_handles_ac
-            .into_iter()
-            .for_each(|th| th.join().expect("can't join thread"));
-
-        let lshared_results = Arc::try_unwrap(shared_results).expect("lock still has owners");
-        let search_results = lshared_results.into_inner().expect("Mutex cannot be locked");
+        };
+        let search_results = lshared_results.into_inner()?;
         info!("Found {} results.", search_results.len());
-        info!("Found {} shared_comments.", shared_comments.read().unwrap().len());
-        info!("Found {} self.shared_comments.", shared_comments.read().unwrap().len());
+        info!("Found {} shared_comments.", shared_comments.read()?.len());
+        info!("Found {} self.shared_comments.", shared_comments.read()?.len());
         return Ok(search_results);
     }
 
     pub fn perform_search_with_interface(
         &mut self,
         di_arw: Arc<RwLock<Box<DataInterface>>>,
-    ) -> Result<Vec<Box<SearchResult>>, Box<dyn StdErr>> {
+    ) -> Result<Vec<Box<SearchResult>>, SearchError> {
         let mut search_results: Vec<Box<SearchResult>> = Vec::new();
-        let di = di_arw.read().unwrap();
+        let di = di_arw.read()?;
 
-        let v_mrs = di.mem_ranges.get_mem_ranges();
-        let mut wv_mrs = Vec::new();
-        for mr in v_mrs.iter() {
-            if mr.perm.find("w").is_some() {
-                wv_mrs.push(mr.clone());
-            }
-        }
+        let wv_mrs = di.mem_ranges.get_filtered_mem_ranges(&self.region_filter);
 
         for mr in wv_mrs.iter() {
             debug!(
@@ -564,7 +499,7 @@ impl LuaPageSearch {
             let _size: u64 = mr.size;
 
             let r_results = self.perform_search_with_vaddr_start(di_arw.clone(), vaddr);
-            let mut results: Vec<Box<SearchResult>> = r_results.unwrap();
+            let mut results: Vec<Box<SearchResult>> = r_results?;
             debug!(
                 "Lua Pages Found {} results in {}, search_results.len() = {}.",
                 results.len(),
@@ -583,9 +518,23 @@ impl LuaPageSearch {
         &mut self,
         di_arw: Arc<RwLock<Box<DataInterface>>>,
         svaddr: u64,
-    ) -> Result<Vec<Box<SearchResult>>, Box<dyn StdErr>> {
-        let di = di_arw.read().unwrap();
-        let mut search_results: Vec<Box<SearchResult>> = Vec::new();
+    ) -> Result<Vec<Box<SearchResult>>, SearchError> {
+        let word_sz = di_arw.read()?.vmem_info.word_sz;
+        let results = if word_sz == 4 {
+            self.search_vaddr_start_for_pages::<LuaPageX32>(di_arw, svaddr)?
+        } else {
+            self.search_vaddr_start_for_pages::<LuaPageX64>(di_arw, svaddr)?
+        };
+        return Ok(results.into_iter().map(Box::new).collect());
+    }
+
+    fn search_vaddr_start_for_pages<P: LuaPage>(
+        &mut self,
+        di_arw: Arc<RwLock<Box<DataInterface>>>,
+        svaddr: u64,
+    ) -> Result<Vec<SearchResult>, SearchError> {
+        let di = di_arw.read()?;
+        let mut search_results: Vec<SearchResult> = Vec::new();
         let alignment: u64 = if di.vmem_info.alignment == 0 {
             1
         } else {
@@ -605,7 +554,10 @@ impl LuaPageSearch {
             return Ok(search_results);
         }
 
-        let phys_base = di.get_paddr_base_from_vaddr(&svaddr).unwrap();
+        let phys_base = match di.get_paddr_base_from_vaddr(&svaddr) {
+            Some(phys_base) => phys_base,
+            None => return Ok(search_results),
+        };
 
         let o_end = di.get_vaddr_end(svaddr);
         if o_end.is_none() {
@@ -626,7 +578,7 @@ impl LuaPageSearch {
         }
         let vaddr_buf = o_vaddr_buf.unwrap();
         let hard_coded_page_value = 0x3fe8 as u64;
-        let page_size_fld_offset = 16 as u64;
+        let page_size_fld_offset = P::get_page_size_fld_offset();
         while pos + incr - 1 < vaddr_buf.len() as u64 {
             let vaddr = pos + virt_base;
             let paddr = pos + phys_base;
@@ -636,13 +588,17 @@ impl LuaPageSearch {
                 break;
             }
 
-            let value = di.read_u32(&vaddr_buf[pos as usize..], None).unwrap();
+            let value = di.read_u32(&vaddr_buf[pos as usize..], None).unwrap_or(0);
             if value as u64 != hard_coded_page_value {
                 pos += incr;
                 continue;
             }
+            if pos < page_size_fld_offset {
+                pos += incr;
+                continue;
+            }
             let lp_start_pos = pos - page_size_fld_offset;
-            let o_lp = LuaPageX64::load(&vaddr_buf[lp_start_pos as usize..], di_arw.clone());
+            let o_lp = P::load(&vaddr_buf[lp_start_pos as usize..], &di);
             if o_lp.is_none() {
                 pos += incr;
                 continue;
@@ -655,11 +611,11 @@ impl LuaPageSearch {
             let lp_vaddr = vaddr - page_size_fld_offset;
             let lp_paddr = paddr - page_size_fld_offset;
 
-            let comment = lp.get_comment(&lp_vaddr, &lp_paddr, &virt_base, &phys_base);
+            let comment = lp.get_comment(&lp_vaddr, &virt_base, &lp_paddr, &phys_base);
 
-            let mut sr = Box::new(SearchResult::default());
+            let mut sr = SearchResult::default();
             sr.boundary_offset = lp_paddr as u64;
-            sr.size = LuaPageX64::get_x64_size() + lp.get_page_size() as u64;
+            sr.size = P::get_size() + lp.get_page_size() as u64;
             sr.vaddr = lp_vaddr;
             sr.paddr = lp_paddr;
             sr.digest = "".to_string();
@@ -680,7 +636,7 @@ impl LuaPageSearch {
             phys_base,
             virt_base
         );
-        return Ok(search_results.clone());
+        return Ok(search_results);
     }
 
     pub fn perform_search_buffer_with_bases(
@@ -688,9 +644,25 @@ impl LuaPageSearch {
         buffer: &[u8],
         phys_base: u64,
         virt_base: u64,
-    ) -> Result<Vec<Box<SearchResult>>, Box<dyn StdErr>> {
-        let mut search_results: Vec<Box<SearchResult>> = Vec::new();
-        let di = self.data_interface.read().unwrap();
+    ) -> Result<Vec<Box<SearchResult>>, SearchError> {
+        let word_sz = self.data_interface.read()?.vmem_info.word_sz;
+        let results = if word_sz == 4 {
+            self.search_buffer_for_pages::<LuaPageX32>(buffer, phys_base, virt_base)?
+        } else {
+            self.search_buffer_for_pages::<LuaPageX64>(buffer, phys_base, virt_base)?
+        };
+        return Ok(results.into_iter().map(Box::new).collect());
+    }
+
+    fn search_buffer_for_pages<P: LuaPage>(
+        &mut self,
+        buffer: &[u8],
+        phys_base: u64,
+        virt_base: u64,
+    ) -> Result<Vec<SearchResult>, SearchError> {
+        let mut search_results: Vec<SearchResult> = Vec::new();
+        let data_interface = self.data_interface.clone();
+        let di = data_interface.read()?;
 
         let incr = if di.vmem_info.word_sz == 0 {
             1
@@ -701,12 +673,11 @@ impl LuaPageSearch {
         let end: u64 = buffer.len() as u64;
 
         let _hard_coded_page_value = 0x3fe8 as u64;
-        let page_size_fld_offset = 16 as u64;
         while pos < end {
             let vaddr = pos + virt_base;
             let paddr = pos + phys_base;
             // debug!("Reading buffer at {:08x}", pos );
-            let o_lp = LuaPageX64::load(&buffer[pos as usize..], self.data_interface.clone());
+            let o_lp = P::load(&buffer[pos as usize..], &di);
             if o_lp.is_none() {
                 pos += incr;
                 continue;
@@ -718,14 +689,15 @@ impl LuaPageSearch {
                 continue;
             }
 
-            let lp_vaddr = vaddr - page_size_fld_offset;
-            let lp_paddr = paddr - page_size_fld_offset;
+            // the header was decoded at `pos`, not relative to the page_size field
+            let lp_vaddr = vaddr;
+            let lp_paddr = paddr;
 
-            let comment = lp.get_comment(&lp_vaddr, &lp_paddr, &virt_base, &phys_base);
+            let comment = lp.get_comment(&lp_vaddr, &virt_base, &lp_paddr, &phys_base);
 
-            let mut sr = Box::new(SearchResult::default());
+            let mut sr = SearchResult::default();
             sr.boundary_offset = lp_paddr as u64;
-            sr.size = LuaPageX64::get_x64_size() + lp.get_page_size() as u64;
+            sr.size = P::get_size() + lp.get_page_size() as u64;
             sr.vaddr = lp_vaddr;
             sr.paddr = lp_paddr;
             // sr.section_name = match di.get_vaddr_section_name(vaddr) {
@@ -751,7 +723,7 @@ impl LuaPageSearch {
             virt_base
         );
 
-        return Ok(search_results.clone());
+        return Ok(search_results);
     }
 
     pub fn new(start: Option<u64>, stop: Option<u64>, data_interface: Arc<RwLock<Box<DataInterface>>>, max_block_size: Option<u32>, page_size: Option<u32>) -> Self {
@@ -765,6 +737,21 @@ impl LuaPageSearch {
             page_size: page_size,
             max_threads: 30,
             shared_comments: Arc::new(RwLock::new(Box::new(BTreeMap::new()))),
+            region_filter: RegionFilter::writable(),
+            scheduler: None,
+        }
+    }
+
+    // a scheduler with `max_threads` threads, its pool is kept for the next searches
+    fn scheduler(&mut self) -> ChunkScheduler {
+        let num_threads = self.max_threads as usize;
+        match &self.scheduler {
+            Some(scheduler) if scheduler.num_threads() == num_threads => scheduler.clone(),
+            _ => {
+                let scheduler = ChunkScheduler::new(num_threads);
+                self.scheduler = Some(scheduler.clone());
+                scheduler
+            }
         }
     }
 
@@ -776,7 +763,7 @@ impl LuaPageSearch {
         return comments;
     }
 
-    pub fn write_comments(&self, output_filename: PathBuf ) -> () {
+    pub fn write_comments(&self, output_filename: PathBuf) -> Result<(), SearchError> {
         let o_writer = File::create(&output_filename);
         let mut writer = match o_writer {
             Ok(file) => BufWriter::new(file),
@@ -787,12 +774,12 @@ impl LuaPageSearch {
                     err
                 );
                 error!("{}", msg);
-                panic!("{}", msg);
+                return Err(err.into());
             }
         };
-        for (_, c) in self.shared_comments.read().unwrap().iter() {
+        for (_, c) in self.shared_comments.read()?.iter() {
             match writeln!(writer, "{}", json!(c).to_string()) {
-                Ok(_) => {writer.flush().unwrap();}
+                Ok(_) => {writer.flush()?;}
                 Err(err) => {
                     let msg = format!(
                         "Failed to write data to: {}. {} ",
@@ -800,9 +787,10 @@ impl LuaPageSearch {
                         err
                     );
                     error!("{}", msg);
-                    panic!("{}", msg);
+                    return Err(err.into());
                 }
             };
         }
+        return Ok(());
     }
 }
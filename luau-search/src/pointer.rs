@@ -3,19 +3,22 @@ use std::fs::{File};
 use std::path::{PathBuf};
 use std::io::{BufWriter, Write};
 
-use std::collections::{BTreeMap, HashMap, VecDeque};
-use std::error::Error as StdErr;
+use std::collections::{BTreeMap, HashMap};
+use crate::classify::{AddressClass, Classifier, PointerFilter};
+use crate::error::SearchError;
+use crate::scheduler::{ChunkScheduler, ScanChunk};
 use byteorder::{BigEndian, ByteOrder, LittleEndian};
 use log::{debug, info, error};
 use std::sync::{Arc, RwLock};
-use chrono::{Utc, Duration};
 
 use serde_json::json;
 use serde::ser::{Serialize, Serializer, SerializeMap};
 
-use std::thread;
 use mem_analysis::memory::{MemRange};
+use mem_analysis::region::RegionFilter;
 use mem_analysis::data_interface::{DataInterface, ReadValue, ENDIAN};
+use mem_analysis::error::MemError;
+use mem_analysis::pointers::XrefIndex;
 
 use crate::search::*;
 
@@ -24,24 +27,28 @@ impl Search for PointerSearch {
         &mut self,
         _buffer: &[u8],
         _pos: u64,
-    ) -> Result<Option<SearchResult>, Box<dyn StdErr>> {
-        panic!("Need to know base address for buffer");
+    ) -> Result<Option<SearchResult>, SearchError> {
+        return Err(SearchError::unsupported(
+            "Pointer searches need the base address of the buffer",
+        ));
     }
-    fn search_buffer(&mut self, _buffer: &[u8]) -> Result<Vec<Box<SearchResult>>, Box<dyn StdErr>> {
-        panic!("Need to know base address for buffer");
+    fn search_buffer(&mut self, _buffer: &[u8]) -> Result<Vec<Box<SearchResult>>, SearchError> {
+        return Err(SearchError::unsupported(
+            "Pointer searches need the base address of the buffer",
+        ));
     }
     fn search_buffer_with_bases(
         &mut self,
         buffer: &[u8],
         phys_base: u64,
         virt_base: u64,
-    ) -> Result<Vec<Box<SearchResult>>, Box<dyn StdErr>> {
+    ) -> Result<Vec<Box<SearchResult>>, SearchError> {
         return self.perform_search_buffer_with_bases(buffer, phys_base, virt_base);
     }
     fn search_interface(
         &mut self,
         di: Arc<RwLock<Box<DataInterface>>>,
-    ) -> Result<Vec<Box<SearchResult>>, Box<dyn StdErr>> {
+    ) -> Result<Vec<Box<SearchResult>>, SearchError> {
         return self.perform_search_with_interface_mt(di);
     }
     fn search_interface_with_bases(
@@ -49,7 +56,7 @@ impl Search for PointerSearch {
         di: Arc<RwLock<Box<DataInterface>>>,
         _phys_base: u64,
         virt_base: u64,
-    ) -> Result<Vec<Box<SearchResult>>, Box<dyn StdErr>> {
+    ) -> Result<Vec<Box<SearchResult>>, SearchError> {
         return self.perform_search_with_vaddr_start(di, virt_base);
     }
 }
@@ -67,6 +74,9 @@ pub struct Comment {
     pub sink_vaddr_base: u64,
     pub sink_paddr_base: u64,
     pub sink_value: Option<u64>,
+    /// Filled in by `PointerSearch::classify_comments`.
+    pub source_class: Option<AddressClass>,
+    pub sink_class: Option<AddressClass>,
 }
 
 impl Serialize for Comment {
@@ -84,10 +94,19 @@ impl Serialize for Comment {
         map.serialize_entry("sink_paddr", &format!("{:08x}", self.sink_paddr))?;
         map.serialize_entry("sink_vaddr_base", &format!("{:08x}", self.sink_vaddr_base))?;
         map.serialize_entry("sink_paddr_base", &format!("{:08x}", self.sink_paddr_base))?;
-        if self.sink_value.is_some() {
-            map.serialize_entry("sink_value", &format!("{:08x}", self.sink_value.unwrap() ))?;
-        } else {
-            map.serialize_entry("sink_value", "null")?;
+        match self.sink_value {
+            Some(value) => map.serialize_entry("sink_value", &format!("{:08x}", value))?,
+            None => map.serialize_entry("sink_value", "null")?,
+        }
+        if let Some(class) = &self.source_class {
+            map.serialize_entry("source_class", &class.class)?;
+            map.serialize_entry("source_section", &class.section)?;
+            map.serialize_entry("source_perm", &class.perm.to_string())?;
+        }
+        if let Some(class) = &self.sink_class {
+            map.serialize_entry("sink_class", &class.class)?;
+            map.serialize_entry("sink_section", &class.section)?;
+            map.serialize_entry("sink_perm", &class.perm.to_string())?;
         }
 
         map.end()
@@ -97,10 +116,10 @@ impl Serialize for Comment {
 impl Display for Comment {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
 
-        let mut sv = "null".to_string();
-        if self.sink_value.is_some() {
-            sv = format!("{:08x}", self.sink_value.unwrap());
-        }
+        let sv = match self.sink_value {
+            Some(value) => format!("{:08x}", value),
+            None => "null".to_string(),
+        };
 
         write!(
             f,
@@ -130,6 +149,12 @@ pub struct PointerSearch {
     pub comments : Box<BTreeMap<u64, Box<Comment>>>,
     pub shared_comments : Arc<RwLock<Box<BTreeMap<u64, Box<Comment>>>>>,
     pub max_threads: u64,
+    pub region_filter: RegionFilter,
+    /// Every pointer found by the searches, shared with the search threads.
+    pub xrefs: Arc<RwLock<XrefIndex>>,
+    /// Runs the threaded searches, started by the first one and restarted when `max_threads`
+    /// changes.
+    pub scheduler: Option<ChunkScheduler>,
 }
 
 pub fn perform_search_with_vaddr_start(
@@ -137,8 +162,42 @@ pub fn perform_search_with_vaddr_start(
     mr: &Box<MemRange>,
     shared_results : Arc<RwLock<Vec<Box<SearchResult>>>>,
     shared_comments: Arc<RwLock<Box<BTreeMap<u64, Box<Comment>>>>>,
-) -> Result<(), Box<dyn StdErr>> {
-    let di = di_arw.read().unwrap();
+    shared_xrefs: Arc<RwLock<XrefIndex>>,
+    shared_src_to_sinks: Arc<RwLock<HashMap<u64, u64>>>,
+    shared_sink_values: Arc<RwLock<HashMap<u64, Option<u64>>>>,
+) -> Result<(), SearchError> {
+    let di = di_arw.read()?;
+    return perform_search_chunk(
+        &di,
+        &ScanChunk::whole(mr),
+        &shared_results,
+        &shared_comments,
+        &shared_xrefs,
+        &shared_src_to_sinks,
+        &shared_sink_values,
+    );
+}
+
+/// Search the offsets `[chunk.start, chunk.end)` of a memory range for pointers, the word at
+/// the last offset may extend into the next chunk.  Fills the same indexes as
+/// `PointerSearch::perform_search_with_vaddr_start`: the source -> sink pointers and, when the
+/// sink holds a pointer as well, sink -> value.
+pub fn perform_search_chunk(
+    di: &DataInterface,
+    chunk: &ScanChunk,
+    shared_results : &RwLock<Vec<Box<SearchResult>>>,
+    shared_comments: &RwLock<Box<BTreeMap<u64, Box<Comment>>>>,
+    shared_xrefs: &RwLock<XrefIndex>,
+    shared_src_to_sinks: &RwLock<HashMap<u64, u64>>,
+    shared_sink_values: &RwLock<HashMap<u64, Option<u64>>>,
+) -> Result<(), SearchError> {
+    let mr = &chunk.mr;
+    // collected per chunk and merged once, instead of taking the locks for every pointer
+    let mut xrefs = XrefIndex::new();
+    let mut src_to_sinks: HashMap<u64, u64> = HashMap::new();
+    let mut sink_values: HashMap<u64, Option<u64>> = HashMap::new();
+    let mut results: Vec<Box<SearchResult>> = Vec::new();
+    let mut comments: BTreeMap<u64, Box<Comment>> = BTreeMap::new();
     let svaddr: u64 = mr.vaddr_start;
     let mut found = 0 as u64;
     let alignment: u64 = if di.vmem_info.alignment == 0 {
@@ -151,38 +210,47 @@ pub fn perform_search_with_vaddr_start(
     } else {
         di.vmem_info.word_sz.into()
     };
-    let page_mask = di.vmem_info.page_mask;
 
-    let mut pos: u64 = 0;
-
-    let o_vaddr_base = di.get_vaddr_base(&svaddr);
-    if o_vaddr_base.is_none() {
-        return Ok(());
-    }
-
-    let phys_base = di.get_paddr_base_from_vaddr(&svaddr).unwrap();
-
-    let o_end = di.get_vaddr_end(svaddr);
-    if o_end.is_none() {
-        return Ok(());
-    }
-    let _end = o_end.unwrap();
-    let vaddr_base = o_vaddr_base.unwrap();
+    let mut pos: u64 = chunk.start;
+
+    // the scheduler logs the failed chunk and goes on with the others
+    let (vaddr_base, phys_base) = match (
+        di.get_vaddr_base(&svaddr),
+        di.get_paddr_base_from_vaddr(&svaddr),
+        di.get_vaddr_end(svaddr),
+    ) {
+        (Some(vaddr_base), Some(phys_base), Some(_end)) => (vaddr_base, phys_base),
+        _ => {
+            let size = chunk.end - chunk.start;
+            return Err(MemError::OutOfRange { addr: svaddr + chunk.start, size }.into());
+        }
+    };
     let virt_base = if svaddr != vaddr_base {
         svaddr % alignment
     } else {
         svaddr
     };
-    let o_vaddr_buf = di.shared_buffer_vaddr(virt_base);
-    if o_vaddr_buf.is_none() {
-        return Ok(());
-    }
-    let vaddr_buf = o_vaddr_buf.unwrap();
+    // only the chunk and the rest of the word at its last offset, not the whole range
+    let o_chunk_buf = di.shared_buffer_vaddr_at(virt_base + chunk.start, chunk.end - chunk.start + incr - 1);
+    let chunk_buf = match o_chunk_buf {
+        Some(chunk_buf) => chunk_buf,
+        None => {
+            debug!(
+                "Skipping the pointer search of {} at offsets {:08x}-{:08x}, there is no data.",
+                mr.name,
+                chunk.start,
+                chunk.end
+            );
+            return Ok(());
+        }
+    };
+    let buf_end = chunk.start + chunk_buf.len() as u64;
+    let chunk_end = chunk.end.min(buf_end);
     let endian = &di.vmem_info.endian;
     let read_value = |buffer: &[u8]| -> u64 {
         match endian {
             ENDIAN::BIG => match incr {
-                1 => <u8 as TryInto<u64>>::try_into(buffer[0]).unwrap() as u64,
+                1 => buffer[0] as u64,
                 2 => BigEndian::read_u16(buffer) as u64,
                 4 => BigEndian::read_u32(buffer) as u64,
                 8 => BigEndian::read_u64(buffer) as u64,
@@ -200,26 +268,44 @@ pub fn perform_search_with_vaddr_start(
         }
     };
 
-    while pos + incr - 1 < vaddr_buf.len() as u64 {
+    while pos < chunk_end && pos + incr - 1 < buf_end {
         let vaddr = pos + virt_base;
         let paddr = pos + phys_base;
-        let sink = read_value(&vaddr_buf[pos as usize..]);
-        let sink_page = sink & page_mask;
-        let lookup_has_page = di.vmem_info.ptr_lookup.contains_key(&sink_page);
-
-        if lookup_has_page {
-            let sink_paddr = di.convert_vaddr_to_paddr(&sink).unwrap();
-            let sink_paddr_base = di.get_paddr_base_from_vaddr(&sink).unwrap();
-            let sink_vaddr_base = di.get_vaddr_base_from_vaddr(&sink).unwrap();
+        let sink = read_value(&chunk_buf[(pos - chunk.start) as usize..]);
+        let lookup_has_page = di.vmem_info.ptr_lookup.contains(sink);
+
+        let has_alignment = sink % alignment == 0;
+        if has_alignment && lookup_has_page {
+            let (sink_paddr, sink_paddr_base, sink_vaddr_base) = match (
+                di.convert_vaddr_to_paddr(&sink),
+                di.get_paddr_base_from_vaddr(&sink),
+                di.get_vaddr_base_from_vaddr(&sink),
+            ) {
+                (Some(paddr), Some(paddr_base), Some(vaddr_base)) => {
+                    (paddr, paddr_base, vaddr_base)
+                }
+                _ => {
+                    pos += incr;
+                    continue;
+                }
+            };
 
-            let _bal = di.vmem_info.ptr_lookup.get(&sink_page);
+            src_to_sinks.insert(vaddr, sink);
+            sink_values.insert(vaddr, Some(sink));
+            xrefs.add(vaddr, sink);
             let o_ptr_value = di.read_word_size_value_at_vaddr(sink);
             let o_sink_value = match o_ptr_value {
                 Some(x) => {
                     let value = x.value;
+                    sink_values.insert(sink, Some(value));
+                    if di.is_vaddr_ptr(value) {
+                        src_to_sinks.insert(sink, value);
+                        xrefs.add(sink, value);
+                    }
                     Some(value)
                 }
                 None => {
+                    sink_values.insert(sink, None);
                     None
                 }
             };
@@ -232,6 +318,8 @@ pub fn perform_search_with_vaddr_start(
                     sink_paddr: sink_paddr,
                     sink_vaddr: sink,
                     sink_value: Some(value),
+                    source_class: None,
+                    sink_class: None,
                     sink_vaddr_base: sink_vaddr_base,
                     sink_paddr_base: sink_paddr_base,
                     paddr_base: phys_base,
@@ -244,6 +332,8 @@ pub fn perform_search_with_vaddr_start(
                     sink_paddr: sink_paddr,
                     sink_vaddr: sink,
                     sink_value: None,
+                    source_class: None,
+                    sink_class: None,
                     sink_vaddr_base: sink_vaddr_base,
                     sink_paddr_base: sink_paddr_base,
                     paddr_base: phys_base,
@@ -254,24 +344,30 @@ pub fn perform_search_with_vaddr_start(
             let mut sr = Box::new(SearchResult::default());
             sr.boundary_offset = paddr as u64;
             sr.size = incr;
+            sr.data = o_sink_value.map(|v| v.to_le_bytes().to_vec());
             sr.vaddr = vaddr;
             sr.paddr = paddr;
             sr.digest = "".to_string();
             sr.section_name = mr.name.clone();
-            shared_comments.write().unwrap().insert(vaddr, i_comment);
-            shared_results.write().unwrap().push(sr);
+            comments.insert(vaddr, i_comment);
+            results.push(sr);
             found += 1;
         }
         pos += incr;
     }
-    info!(
-            "Found {} results in perform_search_buffer_with_bases: paddr: {:08x} vaddr: {:08x} name: {}, total comments: {}, total results: {}",
+    shared_xrefs.write()?.merge(&xrefs);
+    shared_src_to_sinks.write()?.extend(src_to_sinks);
+    shared_sink_values.write()?.extend(sink_values);
+    shared_comments.write()?.append(&mut comments);
+    shared_results.write()?.append(&mut results);
+    debug!(
+            "Found {} results in perform_search_chunk: paddr: {:08x} vaddr: {:08x} name: {} offsets: {:08x}-{:08x}",
             found,
             phys_base,
             virt_base,
             mr.name,
-            shared_comments.read().unwrap().len(),
-            shared_results.read().unwrap().len()
+            chunk.start,
+            chunk_end
         );
     return Ok(());
 }
@@ -281,84 +377,51 @@ impl PointerSearch {
     pub fn perform_search_with_interface_mt(
         &mut self,
         di_arw: Arc<RwLock<Box<DataInterface>>>,
-    ) -> Result<Vec<Box<SearchResult>>, Box<dyn StdErr>> {
-        let mut thread_handles_ac: VecDeque<thread::JoinHandle<()>> = VecDeque::new();
+    ) -> Result<Vec<Box<SearchResult>>, SearchError> {
         let shared_results : Arc<RwLock<Vec<Box<SearchResult>>>> = Arc::new(RwLock::new(Vec::new()));
         let shared_comments = Arc::clone(&self.shared_comments);
-        let di = di_arw.read().unwrap();
-
-        let v_mrs = di.mem_ranges.get_mem_ranges();
-        let mut wv_mrs = Vec::new();
-        for mr in v_mrs.iter() {
-            if mr.perm.find("w").is_some() {
-                wv_mrs.push(mr.clone());
+        let shared_xrefs = Arc::clone(&self.xrefs);
+        let shared_src_to_sinks: RwLock<HashMap<u64, u64>> = RwLock::new(HashMap::new());
+        let shared_sink_values: RwLock<HashMap<u64, Option<u64>>> = RwLock::new(HashMap::new());
+        let di = di_arw.read()?;
+
+        let wv_mrs = di.mem_ranges.get_filtered_mem_ranges(&self.region_filter);
+        let incr = (di.vmem_info.word_sz as u64).max(1);
+        let scheduler = self.scheduler();
+        scheduler.run("pointer", &wv_mrs, incr, |chunk| {
+            perform_search_chunk(
+                &di,
+                chunk,
+                &shared_results,
+                &shared_comments,
+                &shared_xrefs,
+                &shared_src_to_sinks,
+                &shared_sink_values,
+            )
+        })?;
+        self.src_to_sinks.extend(shared_src_to_sinks.into_inner()?);
+        self.sink_values.extend(shared_sink_values.into_inner()?);
+
+        let lshared_results = match Arc::try_unwrap(shared_results) {
+            Ok(lock) => lock,
+            Err(_) => {
+                let msg = "the results are still shared with a search thread".to_string();
+                return Err(SearchError::Thread(msg));
             }
-        }
-        let max_threads = self.max_threads.clone() as usize;
-        for mr in wv_mrs.iter() {
-            let bsr = Arc::clone(&shared_results);
-            let bsc = Arc::clone(&shared_comments);
-            let bmr = mr.clone();
-            let cdi = di_arw.clone();
-            let t = thread::spawn(move || {
-                let _ = perform_search_with_vaddr_start(cdi.clone(), &bmr, bsr.clone(), bsc.clone());
-            });
-            thread_handles_ac.push_back(t);
-            debug!(
-            "Searching Memory Range: {} of {} for pointers from starting at vaddr {:08x} and paddr {:08x}.",
-            mr.name, mr.vsize, mr.vaddr_start, mr.paddr_start);
-            // this loop structure is used to relieve memory pressure and ensure too many threads
-            // are not created at once.  I had a hard time using the thread pool to help men manage
-            // this
-            while thread_handles_ac.len() > max_threads {
-                let mut dt_enter = Utc::now();
-                let dt_exit = dt_enter.checked_add_signed(Duration::milliseconds(500)).unwrap();
-                // Join as many threads as we can in 500 ms
-                while dt_enter < dt_exit && thread_handles_ac.len() > 0{
-                    let at = thread_handles_ac.pop_front().unwrap();
-                    // debug!("joining the threas.");
-                    let _ = at.join();
-                    // debug!("joining the threas.");
-                    dt_enter = Utc::now();
-                }
-                // want to push down the number of running threads further. Join as many threads as
-                // we can in the next 500 ms (not a sophisticated strategy)
-                if thread_handles_ac.len() > 0 && thread_handles_ac.len() > max_threads - 5{
-                    let mut dt_enter = Utc::now();
-                    let dt_exit = dt_enter.checked_add_signed(Duration::milliseconds(500)).unwrap();
-                    while dt_enter < dt_exit && thread_handles_ac.len() > 0{
-                        let at = thread_handles_ac.pop_front().unwrap();
-                        let _ = at.join();
-                        dt_enter = Utc::now();
-                    }
-                }
-            }
-        }
-
-        thread_handles_ac
-            .into_iter()
-            .for_each(|th| th.join().expect("can't join thread"));
-
-        let lshared_results = Arc::try_unwrap(shared_results).expect("lock still has owners");
-        let search_results = lshared_results.into_inner().expect("Mutex cannot be locked");
+        };
+        let search_results = lshared_results.into_inner()?;
         info!("Found {} results.", search_results.len());
-        info!("Found {} shared_comments.", shared_comments.read().unwrap().len());
-        info!("Found {} self.shared_comments.", shared_comments.read().unwrap().len());
+        info!("Found {} shared_comments.", shared_comments.read()?.len());
+        info!("Found {} self.shared_comments.", shared_comments.read()?.len());
         return Ok(search_results);
     }
     pub fn perform_search_with_interface(
         &mut self,
         di_arw: Arc<RwLock<Box<DataInterface>>>,
-    ) -> Result<Vec<Box<SearchResult>>, Box<dyn StdErr>> {
+    ) -> Result<Vec<Box<SearchResult>>, SearchError> {
         let mut search_results: Vec<Box<SearchResult>> = Vec::new();
-        let di = di_arw.read().unwrap();
-        let v_mrs = di.mem_ranges.get_mem_ranges();
-        let mut wv_mrs = Vec::new();
-        for mr in v_mrs.iter() {
-            if mr.perm.find("w").is_some() {
-                wv_mrs.push(mr.clone());
-            }
-        }
+        let di = di_arw.read()?;
+        let wv_mrs = di.mem_ranges.get_filtered_mem_ranges(&self.region_filter);
 
         for mr in wv_mrs.iter() {
             debug!(
@@ -371,7 +434,7 @@ impl PointerSearch {
             let _size: u64 = mr.size;
 
             let r_results = self.perform_search_with_vaddr_start(di_arw.clone(), vaddr);
-            let mut results: Vec<Box<SearchResult>> = r_results.unwrap();
+            let mut results: Vec<Box<SearchResult>> = r_results?;
             debug!(
                 "Found {} results in {}, search_results.len() = {}.",
                 results.len(),
@@ -390,9 +453,10 @@ impl PointerSearch {
         &mut self,
         di_arw: Arc<RwLock<Box<DataInterface>>>,
         svaddr: u64,
-    ) -> Result<Vec<Box<SearchResult>>, Box<dyn StdErr>> {
-        let di = di_arw.read().unwrap();
+    ) -> Result<Vec<Box<SearchResult>>, SearchError> {
+        let di = di_arw.read()?;
         let mut search_results: Vec<Box<SearchResult>> = Vec::new();
+        let mut xrefs = XrefIndex::new();
         let alignment: u64 = if di.vmem_info.alignment == 0 {
             1
         } else {
@@ -403,7 +467,6 @@ impl PointerSearch {
         } else {
             di.vmem_info.word_sz.into()
         };
-        let page_mask = di.vmem_info.page_mask;
 
         let mut pos: u64 = 0;
 
@@ -412,7 +475,10 @@ impl PointerSearch {
             return Ok(search_results);
         }
 
-        let phys_base = di.get_paddr_base_from_vaddr(&svaddr).unwrap();
+        let phys_base = match di.get_paddr_base_from_vaddr(&svaddr) {
+            Some(phys_base) => phys_base,
+            None => return Ok(search_results),
+        };
 
         let o_end = di.get_vaddr_end(svaddr);
         if o_end.is_none() {
@@ -436,7 +502,7 @@ impl PointerSearch {
         let read_value = |buffer: &[u8]| -> u64 {
             match endian {
                 ENDIAN::BIG => match incr {
-                    1 => <u8 as TryInto<u64>>::try_into(buffer[0]).unwrap() as u64,
+                    1 => buffer[0] as u64,
                     2 => BigEndian::read_u16(buffer) as u64,
                     4 => BigEndian::read_u32(buffer) as u64,
                     8 => BigEndian::read_u64(buffer) as u64,
@@ -460,19 +526,28 @@ impl PointerSearch {
             // debug!("Reading buffer at {:08x}", pos );
 
             let sink = read_value(&vaddr_buf[pos as usize..]);
-            let sink_page = sink & page_mask;
-            let lookup_has_page = di.vmem_info.ptr_lookup.contains_key(&sink_page);
+            let lookup_has_page = di.vmem_info.ptr_lookup.contains(sink);
 
             let has_alignment = sink % alignment == 0;
 
             if has_alignment && lookup_has_page {
-                let sink_paddr = di.convert_vaddr_to_paddr(&sink).unwrap();
-                let sink_paddr_base = di.get_paddr_base_from_vaddr(&sink).unwrap();
-                let sink_vaddr_base = di.get_vaddr_base_from_vaddr(&sink).unwrap();
+                let (sink_paddr, sink_paddr_base, sink_vaddr_base) = match (
+                    di.convert_vaddr_to_paddr(&sink),
+                    di.get_paddr_base_from_vaddr(&sink),
+                    di.get_vaddr_base_from_vaddr(&sink),
+                ) {
+                    (Some(paddr), Some(paddr_base), Some(vaddr_base)) => {
+                        (paddr, paddr_base, vaddr_base)
+                    }
+                    _ => {
+                        pos += incr;
+                        continue;
+                    }
+                };
 
-                let _bal = di.vmem_info.ptr_lookup.get(&sink_page);
                 self.src_to_sinks.insert(vaddr, sink);
                 self.sink_values.insert(vaddr, Some(sink));
+                xrefs.add(vaddr, sink);
                 let o_ptr_value = di.read_word_size_value_at_vaddr(sink);
                 let o_sink_value = match o_ptr_value {
                     Some(x) => {
@@ -480,6 +555,7 @@ impl PointerSearch {
                         self.sink_values.insert(sink, Some(value));
                         if di.is_vaddr_ptr(value) {
                             self.src_to_sinks.insert(sink, value);
+                            xrefs.add(sink, value);
                         }
                         Some(value)
                     }
@@ -497,6 +573,8 @@ impl PointerSearch {
                         sink_paddr: sink_paddr,
                         sink_vaddr: sink,
                         sink_value: Some(value),
+                        source_class: None,
+                        sink_class: None,
                         sink_vaddr_base: sink_vaddr_base,
                         sink_paddr_base: sink_paddr_base,
                         paddr_base: phys_base,
@@ -509,6 +587,8 @@ impl PointerSearch {
                         sink_paddr: sink_paddr,
                         sink_vaddr: sink,
                         sink_value: None,
+                        source_class: None,
+                        sink_class: None,
                         sink_vaddr_base: sink_vaddr_base,
                         sink_paddr_base: sink_paddr_base,
                         paddr_base: phys_base,
@@ -544,6 +624,7 @@ impl PointerSearch {
             phys_base,
             virt_base
         );
+        self.xrefs.write()?.merge(&xrefs);
         return Ok(search_results.clone());
     }
 
@@ -552,9 +633,10 @@ impl PointerSearch {
         buffer: &[u8],
         phys_base: u64,
         virt_base: u64,
-    ) -> Result<Vec<Box<SearchResult>>, Box<dyn StdErr>> {
+    ) -> Result<Vec<Box<SearchResult>>, SearchError> {
         let mut search_results: Vec<Box<SearchResult>> = Vec::new();
-        let di = self.data_interface.read().unwrap();
+        let mut xrefs = XrefIndex::new();
+        let di = self.data_interface.read()?;
         let alignment: u64 = if di.vmem_info.alignment == 0 {
             1
         } else {
@@ -567,9 +649,16 @@ impl PointerSearch {
         };
         let mut pos = 0;
         let end: u64 = buffer.len() as u64;
-        let page_mask = di.vmem_info.page_mask;
-        let vaddr_base = di.get_vaddr_base_from_vaddr(&virt_base).unwrap();
-        let paddr_base = di.get_paddr_base_from_vaddr(&virt_base).unwrap();
+        let (vaddr_base, paddr_base) = match (
+            di.get_vaddr_base_from_vaddr(&virt_base),
+            di.get_paddr_base_from_vaddr(&virt_base),
+        ) {
+            (Some(vaddr_base), Some(paddr_base)) => (vaddr_base, paddr_base),
+            _ => {
+                let size = buffer.len() as u64;
+                return Err(MemError::OutOfRange { addr: virt_base, size }.into());
+            }
+        };
 
         while pos < end {
             let vaddr = pos + virt_base;
@@ -581,17 +670,26 @@ impl PointerSearch {
             }
             let rvalue: ReadValue = o_rvalue.unwrap();
             let sink = rvalue.value;
-            let sink_page = sink & page_mask;
-            let lookup_has_page = di.vmem_info.ptr_lookup.contains_key(&sink_page);
+            let lookup_has_page = di.vmem_info.ptr_lookup.contains(sink);
 
             let has_alignment = sink % alignment == 0;
             if has_alignment && lookup_has_page {
-                let sink_paddr = di.convert_vaddr_to_paddr(&sink).unwrap();
-                let sink_paddr_base = di.get_paddr_base_from_vaddr(&sink).unwrap();
-                let sink_vaddr_base = di.get_vaddr_base_from_vaddr(&sink).unwrap();
-                let _bal = di.vmem_info.ptr_lookup.get(&sink_page);
+                let (sink_paddr, sink_paddr_base, sink_vaddr_base) = match (
+                    di.convert_vaddr_to_paddr(&sink),
+                    di.get_paddr_base_from_vaddr(&sink),
+                    di.get_vaddr_base_from_vaddr(&sink),
+                ) {
+                    (Some(paddr), Some(paddr_base), Some(vaddr_base)) => {
+                        (paddr, paddr_base, vaddr_base)
+                    }
+                    _ => {
+                        pos += incr;
+                        continue;
+                    }
+                };
                 self.src_to_sinks.insert(vaddr, sink);
                 self.sink_values.insert(vaddr, Some(sink));
+                xrefs.add(vaddr, sink);
                 let o_ptr_value = di.read_word_size_value_at_vaddr(sink);
                 let o_sink_value = match o_ptr_value {
                     Some(x) => {
@@ -599,6 +697,7 @@ impl PointerSearch {
                         self.sink_values.insert(sink, Some(value));
                         if di.is_vaddr_ptr(value) {
                             self.src_to_sinks.insert(sink, value);
+                            xrefs.add(sink, value);
                         }
                         Some(value)
                     }
@@ -616,6 +715,8 @@ impl PointerSearch {
                         sink_paddr: sink_paddr,
                         sink_vaddr: sink,
                         sink_value: Some(value),
+                        source_class: None,
+                        sink_class: None,
                         sink_vaddr_base: sink_vaddr_base,
                         sink_paddr_base: sink_paddr_base,
                         paddr_base: paddr_base,
@@ -628,6 +729,8 @@ impl PointerSearch {
                         sink_paddr: sink_paddr,
                         sink_vaddr: sink,
                         sink_value: None,
+                        source_class: None,
+                        sink_class: None,
                         sink_vaddr_base: sink_vaddr_base,
                         sink_paddr_base: sink_paddr_base,
                         paddr_base: paddr_base,
@@ -665,6 +768,7 @@ impl PointerSearch {
             virt_base
         );
 
+        self.xrefs.write()?.merge(&xrefs);
         return Ok(search_results.clone());
     }
 
@@ -678,18 +782,55 @@ impl PointerSearch {
             comments: Box::new(BTreeMap::new()),
             shared_comments: Arc::new(RwLock::new(Box::new(BTreeMap::new()))),
             max_threads: 30,
+            region_filter: RegionFilter::writable(),
+            xrefs: Arc::new(RwLock::new(XrefIndex::new())),
+            scheduler: None,
+        }
+    }
+
+    // a scheduler with `max_threads` threads, its pool is kept for the next searches
+    fn scheduler(&mut self) -> ChunkScheduler {
+        let num_threads = self.max_threads as usize;
+        match &self.scheduler {
+            Some(scheduler) if scheduler.num_threads() == num_threads => scheduler.clone(),
+            _ => {
+                let scheduler = ChunkScheduler::new(num_threads);
+                self.scheduler = Some(scheduler.clone());
+                scheduler
+            }
         }
     }
 
-    pub fn get_comments(&self) -> Vec<Box<Comment>> {
+    pub fn get_comments(&self) -> Result<Vec<Box<Comment>>, SearchError> {
         let mut comments = Vec::new();
-        for (_, c) in self.shared_comments.read().unwrap().iter() {
+        for (_, c) in self.shared_comments.read()?.iter() {
             comments.push(c.clone())
         }
-        return comments;
+        return Ok(comments);
+    }
+
+    /// Tag the source and sink of every comment with the class of the memory they are in.
+    pub fn classify_comments(&self, classifier: &Classifier) -> Result<(), SearchError> {
+        let mut sc = self.shared_comments.write()?;
+        for (_, c) in sc.iter_mut() {
+            c.source_class = Some(classifier.classify(c.vaddr));
+            c.sink_class = Some(classifier.classify(c.sink_vaddr));
+        }
+        info!("Classified {} pointers.", sc.len());
+        return Ok(());
+    }
+
+    pub fn write_comments(&self, output_filename: PathBuf) -> Result<(), SearchError> {
+        return self.write_filtered_comments(output_filename, None);
     }
 
-    pub fn write_comments(&self, output_filename: PathBuf ) -> () {
+    /// Like `write_comments`, but only the classified comments that match `o_filter` are
+    /// written when it is given.
+    pub fn write_filtered_comments(
+        &self,
+        output_filename: PathBuf,
+        o_filter: Option<&PointerFilter>,
+    ) -> Result<(), SearchError> {
         let capacity = 1000 * 1024 * 1024;
         let o_writer = File::create(&output_filename);
         let mut writer = match o_writer {
@@ -701,7 +842,7 @@ impl PointerSearch {
                     err
                 );
                 error!("{}", msg);
-                panic!("{}", msg);
+                return Err(err.into());
             }
         };
         let msg = format!(
@@ -709,13 +850,19 @@ impl PointerSearch {
         );
         debug!("{}", msg);
 
-        let sc = self.shared_comments.read().unwrap();
+        let sc = self.shared_comments.read()?;
         let msg = format!(
             "Writing {} results to file: {}", sc.len(), output_filename.display()
         );
         info!("{}", msg);
         let mut lines_written = 0;
         for (_, c) in sc.iter() {
+            if let Some(filter) = o_filter {
+                match (&c.source_class, &c.sink_class) {
+                    (Some(source), Some(sink)) if filter.matches(source, sink) => {}
+                    _ => continue,
+                }
+            }
             lines_written += 1;
             if lines_written % 100000 == 0 {
                 info!("Wrote {} results", lines_written);
@@ -730,10 +877,12 @@ impl PointerSearch {
                         err
                     );
                     error!("{}", msg);
-                    panic!("{}", msg);
+                    return Err(err.into());
                 }
             };
         }
-        writer.flush().unwrap();
+        writer.flush()?;
+        info!("Wrote {} results to file: {}", lines_written, output_filename.display());
+        return Ok(());
     }
 }
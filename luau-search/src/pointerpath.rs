@@ -0,0 +1,300 @@
+use mem_analysis::data_interface::DataInterface;
+use mem_analysis::memory::MemRange;
+use mem_analysis::pointers::XrefIndex;
+use mem_analysis::region::MemType;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use log::{debug, info, warn};
+
+pub const DEFAULT_MAX_DEPTH: usize = 4;
+pub const DEFAULT_MAX_OFFSET: u64 = 0x400;
+pub const DEFAULT_MAX_RESULTS: usize = 1000;
+pub const DEFAULT_MAX_VISITS: usize = 1_000_000;
+
+/// A chain of pointers from a static address in a module to a target.  Starting at
+/// `module + base_offset`, each hop reads the pointer at the current address and adds the next
+/// offset, the address after the last hop is the target.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PointerPath {
+    pub module: String,
+    pub base_offset: u64,
+    pub offsets: Vec<u64>,
+}
+
+impl Display for PointerPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}+{:x}", self.module, self.base_offset)?;
+        for offset in self.offsets.iter() {
+            write!(f, " -> [+{:x}]", offset)?;
+        }
+        Ok(())
+    }
+}
+
+impl PointerPath {
+    pub fn depth(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Follow the path through `di`, `None` when the module is not loaded or a pointer along
+    /// the way can not be read.
+    pub fn resolve(&self, di: &DataInterface) -> Option<u64> {
+        let mut vaddr = module_base(di, &self.module)?.checked_add(self.base_offset)?;
+        for offset in self.offsets.iter() {
+            vaddr = PointerPath::follow(di, vaddr, *offset)?;
+        }
+        Some(vaddr)
+    }
+
+    /// The address after the hop `offset` from `vaddr`, the next step of `resolve`.
+    pub fn follow(di: &DataInterface, vaddr: u64, offset: u64) -> Option<u64> {
+        di.read_word_size_value_at_vaddr(vaddr)?
+            .value
+            .checked_add(offset)
+    }
+
+    pub fn resolves_to(&self, di: &DataInterface, target: u64) -> bool {
+        self.resolve(di) == Some(target)
+    }
+}
+
+/// Whether the range belongs to a module, i.e. stays at the same offset from the module base
+/// between runs.  Windows images are `MemType::Image`, Linux file mappings are named by their
+/// path.
+pub fn is_module_range(mr: &MemRange) -> bool {
+    match mr.mem_type {
+        MemType::Image => true,
+        MemType::Mapped => mr.name.contains('/'),
+        _ => false,
+    }
+}
+
+/// The lowest address of the module ranges named `module`.
+pub fn module_base(di: &DataInterface, module: &str) -> Option<u64> {
+    di.mem_ranges
+        .vmem_ranges
+        .iter()
+        .find(|(_range, mr)| mr.name == module && is_module_range(mr))
+        .map(|(_range, mr)| mr.vaddr_start)
+}
+
+/// Breadth-first search for pointer paths over the pointers found by `PointerSearch`.  Walking
+/// back from the target, every pointer into `[node - max_offset, node]` adds a hop, the paths
+/// end at the first pointers that live in a module.
+#[derive(Debug, Clone)]
+pub struct PointerPathSearch {
+    pub max_depth: usize,
+    pub max_offset: u64,
+    pub max_results: usize,
+    /// The number of partial paths walked before giving up, the paths between the module
+    /// pointers and the target can grow exponentially with the depth.
+    pub max_visits: usize,
+}
+
+impl Default for PointerPathSearch {
+    fn default() -> Self {
+        PointerPathSearch::new(DEFAULT_MAX_DEPTH, DEFAULT_MAX_OFFSET)
+    }
+}
+
+impl PointerPathSearch {
+    pub fn new(max_depth: usize, max_offset: u64) -> Self {
+        PointerPathSearch {
+            max_depth,
+            max_offset,
+            max_results: DEFAULT_MAX_RESULTS,
+            max_visits: DEFAULT_MAX_VISITS,
+        }
+    }
+
+    /// The paths from module pointers to `target`, shortest first.
+    pub fn find_paths(
+        &self,
+        di: &DataInterface,
+        xrefs: &XrefIndex,
+        target: u64,
+    ) -> Vec<PointerPath> {
+        self.collect_paths(di, xrefs, target, &[])
+    }
+
+    /// The paths to `target` in `di` that also lead to the target in each of the `rescans`,
+    /// dumps of other runs of the same binary paired with the address of the target in them.
+    /// Modules are located by name in every dump, so a different load address does not matter.
+    /// A path is dropped at the first hop that can not be followed in one of the rescans.
+    pub fn find_stable_paths(
+        &self,
+        di: &DataInterface,
+        xrefs: &XrefIndex,
+        target: u64,
+        rescans: &[(&DataInterface, u64)],
+    ) -> Vec<PointerPath> {
+        let paths = self.collect_paths(di, xrefs, target, rescans);
+        info!(
+            "{} pointer paths to {:08x} resolve in all {} rescanned dumps.",
+            paths.len(),
+            target,
+            rescans.len()
+        );
+        paths
+    }
+
+    // up to `max_results` of the paths that also resolve to the target of every rescan
+    fn collect_paths(
+        &self,
+        di: &DataInterface,
+        xrefs: &XrefIndex,
+        target: u64,
+        rescans: &[(&DataInterface, u64)],
+    ) -> Vec<PointerPath> {
+        // the hop count to the target of every address reached, and the hops out of it
+        let mut levels: HashMap<u64, usize> = HashMap::new();
+        let mut hops: HashMap<u64, Vec<(u64, u64)>> = HashMap::new();
+        let mut roots: Vec<u64> = Vec::new();
+        levels.insert(target, 0);
+
+        let mut frontier: Vec<u64> = vec![target];
+        for depth in 1..=self.max_depth {
+            let mut next: Vec<u64> = Vec::new();
+            for node in frontier.iter() {
+                let start = node.saturating_sub(self.max_offset);
+                for (source, sink) in xrefs.xrefs_to_range(start, node.saturating_add(1)) {
+                    match levels.get(&source) {
+                        // already as close or closer to the target
+                        Some(level) if *level < depth => continue,
+                        Some(_) => {}
+                        None => {
+                            levels.insert(source, depth);
+                            let in_module = di
+                                .mem_ranges
+                                .get_vaddr_range(source)
+                                .map(|mr| is_module_range(&mr))
+                                .unwrap_or(false);
+                            if in_module {
+                                roots.push(source);
+                            } else {
+                                next.push(source);
+                            }
+                        }
+                    }
+                    hops.entry(source).or_default().push((*node, node - sink));
+                }
+            }
+            debug!(
+                "Depth {}: {} module pointers, {} addresses to expand.",
+                depth,
+                roots.len(),
+                next.len()
+            );
+            if next.is_empty() {
+                break;
+            }
+            frontier = next;
+        }
+
+        let mut expansion = Expansion {
+            search: self,
+            hops: &hops,
+            target,
+            rescans,
+            visits: 0,
+            paths: Vec::new(),
+        };
+        for root in roots.iter() {
+            if expansion.done() {
+                break;
+            }
+            let mr = match di.mem_ranges.get_vaddr_range(*root) {
+                Some(mr) => mr,
+                None => continue,
+            };
+            let base = match module_base(di, &mr.name) {
+                Some(base) => base,
+                None => continue,
+            };
+            let prefix = PointerPath {
+                module: mr.name.clone(),
+                base_offset: root - base,
+                offsets: Vec::new(),
+            };
+            // where the prefix leads in every rescan
+            let o_resolved: Option<Vec<u64>> = rescans
+                .iter()
+                .map(|(other, _)| module_base(other, &mr.name)?.checked_add(prefix.base_offset))
+                .collect();
+            if let Some(resolved) = o_resolved {
+                expansion.expand(*root, prefix, resolved);
+            }
+        }
+        if expansion.visits >= self.max_visits {
+            warn!(
+                "Stopped the pointer path search to {:08x} after {} partial paths.",
+                target, expansion.visits
+            );
+        }
+        let paths = expansion.paths;
+        info!(
+            "Found {} pointer paths to {:08x} from {} module pointers.",
+            paths.len(),
+            target,
+            roots.len()
+        );
+        paths
+    }
+
+}
+
+// the state of the depth-first walk from the module pointers to the target
+struct Expansion<'a> {
+    search: &'a PointerPathSearch,
+    hops: &'a HashMap<u64, Vec<(u64, u64)>>,
+    target: u64,
+    rescans: &'a [(&'a DataInterface, u64)],
+    visits: usize,
+    paths: Vec<PointerPath>,
+}
+
+impl Expansion<'_> {
+    fn done(&self) -> bool {
+        self.paths.len() >= self.search.max_results || self.visits >= self.search.max_visits
+    }
+
+    // every hop strictly decreases the level, so the walk ends at the target.  `resolved` is
+    // where `path` leads in each of the rescans.
+    fn expand(&mut self, node: u64, path: PointerPath, resolved: Vec<u64>) {
+        self.visits += 1;
+        if node == self.target {
+            let stable = resolved
+                .iter()
+                .zip(self.rescans.iter())
+                .all(|(vaddr, (_, other_target))| vaddr == other_target);
+            if stable {
+                self.paths.push(path);
+            }
+            return;
+        }
+        let hops = self.hops;
+        let outgoing = match hops.get(&node) {
+            Some(outgoing) => outgoing,
+            None => return,
+        };
+        for (next, offset) in outgoing.iter() {
+            if self.done() {
+                return;
+            }
+            let o_next_resolved: Option<Vec<u64>> = resolved
+                .iter()
+                .zip(self.rescans.iter())
+                .map(|(vaddr, (other, _))| PointerPath::follow(other, *vaddr, *offset))
+                .collect();
+            let next_resolved = match o_next_resolved {
+                Some(next_resolved) => next_resolved,
+                None => continue,
+            };
+            let mut next_path = path.clone();
+            next_path.offsets.push(*offset);
+            self.expand(*next, next_path, next_resolved);
+        }
+    }
+}
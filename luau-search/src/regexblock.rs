@@ -1,5 +1,5 @@
 use crate::search::*;
-use std::error::Error as StdErr;
+use crate::error::SearchError;
 use std::sync::{Arc, RwLock};
 
 use log::{debug, error, info};
@@ -37,11 +37,12 @@ impl Search for RegexBlockSearch {
         &mut self,
         buffer: &[u8],
         pos: u64,
-    ) -> Result<Option<SearchResult>, Box<dyn StdErr>> {
+    ) -> Result<Option<SearchResult>, SearchError> {
         return self.perform_search_buffer_next(buffer, pos);
     }
-    fn search_buffer(&mut self, buffer: &[u8]) -> Result<Vec<Box<SearchResult>>, Box<dyn StdErr>> {
-        return self.perform_search_buffer(buffer);
+    fn search_buffer(&mut self, buffer: &[u8]) -> Result<Vec<Box<SearchResult>>, SearchError> {
+        let results = self.perform_search_buffer(buffer)?;
+        return Ok(results.into_iter().map(Box::new).collect());
     }
 
     fn search_buffer_with_bases(
@@ -49,15 +50,15 @@ impl Search for RegexBlockSearch {
         buffer: &[u8],
         phys_base: u64,
         virt_base: u64,
-    ) -> Result<Vec<Box<SearchResult>>, Box<dyn StdErr>> {
+    ) -> Result<Vec<Box<SearchResult>>, SearchError> {
         return self.perform_search_buffer_with_bases(buffer, phys_base, virt_base);
     }
 
     fn search_interface(
         &mut self,
         di_arw: Arc<RwLock<Box<DataInterface>>>,
-    ) -> Result<Vec<Box<SearchResult>>, Box<dyn StdErr>> {
-        let di = di_arw.read().unwrap();
+    ) -> Result<Vec<Box<SearchResult>>, SearchError> {
+        let di = di_arw.read()?;
         let o_ro_buf = di.buffer.get_shared_buffer();
         let search_results = Vec::new();
         if !o_ro_buf.is_some() {
@@ -66,15 +67,16 @@ impl Search for RegexBlockSearch {
         }
         let ro_buf = o_ro_buf.unwrap();
         // TODO pass the DataInterface to the struct and use that to interact with the backend data
-        return self.perform_search_buffer(ro_buf);
+        let results = self.perform_search_buffer(ro_buf)?;
+        return Ok(results.into_iter().map(Box::new).collect());
     }
     fn search_interface_with_bases(
         &mut self,
         di_arw: Arc<RwLock<Box<DataInterface>>>,
         phys_base: u64,
         virt_base: u64,
-    ) -> Result<Vec<Box<SearchResult>>, Box<dyn StdErr>> {
-        let di = di_arw.read().unwrap();
+    ) -> Result<Vec<Box<SearchResult>>, SearchError> {
+        let di = di_arw.read()?;
         let o_ro_buf = di.buffer.get_shared_buffer();
         let search_results = Vec::new();
         if !o_ro_buf.is_some() {
@@ -99,24 +101,24 @@ impl RegexBlockSearch {
         offtype: Option<OffsetType>,
         base_vaddr: Option<u64>,
         base_paddr: Option<u64>,
-    ) -> Self {
+    ) -> Result<Self, SearchError> {
         let regex_start: Regex = match Regex::new(re_start_tag.as_str()) {
             Ok(r) => r,
-            Err(e) => panic!(
-                "Invalid regular expression provided: '{}', {}",
-                re_start_tag, e
-            ),
+            Err(e) => {
+                error!("Invalid regular expression provided: '{}', {}", re_start_tag, e);
+                return Err(e.into());
+            }
         };
 
         let regex_end: Regex = match Regex::new(re_end_tag.as_str()) {
             Ok(r) => r,
-            Err(e) => panic!(
-                "Invalid regular expression provided: '{}', {}",
-                re_start_tag, e
-            ),
+            Err(e) => {
+                error!("Invalid regular expression provided: '{}', {}", re_end_tag, e);
+                return Err(e.into());
+            }
         };
 
-        RegexBlockSearch {
+        Ok(RegexBlockSearch {
             stop,
             start,
             start_pattern: regex_start.to_string(),
@@ -125,7 +127,7 @@ impl RegexBlockSearch {
             base_vaddr: base_vaddr,
             base_paddr: base_paddr,
             comments: Vec::new(),
-        }
+        })
     }
 
     pub fn create(
@@ -136,21 +138,15 @@ impl RegexBlockSearch {
         offtype: Option<OffsetType>,
         base_vaddr: Option<u64>,
         base_paddr: Option<u64>,
-    ) -> Self {
-        let regex_start: Regex = match re_start_tag {
-            Some(pattern) => match Regex::new(pattern.as_str()) {
-                Ok(r) => r,
-                Err(e) => panic!("Invalid regular expression provided: '{}', {}", pattern, e),
-            },
-            None => Regex::new(ROBLOX_REGEX_START).unwrap(),
+    ) -> Result<Self, SearchError> {
+        let regex_start = match re_start_tag {
+            Some(pattern) => pattern.as_str(),
+            None => ROBLOX_REGEX_START,
         };
 
-        let regex_end: Regex = match re_end_tag {
-            Some(pattern) => match Regex::new(pattern.as_str()) {
-                Ok(r) => r,
-                Err(e) => panic!("Invalid regular expression provided: '{}', {}", pattern, e),
-            },
-            None => Regex::new(ROBLOX_REGEX_END).unwrap(),
+        let regex_end = match re_end_tag {
+            Some(pattern) => pattern.as_str(),
+            None => ROBLOX_REGEX_END,
         };
         return RegexBlockSearch::new(
             &regex_start.to_string(),
@@ -181,9 +177,14 @@ impl RegexBlockSearch {
         &mut self,
         buffer: &[u8],
         pos: u64,
-    ) -> Result<Option<SearchResult>, Box<dyn StdErr>> {
-        let start_pattern = Regex::new(&self.start_pattern.as_str()).unwrap();
-        let end_pattern = Regex::new(&self.end_pattern.as_str()).unwrap();
+    ) -> Result<Option<SearchResult>, SearchError> {
+        let start_pattern = Regex::new(self.start_pattern.as_str())?;
+        let end_pattern = Regex::new(self.end_pattern.as_str())?;
+
+        if pos > buffer.len() as u64 {
+            let len = buffer.len() as u64;
+            return Err(SearchError::OutOfRange { pos, len });
+        }
 
         let mut search_result = Ok(None);
         let start_seq: Option<Vec<usize>> =
@@ -259,8 +260,12 @@ impl RegexBlockSearch {
         buffer: &[u8],
         phys_base: u64,
         virt_base: u64,
-    ) -> Result<Vec<Box<SearchResult>>, Box<dyn StdErr>> {
-        let mut search_results = self.perform_search_buffer(buffer)?;
+    ) -> Result<Vec<Box<SearchResult>>, SearchError> {
+        let mut search_results: Vec<Box<SearchResult>> = self
+            .perform_search_buffer(buffer)?
+            .into_iter()
+            .map(Box::new)
+            .collect();
         for r in search_results.iter_mut() {
             let va = virt_base + r.boundary_offset;
             let pa = phys_base + r.boundary_offset;
@@ -274,12 +279,9 @@ impl RegexBlockSearch {
         return Ok(search_results);
     }
 
-    fn perform_search_buffer(
-        &mut self,
-        buffer: &[u8],
-    ) -> Result<Vec<Box<SearchResult>>, Box<dyn StdErr>> {
-        let start_pattern = Regex::new(&self.start_pattern.as_str()).unwrap();
-        let end_pattern = Regex::new(&self.end_pattern.as_str()).unwrap();
+    fn perform_search_buffer(&mut self, buffer: &[u8]) -> Result<Vec<SearchResult>, SearchError> {
+        let start_pattern = Regex::new(self.start_pattern.as_str())?;
+        let end_pattern = Regex::new(self.end_pattern.as_str())?;
 
         let stop: u64 = match &self.stop {
             Some(x) => *x,
@@ -291,6 +293,10 @@ impl RegexBlockSearch {
             Some(x) => *x,
             None => 0,
         };
+        if pos > buffer.len() as u64 {
+            let len = buffer.len() as u64;
+            return Err(SearchError::OutOfRange { pos, len });
+        }
         info!("Searching for blocks of data.");
         loop {
             debug!(
@@ -361,8 +367,7 @@ impl RegexBlockSearch {
                 "regexblock| vstart:{:08x} pstart:{:08x} size:{:08x}",
                 sr.vaddr, sr.paddr, sr.size
             );
-            let result = Box::new(sr);
-            search_results.push(result);
+            search_results.push(sr);
             // want to find start markers that may overlap
             // e.g. [start_marker] => [start_marker'] => [start_marker"] => [end_marker]
             pos = pos + *start_end as u64;
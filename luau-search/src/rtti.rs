@@ -0,0 +1,383 @@
+use crate::pointerpath::is_module_range;
+use binread::{BinRead, BinResult, ReadOptions};
+use mem_analysis::data_interface::DataInterface;
+use mem_analysis::pointers::XrefIndex;
+use mem_analysis::reader::VWord;
+use std::collections::HashMap;
+use std::io::{Read, Seek};
+
+use log::{debug, info};
+
+// RTTICompleteObjectLocator signatures, x64 locators hold image relative offsets
+const COL_SIGNATURE_X86: u32 = 0;
+const COL_SIGNATURE_X64: u32 = 1;
+
+const TYPE_NAME_PREFIX: &str = ".?A";
+const MAX_TYPE_NAME_LEN: usize = 4096;
+
+/// `RTTICompleteObjectLocator`, the vtable of a class with RTTI is preceded by a pointer to it.
+#[derive(Debug, BinRead, Clone, PartialEq, Eq)]
+pub struct CompleteObjectLocator {
+    pub signature: u32,
+    /// Offset of the vtable pointer in the complete object.
+    pub offset: u32,
+    pub cd_offset: u32,
+    pub type_descriptor: u32,
+    pub class_descriptor: u32,
+    #[br(if(signature == COL_SIGNATURE_X64))]
+    pub self_rva: Option<u32>,
+}
+
+/// `TypeDescriptor`, the `type_info` of the class followed by its decorated name.
+#[derive(Debug, BinRead, Clone, PartialEq)]
+#[br(import(word_sz: u8))]
+pub struct TypeDescriptor {
+    #[br(args(word_sz))]
+    pub vftable: VWord,
+    #[br(args(word_sz))]
+    pub spare: VWord,
+    #[br(parse_with = read_type_name)]
+    pub name: String,
+}
+
+// the NUL terminated decorated name, giving up after `MAX_TYPE_NAME_LEN` bytes instead of
+// reading on through memory that holds no terminator
+fn read_type_name<R: Read + Seek>(
+    reader: &mut R,
+    _options: &ReadOptions,
+    _args: (),
+) -> BinResult<String> {
+    let pos = reader.stream_position()?;
+    let mut name: Vec<u8> = Vec::new();
+    let mut chunk = [0u8; 64];
+    while name.len() < MAX_TYPE_NAME_LEN {
+        let want = chunk.len().min(MAX_TYPE_NAME_LEN - name.len());
+        let len = reader.read(&mut chunk[..want])?;
+        if len == 0 {
+            break;
+        }
+        if let Some(end) = chunk[..len].iter().position(|c| *c == 0) {
+            name.extend_from_slice(&chunk[..end]);
+            return Ok(String::from_utf8_lossy(&name).into_owned());
+        }
+        name.extend_from_slice(&chunk[..len]);
+    }
+    Err(binread::Error::AssertFail {
+        pos,
+        message: format!("No type name of at most {} bytes", MAX_TYPE_NAME_LEN),
+    })
+}
+
+/// A vtable whose RTTI chain resolved to a class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RttiClass {
+    pub vtable: u64,
+    pub locator: u64,
+    pub type_descriptor: u64,
+    /// Offset of the vtable pointer in the complete object.
+    pub offset: u32,
+    pub mangled: String,
+    /// The demangled class name, the decorated name when it could not be demangled.
+    pub name: String,
+}
+
+/// An object found through a pointer to a vtable with RTTI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectType {
+    pub object: u64,
+    /// Where the vtable pointer is, `object + class.offset`.
+    pub vptr: u64,
+    pub section: String,
+    pub class: RttiClass,
+}
+
+/// Follows the MSVC RTTI chain (`vtable[-1]` -> `RTTICompleteObjectLocator` ->
+/// `TypeDescriptor`) of vtables in module sections.
+#[derive(Debug)]
+pub struct RttiResolver<'a> {
+    di: &'a DataInterface,
+    // vtable -> class, `None` for addresses without RTTI
+    cache: HashMap<u64, Option<RttiClass>>,
+}
+
+impl<'a> RttiResolver<'a> {
+    pub fn new(di: &'a DataInterface) -> Self {
+        RttiResolver {
+            di,
+            cache: HashMap::new(),
+        }
+    }
+
+    fn in_module(&self, vaddr: u64) -> bool {
+        match self.di.mem_ranges.vmem_ranges.get(&vaddr) {
+            Some(mr) => is_module_range(mr),
+            None => false,
+        }
+    }
+
+    pub fn resolve_vtable(&mut self, vtable: u64) -> Option<RttiClass> {
+        // most pointers lead into the heaps, only vtables in modules are worth caching
+        if !self.in_module(vtable) {
+            return None;
+        }
+        if let Some(cached) = self.cache.get(&vtable) {
+            return cached.clone();
+        }
+        let class = self.read_rtti(vtable);
+        self.cache.insert(vtable, class.clone());
+        class
+    }
+
+    fn read_rtti(&self, vtable: u64) -> Option<RttiClass> {
+        let word_sz = self.di.vmem_info.word_sz;
+        let locator = self
+            .di
+            .read_word_size_value_at_vaddr(vtable.checked_sub(word_sz as u64)?)?
+            .value;
+        if !self.in_module(locator) {
+            return None;
+        }
+        let col: CompleteObjectLocator = self.di.read_struct(locator)?;
+        let type_descriptor = match (word_sz, col.signature, col.self_rva) {
+            (8, COL_SIGNATURE_X64, Some(self_rva)) => {
+                let image_base = locator.checked_sub(self_rva as u64)?;
+                image_base.checked_add(col.type_descriptor as u64)?
+            }
+            (4, COL_SIGNATURE_X86, None) => col.type_descriptor as u64,
+            _ => return None,
+        };
+        if !self.in_module(type_descriptor) {
+            return None;
+        }
+        let td: TypeDescriptor = self.di.read_struct(type_descriptor)?;
+        let mangled = td.name;
+        if !mangled.starts_with(TYPE_NAME_PREFIX) {
+            return None;
+        }
+        let name = demangle_type_name(&mangled).unwrap_or_else(|| mangled.clone());
+        debug!("vtable {:08x} belongs to {}.", vtable, name);
+        Some(RttiClass {
+            vtable,
+            locator,
+            type_descriptor,
+            offset: col.offset,
+            mangled,
+            name,
+        })
+    }
+
+    /// The objects holding a pointer to a vtable with RTTI, for every pointer in `xrefs`.
+    pub fn annotate_objects(&mut self, xrefs: &XrefIndex) -> Vec<ObjectType> {
+        let mut objects = Vec::new();
+        for (source, sink) in xrefs.pointers() {
+            let class = match self.resolve_vtable(sink) {
+                Some(class) => class,
+                None => continue,
+            };
+            let object = match source.checked_sub(class.offset as u64) {
+                Some(object) => object,
+                None => continue,
+            };
+            let section = self.di.get_vaddr_section_name(source).unwrap_or_default();
+            objects.push(ObjectType {
+                object,
+                vptr: source,
+                section,
+                class,
+            });
+        }
+        let classes = self.cache.values().filter(|c| c.is_some()).count();
+        info!(
+            "Found {} objects of {} classes with RTTI.",
+            objects.len(),
+            classes
+        );
+        objects
+    }
+}
+
+/// Demangle an MSVC RTTI type name, e.g. `.?AVInstance@RBX@@` to `RBX::Instance`.  Nested
+/// names, templates with simple arguments and back references are handled, `None` is
+/// returned for anything else.
+pub fn demangle_type_name(mangled: &str) -> Option<String> {
+    let rest = mangled.strip_prefix(TYPE_NAME_PREFIX)?;
+    let mut demangler = Demangler {
+        input: rest.as_bytes(),
+        pos: 0,
+        names: Vec::new(),
+    };
+    let name = match demangler.bump()? {
+        // class, struct or union
+        b'V' | b'U' | b'T' => demangler.qualified_name()?,
+        // enum with its underlying type
+        b'W' => {
+            demangler.bump()?;
+            demangler.qualified_name()?
+        }
+        _ => return None,
+    };
+    if demangler.pos != demangler.input.len() {
+        return None;
+    }
+    Some(name)
+}
+
+struct Demangler<'a> {
+    input: &'a [u8],
+    pos: usize,
+    // names that can be referred to by a single digit
+    names: Vec<String>,
+}
+
+impl<'a> Demangler<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<u8> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn consume(&mut self, prefix: &[u8]) -> bool {
+        if self.input[self.pos..].starts_with(prefix) {
+            self.pos += prefix.len();
+            return true;
+        }
+        false
+    }
+
+    // characters up to the next '@'
+    fn simple_name(&mut self) -> Option<String> {
+        let end = self.input[self.pos..].iter().position(|c| *c == b'@')?;
+        let name = std::str::from_utf8(&self.input[self.pos..self.pos + end]).ok()?;
+        self.pos += end + 1;
+        if name.is_empty() {
+            return None;
+        }
+        Some(name.to_string())
+    }
+
+    // innermost name first, terminated by '@'
+    fn qualified_name(&mut self) -> Option<String> {
+        let mut parts: Vec<String> = Vec::new();
+        while !self.consume(b"@") {
+            parts.push(self.name_fragment()?);
+        }
+        if parts.is_empty() {
+            return None;
+        }
+        parts.reverse();
+        Some(parts.join("::"))
+    }
+
+    fn name_fragment(&mut self) -> Option<String> {
+        let c = self.peek()?;
+        if c.is_ascii_digit() {
+            self.pos += 1;
+            return self.names.get((c - b'0') as usize).cloned();
+        }
+        if self.consume(b"?$") {
+            // template arguments have their own back references
+            let outer = std::mem::take(&mut self.names);
+            let name = self.simple_name()?;
+            self.names.push(name.clone());
+            let mut args: Vec<String> = Vec::new();
+            while !self.consume(b"@") {
+                args.push(self.type_code()?);
+            }
+            self.names = outer;
+            let name = format!("{}<{}>", name, args.join(","));
+            self.names.push(name.clone());
+            return Some(name);
+        }
+        if self.consume(b"?A") {
+            // the namespace key (e.g. `0x1f2e3d4c`) takes a back reference slot
+            self.simple_name()?;
+            let name = "`anonymous namespace'".to_string();
+            self.names.push(name.clone());
+            return Some(name);
+        }
+        let name = self.simple_name()?;
+        self.names.push(name.clone());
+        Some(name)
+    }
+
+    fn type_code(&mut self) -> Option<String> {
+        let name = match self.bump()? {
+            b'C' => "signed char",
+            b'D' => "char",
+            b'E' => "unsigned char",
+            b'F' => "short",
+            b'G' => "unsigned short",
+            b'H' => "int",
+            b'I' => "unsigned int",
+            b'J' => "long",
+            b'K' => "unsigned long",
+            b'M' => "float",
+            b'N' => "double",
+            b'O' => "long double",
+            b'X' => "void",
+            b'_' => match self.bump()? {
+                b'N' => "bool",
+                b'J' => "__int64",
+                b'K' => "unsigned __int64",
+                b'W' => "wchar_t",
+                b'S' => "char16_t",
+                b'U' => "char32_t",
+                _ => return None,
+            },
+            b'P' | b'Q' => return self.indirect_type("*"),
+            b'A' => return self.indirect_type("&"),
+            b'V' | b'U' | b'T' => return self.qualified_name(),
+            b'W' => {
+                self.bump()?;
+                return self.qualified_name();
+            }
+            b'$' => {
+                if !self.consume(b"0") {
+                    return None;
+                }
+                return self.encoded_number().map(|n| n.to_string());
+            }
+            _ => return None,
+        };
+        Some(name.to_string())
+    }
+
+    fn indirect_type(&mut self, suffix: &str) -> Option<String> {
+        // __ptr64
+        self.consume(b"E");
+        let qualifier = match self.bump()? {
+            b'A' => "",
+            b'B' => "const ",
+            b'C' => "volatile ",
+            b'D' => "const volatile ",
+            _ => return None,
+        };
+        let pointee = self.type_code()?;
+        Some(format!("{}{}{}", qualifier, pointee, suffix))
+    }
+
+    // 0-9 for 1-10, otherwise hex digits written as A-P and terminated by '@'
+    fn encoded_number(&mut self) -> Option<i64> {
+        let negative = self.consume(b"?");
+        let c = self.bump()?;
+        let value = if c.is_ascii_digit() {
+            (c - b'0') as i64 + 1
+        } else {
+            let mut value: i64 = 0;
+            let mut c = c;
+            while c != b'@' {
+                if !(b'A'..=b'P').contains(&c) {
+                    return None;
+                }
+                value = value.checked_mul(16)?.checked_add((c - b'A') as i64)?;
+                c = self.bump()?;
+            }
+            value
+        };
+        Some(if negative { -value } else { value })
+    }
+}
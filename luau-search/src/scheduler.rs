@@ -0,0 +1,143 @@
+use crate::error::SearchError;
+use mem_analysis::memory::MemRange;
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::Arc;
+
+use log::{debug, error};
+
+/// Bytes of a memory range scanned as one unit of work.
+pub const DEFAULT_CHUNK_SIZE: u64 = 0x100_0000;
+
+/// A slice of a memory range for one search task.  The task looks for matches starting at the
+/// offsets `[start, end)` from the beginning of the range, but reads past `end` into the rest
+/// of the range, so a match that straddles two chunks is found once, by the chunk it starts in.
+#[derive(Debug, Clone)]
+pub struct ScanChunk {
+    pub mr: Box<MemRange>,
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ScanChunk {
+    /// The whole range as a single chunk.
+    pub fn whole(mr: &MemRange) -> Self {
+        ScanChunk {
+            mr: Box::new(mr.clone()),
+            start: 0,
+            end: mr.vsize.max(mr.size),
+        }
+    }
+}
+
+/// Splits memory ranges into chunks of about `chunk_size` bytes and runs a search over them on
+/// a work-stealing thread pool, so one large heap no longer keeps a single thread busy while
+/// the others sit idle.  The pool is started once and reused by every `run`, clones of the
+/// scheduler share it.
+#[derive(Debug, Clone)]
+pub struct ChunkScheduler {
+    pub chunk_size: u64,
+    num_threads: usize,
+    // `None` runs on the global rayon pool
+    pool: Option<Arc<ThreadPool>>,
+}
+
+impl ChunkScheduler {
+    /// A scheduler with a pool of `num_threads` threads, `0` uses one per core.  If the threads
+    /// can not be started the searches run on the global rayon pool instead.
+    pub fn new(num_threads: usize) -> Self {
+        let pool = match ThreadPoolBuilder::new().num_threads(num_threads).build() {
+            Ok(pool) => Some(Arc::new(pool)),
+            Err(e) => {
+                error!("Failed to start a pool of {} search threads: {}", num_threads, e);
+                None
+            }
+        };
+        ChunkScheduler {
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            num_threads,
+            pool,
+        }
+    }
+
+    /// The number of threads asked for in `new`.
+    pub fn num_threads(&self) -> usize {
+        self.num_threads
+    }
+
+    /// Chunks covering `mrs`, their boundaries are multiples of `incr` from the start of the
+    /// range so the chunks step through the same offsets as a scan of the whole range.
+    pub fn split(&self, mrs: &[Box<MemRange>], incr: u64) -> Vec<ScanChunk> {
+        let incr = incr.max(1);
+        let chunk_size = (self.chunk_size / incr).max(1) * incr;
+        let mut chunks = Vec::new();
+        for mr in mrs.iter() {
+            let len = mr.vsize.max(mr.size);
+            let mut start: u64 = 0;
+            loop {
+                let end = start.saturating_add(chunk_size).min(len);
+                chunks.push(ScanChunk {
+                    mr: mr.clone(),
+                    start,
+                    end,
+                });
+                if end >= len {
+                    break;
+                }
+                start = end;
+            }
+        }
+        chunks
+    }
+
+    /// Run `search` over every chunk of `mrs`.  A chunk that fails is logged and skipped like a
+    /// range that can not be read, a panic in any of them fails the whole search.
+    pub fn run<F>(
+        &self,
+        what: &str,
+        mrs: &[Box<MemRange>],
+        incr: u64,
+        search: F,
+    ) -> Result<(), SearchError>
+    where
+        F: Fn(&ScanChunk) -> Result<(), SearchError> + Sync,
+    {
+        let chunks = self.split(mrs, incr);
+        let threads = match &self.pool {
+            Some(pool) => pool.current_num_threads(),
+            None => rayon::current_num_threads(),
+        };
+        debug!(
+            "Searching {} ranges in {} chunks for {} on {} threads.",
+            mrs.len(),
+            chunks.len(),
+            what,
+            threads
+        );
+        let scan = || {
+            chunks.par_iter().for_each(|chunk| {
+                if let Err(e) = search(chunk) {
+                    error!(
+                        "Failed to search {} at {:08x} for {}: {}",
+                        chunk.mr.name,
+                        chunk.mr.vaddr_start + chunk.start,
+                        what,
+                        e
+                    );
+                }
+            })
+        };
+        let r = catch_unwind(AssertUnwindSafe(|| match &self.pool {
+            Some(pool) => pool.install(scan),
+            None => scan(),
+        }));
+        if r.is_err() {
+            return Err(SearchError::Thread(format!(
+                "a {} search thread panicked",
+                what
+            )));
+        }
+        Ok(())
+    }
+}
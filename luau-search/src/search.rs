@@ -1,6 +1,6 @@
 use mem_analysis::data_interface::DataInterface;
 use serde::Serialize;
-use std::error::Error as StdErr;
+use crate::error::SearchError;
 use std::fmt::{Display, Formatter, Result as FmtResult};
 use std::sync::{Arc, RwLock};
 
@@ -9,25 +9,25 @@ pub trait Search {
         &mut self,
         buffer: &[u8],
         pos: u64,
-    ) -> Result<Option<SearchResult>, Box<dyn StdErr>>;
-    fn search_buffer(&mut self, buffer: &[u8]) -> Result<Vec<Box<SearchResult>>, Box<dyn StdErr>>;
+    ) -> Result<Option<SearchResult>, SearchError>;
+    fn search_buffer(&mut self, buffer: &[u8]) -> Result<Vec<Box<SearchResult>>, SearchError>;
     fn search_buffer_with_bases(
         &mut self,
         buffer: &[u8],
         phys_base: u64,
         virt_base: u64,
-    ) -> Result<Vec<Box<SearchResult>>, Box<dyn StdErr>>;
+    ) -> Result<Vec<Box<SearchResult>>, SearchError>;
 
     fn search_interface(
         &mut self,
         di: Arc<RwLock<Box<DataInterface>>>,
-    ) -> Result<Vec<Box<SearchResult>>, Box<dyn StdErr>>;
+    ) -> Result<Vec<Box<SearchResult>>, SearchError>;
     fn search_interface_with_bases(
         &mut self,
         di: Arc<RwLock<Box<DataInterface>>>,
         phys_base: u64,
         virt_base: u64,
-    ) -> Result<Vec<Box<SearchResult>>, Box<dyn StdErr>>;
+    ) -> Result<Vec<Box<SearchResult>>, SearchError>;
 }
 
 #[derive(Debug, PartialEq, Clone)]
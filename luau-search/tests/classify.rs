@@ -0,0 +1,75 @@
+use luau_search::classify::{AddressClass, PointerFilter, RegionClass};
+use mem_analysis::region::Protection;
+
+fn filter(s: &str) -> PointerFilter {
+    s.parse().unwrap()
+}
+
+fn address(class: RegionClass, perm: &str) -> AddressClass {
+    AddressClass {
+        class,
+        section: "section".to_string(),
+        perm: Protection::from_perm_str(perm),
+    }
+}
+
+#[test]
+fn filters_parse_source_sink_and_perm() {
+    assert_eq!(
+        filter("heap->image:r--"),
+        PointerFilter {
+            source: Some(RegionClass::Heap),
+            sink: Some(RegionClass::Image),
+            sink_perm: Some(Protection::READ),
+        }
+    );
+    assert_eq!(
+        filter(" stack -> lua_page "),
+        PointerFilter {
+            source: Some(RegionClass::Stack),
+            sink: Some(RegionClass::LuaPage),
+            sink_perm: None,
+        }
+    );
+    // `*` and an empty class match anything
+    assert_eq!(filter("*->"), PointerFilter::default());
+    assert_eq!(
+        filter("->Module:rwx").sink_perm,
+        Some(Protection::READ | Protection::WRITE | Protection::EXECUTE)
+    );
+    assert_eq!(filter("*->*:---").sink_perm, Some(Protection::NONE));
+}
+
+#[test]
+fn malformed_filters_are_rejected() {
+    for s in [
+        "",
+        "heap",
+        "heap:r--",
+        "heap->nowhere",
+        "kernel->image",
+        "heap->image:",
+        "heap->image:rw-p",
+        "heap->image:read",
+        "heap->image:r--:x",
+    ] {
+        assert!(s.parse::<PointerFilter>().is_err(), "{}", s);
+    }
+}
+
+#[test]
+fn filters_match_the_sink_flags_only() {
+    let vtables = filter("heap->image:r--");
+    let heap = address(RegionClass::Heap, "rw-");
+    assert!(vtables.matches(&heap, &address(RegionClass::Image, "r--")));
+    assert!(!vtables.matches(&heap, &address(RegionClass::Image, "r-x")));
+    assert!(!vtables.matches(&heap, &address(RegionClass::Heap, "r--")));
+    assert!(!vtables.matches(&address(RegionClass::Stack, "rw-"), &heap));
+
+    // copy on write is not part of PERM
+    let cow = AddressClass {
+        perm: Protection::READ | Protection::WRITE | Protection::COPY_ON_WRITE,
+        ..address(RegionClass::Image, "")
+    };
+    assert!(filter("*->image:rw-").matches(&heap, &cow));
+}
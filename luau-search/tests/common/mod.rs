@@ -0,0 +1,31 @@
+// helpers shared by the integration tests, not every test uses all of them
+#![allow(dead_code)]
+
+use mem_analysis::data_interface::DataInterface;
+use std::sync::{Arc, RwLock};
+
+pub fn put32(b: &mut [u8], o: usize, v: u32) {
+    b[o..o + 4].copy_from_slice(&v.to_le_bytes());
+}
+
+pub fn put64(b: &mut [u8], o: usize, v: u64) {
+    b[o..o + 8].copy_from_slice(&v.to_le_bytes());
+}
+
+/// The `page_size` of the lua_Pages written by `lua_page`.
+pub const PAGE_SIZE: i32 = 0x3fe8;
+
+/// A lua_Page header of the 64-bit layout at `offset`, the list pointers are `prev` and `next`.
+pub fn lua_page(buf: &mut [u8], offset: usize, prev: u64, next: u64, block_size: i32) {
+    put64(buf, offset, prev);
+    put64(buf, offset + 8, next);
+    put32(buf, offset + 32, PAGE_SIZE as u32);
+    put32(buf, offset + 36, block_size as u32);
+    put32(buf, offset + 48, -1i32 as u32);
+    put32(buf, offset + 52, 5);
+}
+
+/// Wrap `di` the way the searches share it between threads.
+pub fn shared(di: DataInterface) -> Arc<RwLock<Box<DataInterface>>> {
+    Arc::new(RwLock::new(Box::new(di)))
+}
@@ -0,0 +1,173 @@
+use luau_search::graph::{NodeKind, PointerGraph};
+use mem_analysis::builder::AddressSpaceBuilder;
+use mem_analysis::data_interface::DataInterface;
+use mem_analysis::pointers::XrefIndex;
+
+const HEAP: u64 = 0x10000;
+const IMAGE: u64 = 0x40000;
+// lua_Pages inside the heap
+const PAGE_A: u64 = HEAP + 0x100;
+const PAGE_B: u64 = HEAP + 0x200;
+// needs escaping in both formats
+const HEAP_NAME: &str = "lua \"heap\" <&> \\x";
+
+fn address_space() -> DataInterface {
+    AddressSpaceBuilder::new()
+        .region(HEAP, "-rw-", HEAP_NAME, &[0u8; 0x1000])
+        .region(IMAGE, "-r--", "game.exe", &[0u8; 0x1000])
+        .build()
+        .unwrap()
+}
+
+fn xrefs() -> XrefIndex {
+    let mut xrefs = XrefIndex::new();
+    // heap -> image, twice
+    xrefs.add(HEAP + 0x10, IMAGE + 0x20);
+    xrefs.add(HEAP + 0x18, IMAGE + 0x40);
+    // page a -> page b, twice
+    xrefs.add(PAGE_A + 0x08, PAGE_B + 0x10);
+    xrefs.add(PAGE_A + 0x10, PAGE_B + 0x08);
+    // page b -> the rest of the heap
+    xrefs.add(PAGE_B + 0x20, HEAP + 0x8);
+    // out of the address space, the source still becomes a node
+    xrefs.add(IMAGE + 0x8, 0x9999_0000);
+    xrefs
+}
+
+fn edge(graph: &PointerGraph, from: &str, to: &str) -> Option<u64> {
+    graph
+        .edges
+        .get(&(from.to_string(), to.to_string()))
+        .copied()
+}
+
+#[test]
+fn mem_ranges_collapse_into_one_node_each() {
+    let graph = PointerGraph::from_mem_ranges(&address_space(), &xrefs());
+    let ids: Vec<&String> = graph.nodes.keys().collect();
+    assert_eq!(ids, ["r10000", "r40000"]);
+    assert!(graph
+        .nodes
+        .values()
+        .all(|n| n.kind == NodeKind::MemRange && n.size == 0x1000));
+    assert_eq!(graph.nodes["r10000"].label(), HEAP_NAME);
+
+    assert_eq!(graph.edges.len(), 2);
+    assert_eq!(edge(&graph, "r10000", "r40000"), Some(2));
+    // the pointers between and out of the pages stay inside the heap
+    assert_eq!(edge(&graph, "r10000", "r10000"), Some(3));
+}
+
+#[test]
+fn allocations_get_their_own_nodes() {
+    let allocations = [(PAGE_A, 0x100), (PAGE_B, 0x100), (HEAP + 0x800, 0)];
+    let graph = PointerGraph::from_allocations(&address_space(), &xrefs(), &allocations);
+    let ids: Vec<&String> = graph.nodes.keys().collect();
+    assert_eq!(ids, ["a10100", "a10200", "r10000", "r40000"]);
+    let page = &graph.nodes["a10200"];
+    assert_eq!(page.kind, NodeKind::Allocation);
+    assert_eq!((page.vaddr, page.size), (PAGE_B, 0x100));
+    assert_eq!(page.section, HEAP_NAME);
+    assert_eq!(page.label(), format!("{} {:08x}", HEAP_NAME, PAGE_B));
+
+    assert_eq!(graph.edges.len(), 3);
+    assert_eq!(edge(&graph, "r10000", "r40000"), Some(2));
+    assert_eq!(edge(&graph, "a10100", "a10200"), Some(2));
+    assert_eq!(edge(&graph, "a10200", "r10000"), Some(1));
+    assert_eq!(edge(&graph, "r10000", "r10000"), None);
+}
+
+// every unescaped quote of a line is closed on the same line
+fn balanced_quotes(line: &str) -> bool {
+    let mut quotes = 0;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '"' => quotes += 1,
+            _ => {}
+        }
+    }
+    quotes % 2 == 0
+}
+
+#[test]
+fn dot_output_is_well_formed() {
+    let allocations = [(PAGE_A, 0x100), (PAGE_B, 0x100)];
+    let graph = PointerGraph::from_allocations(&address_space(), &xrefs(), &allocations);
+    let mut out = Vec::new();
+    graph.write_dot(&mut out).unwrap();
+    let dot = String::from_utf8(out).unwrap();
+
+    let lines: Vec<&str> = dot.lines().collect();
+    assert_eq!(lines.first(), Some(&"digraph pointers {"));
+    assert_eq!(lines.last(), Some(&"}"));
+    let body = &lines[1..lines.len() - 1];
+    assert_eq!(body.len(), graph.nodes.len() + graph.edges.len());
+    for line in body {
+        assert!(line.ends_with("];"), "{}", line);
+        assert!(balanced_quotes(line), "{}", line);
+    }
+    assert!(dot.contains(
+        "  \"r10000\" [label=\"lua \\\"heap\\\" <&> \\\\x\", vaddr=\"00010000\", size=4096];"
+    ));
+    assert!(dot.contains("  \"a10100\" -> \"a10200\" [weight=2, label=\"2\"];"));
+}
+
+// panics on unbalanced tags, unquoted attributes and bare `&`
+fn check_xml(xml: &str) {
+    let mut open: Vec<String> = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        check_text(&rest[..start]);
+        let end = start + rest[start..].find('>').expect("unterminated tag");
+        let tag = &rest[start + 1..end];
+        assert_eq!(tag.matches('"').count() % 2, 0, "{}", tag);
+        check_text(tag);
+        if tag.starts_with('?') {
+            assert!(tag.ends_with('?'), "{}", tag);
+        } else if let Some(name) = tag.strip_prefix('/') {
+            assert_eq!(open.pop().as_deref(), Some(name));
+        } else if !tag.ends_with('/') {
+            let name = tag.split_whitespace().next().unwrap();
+            open.push(name.to_string());
+        }
+        rest = &rest[end + 1..];
+    }
+    check_text(rest);
+    assert!(open.is_empty(), "unclosed {:?}", open);
+}
+
+fn check_text(text: &str) {
+    assert!(!text.contains('<') && !text.contains('>'), "{}", text);
+    for (pos, _) in text.match_indices('&') {
+        let entity = &text[pos..];
+        assert!(
+            ["&amp;", "&lt;", "&gt;", "&quot;", "&apos;"]
+                .iter()
+                .any(|e| entity.starts_with(e)),
+            "{}",
+            text
+        );
+    }
+}
+
+#[test]
+fn graphml_output_is_well_formed() {
+    let allocations = [(PAGE_A, 0x100), (PAGE_B, 0x100)];
+    let graph = PointerGraph::from_allocations(&address_space(), &xrefs(), &allocations);
+    let mut out = Vec::new();
+    graph.write_graphml(&mut out).unwrap();
+    let xml = String::from_utf8(out).unwrap();
+
+    check_xml(&xml);
+    assert_eq!(xml.matches("<node ").count(), graph.nodes.len());
+    assert_eq!(xml.matches("<edge ").count(), graph.edges.len());
+    assert!(xml.contains("<data key=\"section\">lua &quot;heap&quot; &lt;&amp;&gt; \\x</data>"));
+    assert!(
+        xml.contains("<data key=\"label\">lua &quot;heap&quot; &lt;&amp;&gt; \\x 00010100</data>")
+    );
+    assert!(xml.contains("    <edge source=\"a10200\" target=\"r10000\">"));
+}
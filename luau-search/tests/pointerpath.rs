@@ -0,0 +1,143 @@
+mod common;
+
+use common::put64;
+use luau_search::pointer::PointerSearch;
+use luau_search::pointerpath::{PointerPath, PointerPathSearch};
+use luau_search::search::Search;
+use mem_analysis::builder::AddressSpaceBuilder;
+use mem_analysis::data_interface::DataInterface;
+use mem_analysis::pointers::XrefIndex;
+use mem_analysis::region::{MemType, RegionFilter};
+use std::sync::{Arc, RwLock};
+
+// game.exe+0x20 -> object -> (+0x18) player -> (+0x40) target, and game.exe+0x28 points
+// straight at the player when `shortcut` is set
+fn run(image_base: u64, heap: u64, shortcut: bool) -> DataInterface {
+    let object = heap + 0x100;
+    let player = heap + 0x800;
+    let mut image = vec![0u8; 0x1000];
+    put64(&mut image, 0x20, object);
+    if shortcut {
+        put64(&mut image, 0x28, player);
+    }
+    let mut bytes = vec![0u8; 0x1000];
+    put64(&mut bytes, 0x100 + 0x18, player);
+    AddressSpaceBuilder::new()
+        .region_with_type(image_base, "-r--", "game.exe", &image, MemType::Image)
+        .region(heap, "-rw-", "heap", &bytes)
+        .build()
+        .unwrap()
+}
+
+fn xrefs(di: DataInterface) -> (DataInterface, XrefIndex) {
+    let shared = Arc::new(RwLock::new(Box::new(di)));
+    let mut search = PointerSearch::new(None, None, shared.clone());
+    search.region_filter = RegionFilter::all();
+    search.max_threads = 2;
+    search.search_interface(shared.clone()).unwrap();
+    let xrefs = search.xrefs.read().unwrap().clone();
+    drop(search);
+    let di = Arc::try_unwrap(shared).unwrap().into_inner().unwrap();
+    (*di, xrefs)
+}
+
+fn path(base_offset: u64, offsets: &[u64]) -> PointerPath {
+    PointerPath {
+        module: "game.exe".to_string(),
+        base_offset,
+        offsets: offsets.to_vec(),
+    }
+}
+
+#[test]
+fn paths_lead_from_module_pointers_to_the_target() {
+    let (di, xrefs) = xrefs(run(0x1_4000_0000, 0x10000, true));
+    let target = 0x10000 + 0x800 + 0x40;
+    let paths = PointerPathSearch::new(4, 0x100).find_paths(&di, &xrefs, target);
+    assert_eq!(paths, vec![path(0x28, &[0x40]), path(0x20, &[0x18, 0x40])]);
+    assert_eq!(paths[1].to_string(), "game.exe+20 -> [+18] -> [+40]");
+    assert!(paths.iter().all(|p| p.resolves_to(&di, target)));
+
+    // the offsets are limited by max_offset and the hops by max_depth
+    assert!(PointerPathSearch::new(4, 0x20)
+        .find_paths(&di, &xrefs, target)
+        .is_empty());
+    assert_eq!(
+        PointerPathSearch::new(1, 0x100).find_paths(&di, &xrefs, target),
+        vec![path(0x28, &[0x40])]
+    );
+}
+
+#[test]
+fn stable_paths_resolve_in_every_rescan() {
+    let (di, xrefs) = xrefs(run(0x1_4000_0000, 0x10000, true));
+    let target = 0x10000 + 0x840;
+    // loaded elsewhere, the shortcut is gone in the second run
+    let rescan = run(0x1_5000_0000, 0x30000, false);
+    let paths = PointerPathSearch::default().find_stable_paths(
+        &di,
+        &xrefs,
+        target,
+        &[(&rescan, 0x30000 + 0x840)],
+    );
+    assert_eq!(paths, vec![path(0x20, &[0x18, 0x40])]);
+    assert_eq!(paths[0].resolve(&rescan), Some(0x30840));
+}
+
+const LAYERS: u64 = 10;
+
+// a ladder of `LAYERS` pairs of words in the heap, both words of a layer point at the first
+// word of the layer below, so the number of paths doubles with every layer.  game.exe+0x20
+// and +0x28 point at the top layer, unless `broken` sends the first into unmapped memory.
+fn ladder(image_base: u64, heap: u64, broken: bool) -> DataInterface {
+    let mut image = vec![0u8; 0x1000];
+    let top = heap + 0x100 * LAYERS;
+    put64(&mut image, 0x20, if broken { 0xdead_0000 } else { top });
+    put64(&mut image, 0x28, top);
+    let mut bytes = vec![0u8; 0x1000];
+    for layer in 1..=LAYERS as usize {
+        let below = heap + 0x100 * (layer as u64 - 1);
+        put64(&mut bytes, 0x100 * layer, below);
+        put64(&mut bytes, 0x100 * layer + 8, below);
+    }
+    AddressSpaceBuilder::new()
+        .region_with_type(image_base, "-r--", "game.exe", &image, MemType::Image)
+        .region(heap, "-rw-", "heap", &bytes)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn the_visit_budget_bounds_the_expansion() {
+    let heap = 0x10000;
+    let (di, xrefs) = xrefs(ladder(0x1_4000_0000, heap, false));
+    let mut search = PointerPathSearch::new(LAYERS as usize + 1, 0x10);
+    search.max_results = usize::MAX;
+    // 2 module pointers times 2^LAYERS paths down the ladder
+    assert_eq!(search.find_paths(&di, &xrefs, heap).len(), 2048);
+
+    search.max_visits = 100;
+    let paths = search.find_paths(&di, &xrefs, heap);
+    assert!(!paths.is_empty() && paths.len() < 100, "{}", paths.len());
+    assert!(paths.iter().all(|p| p.resolves_to(&di, heap)));
+
+    search.max_visits = usize::MAX;
+    search.max_results = 10;
+    assert_eq!(search.find_paths(&di, &xrefs, heap).len(), 10);
+}
+
+#[test]
+fn prefixes_that_do_not_resolve_in_a_rescan_are_pruned() {
+    let heap = 0x10000;
+    let (di, xrefs) = xrefs(ladder(0x1_4000_0000, heap, false));
+    let rescan = ladder(0x1_5000_0000, heap, true);
+    let mut search = PointerPathSearch::new(LAYERS as usize + 1, 0x10);
+    // far less than the 1024 paths below game.exe+0x20, only found if the walk leaves them
+    // at the first unreadable pointer
+    search.max_visits = 100;
+    let paths = search.find_stable_paths(&di, &xrefs, heap, &[(&rescan, heap)]);
+    assert!(!paths.is_empty());
+    assert!(paths
+        .iter()
+        .all(|p| p.base_offset == 0x28 && p.resolves_to(&rescan, heap)));
+}
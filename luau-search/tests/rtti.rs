@@ -0,0 +1,145 @@
+mod common;
+
+use common::{put32, put64};
+use luau_search::rtti::{demangle_type_name, RttiResolver};
+use mem_analysis::builder::AddressSpaceBuilder;
+use mem_analysis::pointers::XrefIndex;
+use mem_analysis::region::MemType;
+
+const IMAGE: u64 = 0x1_4000_0000;
+const HEAP: u64 = 0x10000;
+
+// an x64 RTTICompleteObjectLocator at `col` for the TypeDescriptor at `td`, and the vtable
+// at `vtable` that refers to it
+fn rtti(image: &mut [u8], vtable: usize, col: usize, td: usize) {
+    put64(image, vtable - 8, IMAGE + col as u64);
+    put32(image, col, 1);
+    put32(image, col + 12, td as u32);
+    put32(image, col + 20, col as u32);
+}
+
+#[test]
+fn objects_are_typed_through_their_vtable() {
+    let mut image = vec![0u8; 0x3000];
+    rtti(&mut image, 0x400, 0x100, 0x200);
+    image[0x210..0x223].copy_from_slice(b".?AVInstance@RBX@@\0");
+    // the name runs to the end of the image without a terminator
+    rtti(&mut image, 0x500, 0x140, 0x1000);
+    image[0x1010..0x1014].copy_from_slice(b".?AV");
+    image[0x1014..].fill(b'A');
+
+    let mut heap = vec![0u8; 0x1000];
+    put64(&mut heap, 0x40, IMAGE + 0x400);
+    put64(&mut heap, 0x100, IMAGE + 0x500);
+    put64(&mut heap, 0x200, HEAP);
+    let di = AddressSpaceBuilder::new()
+        .region_with_type(IMAGE, "-r--", "game.exe", &image, MemType::Image)
+        .region(HEAP, "-rw-", "heap", &heap)
+        .build()
+        .unwrap();
+
+    let mut xrefs = XrefIndex::new();
+    xrefs.add(HEAP + 0x40, IMAGE + 0x400);
+    xrefs.add(HEAP + 0x100, IMAGE + 0x500);
+    xrefs.add(HEAP + 0x200, HEAP);
+
+    let mut resolver = RttiResolver::new(&di);
+    let objects = resolver.annotate_objects(&xrefs);
+    assert_eq!(objects.len(), 1);
+    assert_eq!(objects[0].object, HEAP + 0x40);
+    assert_eq!(objects[0].section, "heap");
+    let class = &objects[0].class;
+    assert_eq!(class.mangled, ".?AVInstance@RBX@@");
+    assert_eq!(class.name, "RBX::Instance");
+    assert_eq!(class.locator, IMAGE + 0x100);
+    assert_eq!(class.type_descriptor, IMAGE + 0x200);
+
+    assert_eq!(resolver.resolve_vtable(IMAGE + 0x500), None);
+    assert_eq!(resolver.resolve_vtable(HEAP), None);
+    assert_eq!(
+        resolver.resolve_vtable(IMAGE + 0x400).unwrap().name,
+        "RBX::Instance"
+    );
+}
+
+fn demangled(mangled: &str) -> Option<String> {
+    demangle_type_name(mangled)
+}
+
+#[test]
+fn nested_names() {
+    assert_eq!(demangled(".?AUPlayer@@").unwrap(), "Player");
+    assert_eq!(demangled(".?AVInstance@RBX@@").unwrap(), "RBX::Instance");
+    assert_eq!(
+        demangled(".?AVScript@Lua@RBX@@").unwrap(),
+        "RBX::Lua::Script"
+    );
+    assert_eq!(demangled(".?AW4Color@Gfx@@").unwrap(), "Gfx::Color");
+}
+
+#[test]
+fn templates() {
+    assert_eq!(
+        demangled(".?AV?$vector@HV?$allocator@H@std@@@std@@").unwrap(),
+        "std::vector<int,std::allocator<int>>"
+    );
+    assert_eq!(
+        demangled(".?AV?$Ref@PEAVObject@@@@").unwrap(),
+        "Ref<Object*>"
+    );
+    assert_eq!(
+        demangled(".?AV?$Array@_N$0BA@@@").unwrap(),
+        "Array<bool,16>"
+    );
+    assert_eq!(
+        demangled(".?AV?$Map@PEBD_K@@").unwrap(),
+        "Map<const char*,unsigned __int64>"
+    );
+}
+
+#[test]
+fn back_references() {
+    // 1 and 2 refer to `Node` and `Tree` in the argument list of the template
+    assert_eq!(
+        demangled(".?AV?$Handle@VNode@Tree@@V12@@Tree@@").unwrap(),
+        "Tree::Handle<Tree::Node,Tree::Node>"
+    );
+    // a class nested in a template instance
+    assert_eq!(
+        demangled(".?AVIterator@?$List@VItem@Game@@@Game@@").unwrap(),
+        "Game::List<Game::Item>::Iterator"
+    );
+}
+
+#[test]
+fn anonymous_namespaces() {
+    assert_eq!(
+        demangled(".?AVImpl@?A0x1f2e3d4c@Engine@@").unwrap(),
+        "Engine::`anonymous namespace'::Impl"
+    );
+    // the namespace takes a back reference slot
+    assert_eq!(
+        demangled(".?AV?$Box@VImpl@?A0x1f2e3d4c@@V2@@@").unwrap(),
+        "Box<`anonymous namespace'::Impl,`anonymous namespace'>"
+    );
+}
+
+#[test]
+fn rejected_input() {
+    for mangled in [
+        "",
+        "Foo@@",
+        ".?AV",
+        ".?AVFoo",
+        ".?AVFoo@",
+        ".?AXFoo@@",
+        ".?AV@@",
+        ".?AVFoo@@trailing",
+        ".?AV?$A@V5@@@@",
+        ".?AV?$A@Z@@@",
+        ".?AV?$A@$0Q@@@",
+        ".?AV?$A@PEAZ@@@",
+    ] {
+        assert_eq!(demangled(mangled), None, "{}", mangled);
+    }
+}
@@ -0,0 +1,276 @@
+mod common;
+
+use common::{lua_page, put64, PAGE_SIZE};
+use luau_search::error::SearchError;
+use luau_search::scheduler::{ChunkScheduler, ScanChunk};
+use luau_search::search::SearchResult;
+use luau_search::{luapage, pointer};
+use mem_analysis::builder::AddressSpaceBuilder;
+use mem_analysis::data_interface::DataInterface;
+use mem_analysis::memory::MemRange;
+use mem_analysis::pointers::XrefIndex;
+use mem_analysis::region::Protection;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::thread;
+
+fn mem_range(vaddr: u64, vsize: u64, size: u64) -> Box<MemRange> {
+    Box::new(MemRange::new(
+        Protection::from_perm_str("rw-"),
+        format!("range_{:x}", vaddr),
+        vaddr,
+        0,
+        vsize,
+        size,
+        None,
+        None,
+    ))
+}
+
+fn scheduler(chunk_size: u64) -> ChunkScheduler {
+    let mut scheduler = ChunkScheduler::new(2);
+    scheduler.chunk_size = chunk_size;
+    scheduler
+}
+
+fn bounds(chunks: &[ScanChunk]) -> Vec<(u64, u64, u64)> {
+    chunks
+        .iter()
+        .map(|c| (c.mr.vaddr_start, c.start, c.end))
+        .collect()
+}
+
+#[test]
+fn split_covers_every_range() {
+    let mrs = vec![
+        mem_range(0x1000, 0x250, 0x250),
+        mem_range(0x8000, 0x80, 0x80),
+    ];
+    let chunks = scheduler(0x100).split(&mrs, 8);
+    assert_eq!(
+        bounds(&chunks),
+        vec![
+            (0x1000, 0, 0x100),
+            (0x1000, 0x100, 0x200),
+            (0x1000, 0x200, 0x250),
+            (0x8000, 0, 0x80),
+        ]
+    );
+}
+
+#[test]
+fn split_keeps_chunks_a_multiple_of_the_step() {
+    let mrs = vec![mem_range(0x1000, 0x200, 0x200)];
+    // 0x100 rounded down to a multiple of 0x30
+    let chunks = scheduler(0x100).split(&mrs, 0x30);
+    assert_eq!(
+        bounds(&chunks),
+        vec![
+            (0x1000, 0, 0xf0),
+            (0x1000, 0xf0, 0x1e0),
+            (0x1000, 0x1e0, 0x200)
+        ]
+    );
+    // a step larger than the chunk size makes chunks of one step
+    let chunks = scheduler(0x10).split(&mrs, 0x80);
+    assert_eq!(chunks.len(), 4);
+    // no step at all is treated as single bytes
+    assert_eq!(scheduler(0x100).split(&mrs, 0).len(), 2);
+}
+
+#[test]
+fn split_uses_the_larger_of_the_sizes() {
+    let mrs = vec![mem_range(0x1000, 0x180, 0x80), mem_range(0x4000, 0, 0)];
+    let chunks = scheduler(0x100).split(&mrs, 8);
+    assert_eq!(
+        bounds(&chunks),
+        vec![(0x1000, 0, 0x100), (0x1000, 0x100, 0x180), (0x4000, 0, 0)]
+    );
+    assert_eq!(ScanChunk::whole(&mrs[0]).end, 0x180);
+}
+
+#[test]
+fn run_visits_every_chunk_and_skips_failures() {
+    let mrs = vec![mem_range(0x1000, 0x1000, 0x1000)];
+    let visited = AtomicUsize::new(0);
+    scheduler(0x100)
+        .run("test", &mrs, 8, |chunk| {
+            visited.fetch_add(1, Ordering::SeqCst);
+            if chunk.start == 0x300 {
+                return Err(SearchError::unsupported("unreadable chunk"));
+            }
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(visited.load(Ordering::SeqCst), 0x10);
+
+    let r = scheduler(0x100).run("test", &mrs, 8, |chunk| {
+        if chunk.start == 0x500 {
+            panic!("search bug");
+        }
+        Ok(())
+    });
+    assert!(matches!(r, Err(SearchError::Thread(_))));
+}
+
+#[test]
+fn runs_reuse_the_pool_of_the_scheduler() {
+    let mrs = vec![mem_range(0x1000, 0x1000, 0x1000)];
+    let scheduler = scheduler(0x100);
+    assert_eq!(scheduler.num_threads(), 2);
+    let threads = Mutex::new(HashSet::new());
+    // a pool per run would start new threads every time
+    for _ in 0..5 {
+        scheduler
+            .clone()
+            .run("test", &mrs, 8, |_chunk| {
+                threads.lock().unwrap().insert(thread::current().id());
+                Ok(())
+            })
+            .unwrap();
+    }
+    let threads = threads.into_inner().unwrap();
+    assert!(!threads.is_empty() && threads.len() <= 2, "{:?}", threads);
+}
+
+// the sorted sources, the xrefs and the comments of a pointer search
+type PointerScan = (Vec<u64>, XrefIndex, BTreeMap<u64, Box<pointer::Comment>>);
+
+// what `perform_search_chunk` finds in `chunks`
+fn pointer_scan(di: &DataInterface, chunks: &[ScanChunk]) -> PointerScan {
+    let results: RwLock<Vec<Box<SearchResult>>> = RwLock::new(Vec::new());
+    let comments = RwLock::new(Box::new(BTreeMap::new()));
+    let xrefs = RwLock::new(XrefIndex::new());
+    let src_to_sinks = RwLock::new(HashMap::new());
+    let sink_values = RwLock::new(HashMap::new());
+    for chunk in chunks.iter() {
+        pointer::perform_search_chunk(
+            di,
+            chunk,
+            &results,
+            &comments,
+            &xrefs,
+            &src_to_sinks,
+            &sink_values,
+        )
+        .unwrap();
+    }
+    let mut sources: Vec<u64> = results
+        .into_inner()
+        .unwrap()
+        .iter()
+        .map(|r| r.vaddr)
+        .collect();
+    sources.sort();
+    (
+        sources,
+        xrefs.into_inner().unwrap(),
+        *comments.into_inner().unwrap(),
+    )
+}
+
+#[test]
+fn chunked_pointer_search_matches_a_whole_range_scan() {
+    let base = 0x10000u64;
+    let mut heap = vec![0u8; 0x3000];
+    for (idx, word) in heap.chunks_exact_mut(8).enumerate() {
+        if idx % 3 == 0 {
+            let sink = base + (idx as u64 * 0x38) % 0x3000;
+            word.copy_from_slice(&sink.to_le_bytes());
+        }
+    }
+    let di = AddressSpaceBuilder::new()
+        .region(base, "-rw-", "heap", &heap)
+        .build()
+        .unwrap();
+    let mrs = vec![di.mem_ranges.get_vaddr_range(base).unwrap()];
+
+    let whole = pointer_scan(&di, &[ScanChunk::whole(&mrs[0])]);
+    let chunked = pointer_scan(&di, &scheduler(0x100).split(&mrs, 8));
+    assert_eq!(whole.0.len(), 0x3000 / 8 / 3);
+    assert_eq!(chunked, whole);
+}
+
+#[test]
+fn the_word_at_the_end_of_a_chunk_is_read_past_it() {
+    let base = 0x10000u64;
+    let mut heap = vec![0u8; 0x1000];
+    // straddles the end of the first chunk
+    put64(&mut heap, 0x100, base + 0x800);
+    put64(&mut heap, 0x108, base + 0x10);
+    put64(&mut heap, 0xff8, base);
+    let di = AddressSpaceBuilder::new()
+        .region(base, "-rw-", "heap", &heap)
+        .build()
+        .unwrap();
+    let mr = di.mem_ranges.get_vaddr_range(base).unwrap();
+    let chunk = |start: u64, end: u64| ScanChunk {
+        mr: mr.clone(),
+        start,
+        end,
+    };
+
+    let whole = pointer_scan(&di, &[ScanChunk::whole(&mr)]);
+    assert_eq!(whole.0, vec![base + 0x100, base + 0x108, base + 0xff8]);
+    // the chunks still start at every word of the range
+    let chunked = pointer_scan(&di, &[chunk(0, 0x104), chunk(0x108, 0x1000)]);
+    assert_eq!(chunked, whole);
+    // a chunk past the data of the range is skipped, not an error
+    assert!(pointer_scan(&di, &[chunk(0x1000, 0x2000)]).0.is_empty());
+}
+
+// the sorted `(vaddr, size)` of the pages and their comments
+type PageScan = (Vec<(u64, u64)>, BTreeMap<u64, Box<luapage::Comment>>);
+
+// what `perform_search_chunk` finds in `chunks`
+fn lua_page_scan(di: &DataInterface, chunks: &[ScanChunk]) -> PageScan {
+    let results: RwLock<Vec<Box<SearchResult>>> = RwLock::new(Vec::new());
+    let comments = RwLock::new(Box::new(BTreeMap::new()));
+    for chunk in chunks.iter() {
+        luapage::perform_search_chunk(di, chunk, Some(0x400), &results, &comments).unwrap();
+    }
+    let mut pages: Vec<(u64, u64)> = results
+        .into_inner()
+        .unwrap()
+        .iter()
+        .map(|r| (r.vaddr, r.size))
+        .collect();
+    pages.sort();
+    (pages, *comments.into_inner().unwrap())
+}
+
+#[test]
+fn chunked_lua_page_search_matches_a_whole_range_scan() {
+    let base = 0x20_0000u64;
+    let mut heap = vec![0u8; 0x10000];
+    // the page_size field 0x20 into the header is the first word of the second chunk
+    lua_page(&mut heap, 0x0fe0, 0, 0, 0x30);
+    // the field is in the seventh chunk, the header starts in the sixth
+    lua_page(&mut heap, 0x5ff0, base + 0x0fe0, 0, 0x30);
+    // the field is the last word of the ninth chunk, the header ends in the tenth
+    lua_page(&mut heap, 0x8fd8, 0, base + 0x5ff0, 0x30);
+    let di = AddressSpaceBuilder::new()
+        .region(base, "-rw-", "lua heap", &heap)
+        .build()
+        .unwrap();
+    let mrs = vec![di.mem_ranges.get_vaddr_range(base).unwrap()];
+
+    let whole = lua_page_scan(&di, &[ScanChunk::whole(&mrs[0])]);
+    let page_size = 56 + PAGE_SIZE as u64;
+    assert_eq!(
+        whole.0,
+        vec![
+            (base + 0x0fe0, page_size),
+            (base + 0x5ff0, page_size),
+            (base + 0x8fd8, page_size)
+        ]
+    );
+    let chunked = lua_page_scan(&di, &scheduler(0x1000).split(&mrs, 8));
+    assert_eq!(chunked, whole);
+
+    let comment = &chunked.1[&(base + 0x5ff0)];
+    assert_eq!(comment.prev, base + 0x0fe0);
+    assert_eq!((comment.vaddr, comment.vaddr_base), (base + 0x5ff0, base));
+    assert_eq!(comment.paddr - comment.paddr_base, 0x5ff0);
+}
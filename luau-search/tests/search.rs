@@ -0,0 +1,160 @@
+mod common;
+
+use common::{lua_page, put64, shared, PAGE_SIZE};
+use luau_search::luapage::LuaPageSearch;
+use luau_search::pointer::PointerSearch;
+use luau_search::regexblock::RegexBlockSearch;
+use luau_search::search::{Search, SearchResult};
+use mem_analysis::builder::AddressSpaceBuilder;
+use mem_analysis::data_interface::DataInterface;
+use mem_analysis::region::MemType;
+
+const HEAP: u64 = 0x10000;
+const IMAGE: u64 = 0x1_4000_0000;
+const LUA_HEAP: u64 = 0x20_0000;
+
+// a writable heap with pointers into itself and into a read only image, which also points
+// back into the heap
+fn pointer_space() -> DataInterface {
+    let mut heap = vec![0u8; 0x1000];
+    put64(&mut heap, 0x08, HEAP + 0x100);
+    put64(&mut heap, 0x10, IMAGE);
+    put64(&mut heap, 0x18, 0x4141_4141_4141_4141);
+    // misaligned, the search steps over it
+    put64(&mut heap, 0x24, HEAP);
+    put64(&mut heap, 0xff8, HEAP + 0x8);
+    let mut image = vec![0u8; 0x1000];
+    put64(&mut image, 0x20, HEAP + 0x8);
+    AddressSpaceBuilder::new()
+        .region(HEAP, "-rw-", "heap", &heap)
+        .region_with_type(IMAGE, "-r--", "game.exe", &image, MemType::Image)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn pointer_search_finds_aligned_pointers_in_writable_memory() {
+    let di = shared(pointer_space());
+    let mut search = PointerSearch::new(None, None, di.clone());
+    search.max_threads = 2;
+    let results = search.search_interface(di).unwrap();
+
+    let mut sources: Vec<u64> = results.iter().map(|r| r.vaddr).collect();
+    sources.sort();
+    assert_eq!(sources, vec![HEAP + 0x08, HEAP + 0x10, HEAP + 0xff8]);
+    assert!(results
+        .iter()
+        .all(|r| r.section_name == "heap" && r.size == 8));
+
+    let comments = search.get_comments().unwrap();
+    let to_image = comments.iter().find(|c| c.vaddr == HEAP + 0x10).unwrap();
+    assert_eq!(to_image.sink_vaddr, IMAGE);
+    assert_eq!(to_image.sink_vaddr_base, IMAGE);
+    // the first word of the heap page the pointer leads to
+    let to_heap = comments.iter().find(|c| c.vaddr == HEAP + 0xff8).unwrap();
+    assert_eq!(to_heap.sink_value, Some(HEAP + 0x100));
+
+    let xrefs = search.xrefs.read().unwrap();
+    assert_eq!(xrefs.len(), 3);
+    assert_eq!(xrefs.xrefs_to(HEAP + 0x8), &[HEAP + 0xff8]);
+    assert_eq!(xrefs.xrefs_from(HEAP + 0x10), Some(IMAGE));
+}
+
+#[test]
+fn threaded_and_single_threaded_pointer_searches_fill_the_same_indexes() {
+    let di = shared(pointer_space());
+    let mut single = PointerSearch::new(None, None, di.clone());
+    let single_results = single.perform_search_with_interface(di.clone()).unwrap();
+    let mut threaded = PointerSearch::new(None, None, di.clone());
+    threaded.max_threads = 2;
+    let threaded_results = threaded.search_interface(di).unwrap();
+
+    let found = |results: &[Box<SearchResult>]| {
+        let mut found: Vec<(u64, Option<Vec<u8>>)> =
+            results.iter().map(|r| (r.vaddr, r.data.clone())).collect();
+        found.sort();
+        found
+    };
+    assert_eq!(found(&threaded_results), found(&single_results));
+
+    // the sink at HEAP + 0x8 holds a pointer too, both record it as a source of its own
+    let single_xrefs = single.xrefs.read().unwrap();
+    assert_eq!(single_xrefs.xrefs_from(HEAP + 0x8), Some(HEAP + 0x100));
+    assert_eq!(*threaded.xrefs.read().unwrap(), *single_xrefs);
+    assert_eq!(threaded.src_to_sinks, single.src_to_sinks);
+    assert_eq!(threaded.sink_values, single.sink_values);
+    assert_eq!(threaded.sink_values.get(&IMAGE), Some(&Some(0)));
+
+    let threaded_comments = threaded.shared_comments.read().unwrap();
+    assert_eq!(**threaded_comments, *single.comments);
+}
+
+#[test]
+fn lua_page_search_validates_the_headers() {
+    let mut heap = vec![0u8; 0x20000];
+    lua_page(&mut heap, 0x1000, 0, LUA_HEAP + 0x5000, 0x30);
+    lua_page(&mut heap, 0x5000, LUA_HEAP + 0x1000, 0, 0x30);
+    // the list pointers lead nowhere
+    lua_page(&mut heap, 0x9000, 0x4141_4141_4141_4141, 0, 0x30);
+    // blocks larger than the search allows
+    lua_page(&mut heap, 0xd000, 0, 0, 0x1000);
+    let di = shared(
+        AddressSpaceBuilder::new()
+            .region(LUA_HEAP, "-rw-", "lua heap", &heap)
+            .build()
+            .unwrap(),
+    );
+
+    let mut search = LuaPageSearch::new(None, None, di.clone(), Some(0x400), None);
+    search.max_threads = 2;
+    let results = search.search_interface(di).unwrap();
+    let mut pages: Vec<(u64, u64)> = results.iter().map(|r| (r.vaddr, r.size)).collect();
+    pages.sort();
+    assert_eq!(
+        pages,
+        vec![
+            (LUA_HEAP + 0x1000, 56 + PAGE_SIZE as u64),
+            (LUA_HEAP + 0x5000, 56 + PAGE_SIZE as u64)
+        ]
+    );
+
+    let comments = search.shared_comments.read().unwrap();
+    let first = comments.get(&(LUA_HEAP + 0x1000)).unwrap();
+    assert_eq!(first.next, LUA_HEAP + 0x5000);
+    assert_eq!(first.block_size, 0x30);
+    assert_eq!(first.busy_blocks, 5);
+    assert_eq!(first.free_next, -1);
+}
+
+#[test]
+fn regex_block_search_finds_every_block() {
+    let text = b"junk<roblox a='1'>payload</roblox>junk<roblox>x</roblox><roblox unterminated";
+    let di = shared(
+        AddressSpaceBuilder::new()
+            .region(0x7000, "-rw-", "xml", text)
+            .build()
+            .unwrap(),
+    );
+
+    let mut search = RegexBlockSearch::create(&None, &None, None, None, None, None, None).unwrap();
+    let results = search.search_interface(di.clone()).unwrap();
+    let blocks: Vec<(u64, u64)> = results
+        .iter()
+        .map(|r| (r.boundary_offset, r.size))
+        .collect();
+    assert_eq!(blocks, vec![(4, 30), (38, 18)]);
+    assert_eq!(
+        results[1].digest,
+        format!("{:x}", md5::compute(b"<roblox>x</roblox>"))
+    );
+
+    let mut search = RegexBlockSearch::create(&None, &None, None, None, None, None, None).unwrap();
+    let results = search
+        .search_interface_with_bases(di, 0x100, 0x7000)
+        .unwrap();
+    assert_eq!(results[0].vaddr, 0x7004);
+    assert_eq!(results[0].paddr, 0x104);
+
+    let bad = RegexBlockSearch::create(&Some("(".to_string()), &None, None, None, None, None, None);
+    assert!(bad.is_err());
+}
@@ -10,12 +10,19 @@ use regex::bytes::Regex;
 use regex::RegexBuilder;
 use serde_json::json;
 
+use luau_search::classify::{Classifier, PointerFilter};
+use luau_search::graph::PointerGraph;
 use luau_search::pointer::{PointerSearch};
+use luau_search::pointerpath::{PointerPathSearch, DEFAULT_MAX_DEPTH, DEFAULT_MAX_OFFSET};
 use luau_search::luapage::{LuaPageSearch};
+use luau_search::rtti::RttiResolver;
 use luau_search::regexblock::{RegexBlockSearch, ROBLOX_REGEX_END, ROBLOX_REGEX_START};
 use luau_search::search::{Search, SearchResult};
-use mem_analysis::data_interface::DataInterface;
+use mem_analysis::data_interface::{Arch, DataInterface};
+use mem_analysis::error::MemError;
+use mem_analysis::index::{default_index_path, DumpFingerprint};
 use mem_analysis::radare::RadareMemoryInfos;
+use mem_analysis::region::RegionFilter;
 
 /// Search for a pattern in a file and display the lines that contain it.
 #[derive(Parser)]
@@ -33,6 +40,48 @@ struct Arguments {
     #[arg(short='l', long, action, value_name = "FLAG")]
     luapage_search: bool,
 
+    /// with -p, write the pointers between memory ranges (between lua_Pages when -l is given
+    /// too) to pointer_graph.dot and pointer_graph.graphml in the output path
+    #[arg(long, action, value_name = "FLAG", requires = "pointer_search")]
+    pointer_graph: bool,
+
+    /// with -p, write the shortest pointer paths from module (image) sections to ADDR, e.g. a
+    /// global_State, to pointer_paths.json in the output path
+    #[arg(long, value_name = "ADDR", value_parser = parse_address, requires = "pointer_search")]
+    pointer_paths: Option<u64>,
+
+    /// most pointers followed by a --pointer-paths path
+    #[arg(long, value_name = "usize", default_value_t = DEFAULT_MAX_DEPTH)]
+    max_depth: usize,
+
+    /// largest offset added to a pointer in a --pointer-paths path (0x400 by default)
+    #[arg(long, value_name = "ADDR", value_parser = parse_address)]
+    max_offset: Option<u64>,
+
+    /// minidump or ELF core of another run of the same binary, only the --pointer-paths paths
+    /// that also reach the matching --rescan-target in it are kept (repeatable)
+    #[arg(long, value_name = "FILE", requires = "pointer_paths")]
+    rescan_dmp: Vec<PathBuf>,
+
+    /// address of the --pointer-paths target in the --rescan-dmp at the same position
+    #[arg(long, value_name = "ADDR", value_parser = parse_address, requires = "rescan_dmp")]
+    rescan_target: Vec<u64>,
+
+    /// with -p, tag the source and sink of every pointer in pointer_comments.json as image,
+    /// stack, heap, mapped, lua_page (when -l is given too) or unknown, with the sink protection
+    #[arg(long, action, value_name = "FLAG", requires = "pointer_search")]
+    classify_pointers: bool,
+
+    /// with -p, only write the classified pointers matching SOURCE->SINK[:PERM] to
+    /// pointer_comments.json, e.g. heap->image:r-- (`*` matches any class)
+    #[arg(long, value_name = "FILTER", requires = "pointer_search")]
+    pointer_filter: Option<PointerFilter>,
+
+    /// with -p, follow the MSVC RTTI of the vtables pointed to and write the objects with their
+    /// C++ class to rtti_objects.json in the output path
+    #[arg(long, action, value_name = "FLAG", requires = "pointer_search")]
+    rtti: bool,
+
     /// look for lua_Pages
     #[arg(short='r', long, action, value_name = "FLAG")]
     regex_searches: bool,
@@ -41,13 +90,56 @@ struct Arguments {
     #[arg(short='q', long, action, value_name = "FLAG")]
     quick_test: bool,
 
-    /// input path of the memory dump
-    #[arg(long, value_name = "FILE")]
-    dmp: PathBuf,
+    /// input path of the memory dump, optionally zstd, gzip or xz compressed
+    #[arg(long, value_name = "FILE", required_unless_present = "pid")]
+    dmp: Option<PathBuf>,
+
+    /// attach to a running (Linux) process instead of reading a dump
+    #[arg(long, value_name = "u32", conflicts_with = "dmp")]
+    pid: Option<u32>,
 
-    /// input
+    /// radare2 sections (`r2 -qc 'iSj'`) for the dump, read from the minidump or ELF core itself when omitted
     #[arg(long, value_name = "FILE")]
-    r2_sections: PathBuf,
+    r2_sections: Option<PathBuf>,
+
+    /// Volatility3 `windows.memmap` JSON (`vol -r json windows.memmap --pid N`) mapping the
+    /// process pages into a physical memory image given with --dmp
+    #[arg(long, value_name = "FILE", conflicts_with = "r2_sections")]
+    vol_memmap: Option<PathBuf>,
+
+    /// Volatility3 `windows.vadinfo` JSON for the same process, labels the --vol-memmap pages
+    /// with their VAD protection and type
+    #[arg(long, value_name = "FILE", requires = "vol_memmap")]
+    vol_vadinfo: Option<PathBuf>,
+
+    /// CR3 / directory table base (e.g. 0x1aa000) of the process in a raw or LiME x86-64 memory
+    /// image given with --dmp, its page tables are walked to find the process memory
+    #[arg(long, value_name = "ADDR", value_parser = parse_address, conflicts_with_all = ["r2_sections", "vol_memmap"])]
+    dtb: Option<u64>,
+
+    /// reuse the memory ranges built for the dump from this index file (`<dmp>.idx` when no
+    /// FILE is given), the index is written when missing or when the dump or sections changed
+    #[arg(long, value_name = "FILE", num_args = 0..=1, conflicts_with = "pid")]
+    index: Option<Option<PathBuf>>,
+
+    /// architecture of the dumped process (x86, x64, arm, arm64), detected from the dump when omitted
+    #[arg(long, value_name = "ARCH")]
+    arch: Option<Arch>,
+
+    /// memory ranges to scan: all, writable or private (committed private rw memory), pointer and
+    /// lua_Page searches default to writable and the regex range search to all
+    #[arg(long, value_name = "FILTER")]
+    regions: Option<RegionFilter>,
+
+    /// list the problems found in the memory ranges: overlapping sections, data past the end of
+    /// the dump, size/vsize mismatches and empty sections
+    #[arg(long, action, value_name = "FLAG")]
+    check_sections: bool,
+
+    /// like --check-sections, but exit with an error instead of searching when a problem is not
+    /// just a warning
+    #[arg(long, action, value_name = "FLAG")]
+    strict_sections: bool,
 
     /// regular expression for start
     #[arg(short = 's', long, value_name = "STRING")]
@@ -64,7 +156,7 @@ struct Arguments {
     #[arg(short, long, value_name = "FILE")]
     output_path: Option<PathBuf>,
 
-    /// input path of the memory dump
+    /// threads of the pointer and lua_Page searches, 0 for one per core
     #[arg(short, long, value_name = "u64")]
     num_threads: Option<u64>,
 
@@ -75,6 +167,32 @@ struct Arguments {
 //     pub ranges: MemRanges,
 // }
 
+/// The target of --pointer-paths, and the same target in the dumps of other runs.
+struct PathTargets {
+    target: u64,
+    search: PointerPathSearch,
+    rescans: Vec<(DataInterface, u64)>,
+}
+
+/// What is written next to pointer_comments.json.
+struct PointerOutputs {
+    graph: bool,
+    // (vaddr, size) of the lua_Pages found by the -l search
+    lua_pages: Option<Vec<(u64, u64)>>,
+    path_targets: Option<PathTargets>,
+    classify: bool,
+    filter: Option<PointerFilter>,
+    rtti: bool,
+}
+
+fn parse_address(value: &str) -> Result<u64, String> {
+    let parsed = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse::<u64>(),
+    };
+    parsed.map_err(|e| format!("Invalid address: {} ({})", value, e))
+}
+
 fn check_create(ofilename: &PathBuf) -> std::io::Result<()> {
     if Path::new(ofilename).exists() && Path::new(ofilename).is_dir() {
         return Ok(());
@@ -101,7 +219,13 @@ fn search_regex_all(
     let ro_buf = o_ro_buf.unwrap();
 
     let mut search =
-        RegexBlockSearch::new(&spattern, &epattern, None, None, None, Some(0), Some(0));
+        match RegexBlockSearch::new(&spattern, &epattern, None, None, None, Some(0), Some(0)) {
+            Ok(search) => search,
+            Err(e) => {
+                error!("Unable to search for {} => {}. {}", spattern, epattern, e);
+                return search_results;
+            }
+        };
     let r_results = search.search_buffer(&ro_buf);
 
     if r_results.is_ok() {
@@ -147,7 +271,9 @@ fn search_for_pointers(
                 search_results.push(*result.clone());
             }
         }
-        Err(_) => {}
+        Err(e) => {
+            error!("Pointer search failed: {}", e);
+        }
     }
     info!("Found {} results.", search_results.len());
     return search_results;
@@ -166,7 +292,9 @@ fn search_for_luapages(
                 search_results.push(*result.clone());
             }
         }
-        Err(_) => {}
+        Err(e) => {
+            error!("Lua page search failed: {}", e);
+        }
     }
     info!("Found {} results.", search_results.len());
     return search_results;
@@ -176,22 +304,20 @@ fn search_regex_ranges(
     spattern: String,
     epattern: String,
     di_arw: Arc<RwLock<Box<DataInterface>>>,
+    region_filter: &RegionFilter,
 ) -> Vec<SearchResult> {
     debug!("Searching Memory Ranges for {} => {}.", spattern, epattern,);
     let di = di_arw.read().unwrap();
-    let o_ro_buf = di.buffer.get_shared_buffer();
     let mut search_results = Vec::new();
-    if !o_ro_buf.is_some() {
-        error!("No data loaded, can't search.");
-        return search_results;
-    }
-    let ro_buf = o_ro_buf.unwrap();
     let _memory_regex = RegexBuilder::new("Memory_section")
         .case_insensitive(true)
         .build()
         .expect("Invalid Regex");
 
     for (_k, mr) in di.mem_ranges.pmem_ranges.iter() {
+        if !region_filter.matches(mr) {
+            continue;
+        }
         debug!(
             "Searching Memory Range: {} of {} bytes from starting at vaddr {:08x} and paddr {:08x}.",
             mr.name, mr.vsize, mr.vaddr_start, mr.paddr_start
@@ -199,8 +325,14 @@ fn search_regex_ranges(
         let vaddr: u64 = mr.vaddr_start;
         let paddr: u64 = mr.paddr_start;
         let size: u64 = mr.size;
-        let sbuff: &[u8] = &ro_buf[paddr as usize..(paddr + size) as usize];
-        let mut search = RegexBlockSearch::new(
+        let sbuff: &[u8] = match di.buffer.get_shared_slice_from(paddr, Some(size)) {
+            Some(buf) => buf,
+            None => {
+                error!("Unable to read memory range: {}, skipping it.", mr.name);
+                continue;
+            }
+        };
+        let mut search = match RegexBlockSearch::new(
             &spattern,
             &epattern,
             None,
@@ -208,7 +340,13 @@ fn search_regex_ranges(
             None,
             Some(vaddr),
             Some(paddr),
-        );
+        ) {
+            Ok(search) => search,
+            Err(e) => {
+                error!("Unable to search for {} => {}. {}", spattern, epattern, e);
+                return search_results;
+            }
+        };
         let r_results = search.search_buffer(sbuff);
 
         if r_results.is_ok() {
@@ -230,7 +368,10 @@ fn search_regex_ranges(
     return search_results;
 }
 
-fn write_search_results(output_filename: PathBuf, search_results: &Vec<SearchResult>) -> () {
+fn write_search_results(
+    output_filename: PathBuf,
+    search_results: &Vec<SearchResult>,
+) -> Result<(), Box<dyn StdErr>> {
     let o_writer = File::create(&output_filename);
     let mut writer = match o_writer {
         Ok(file) => BufWriter::new(file),
@@ -241,12 +382,12 @@ fn write_search_results(output_filename: PathBuf, search_results: &Vec<SearchRes
                 err
             );
             error!("{}", msg);
-            panic!("{}", msg);
+            return Err(msg.into());
         }
     };
 
     for result in search_results.iter() {
-        match writeln!(writer, "{}", json!(result).to_string()) {
+        match writeln!(writer, "{}", json!(result)) {
             Ok(_) => {}
             Err(err) => {
                 let msg = format!(
@@ -255,17 +396,20 @@ fn write_search_results(output_filename: PathBuf, search_results: &Vec<SearchRes
                     err
                 );
                 error!("{}", msg);
-                panic!("{}", msg);
+                return Err(msg.into());
             }
         };
-        writer.flush().unwrap();
+        writer.flush()?;
     }
+    Ok(())
 }
 
 fn perform_pointer_search(
 o_outputdir: Option<PathBuf>,
 data_interface: Arc<RwLock<Box<DataInterface>>>,
-num_threads: Option<u64>
+num_threads: Option<u64>,
+region_filter: RegionFilter,
+outputs: PointerOutputs,
 ) -> Result<(), Box<dyn StdErr>> {
 
     let max_threads = match num_threads {
@@ -274,11 +418,12 @@ num_threads: Option<u64>
     };
     let mut ptr_search = PointerSearch::new(None, None, data_interface.clone());
     ptr_search.max_threads = max_threads;
+    ptr_search.region_filter = region_filter;
 
     if o_outputdir.is_some() {
         let ofilepath = o_outputdir.as_ref().unwrap();
         info!("Checking for output directory: {}", ofilepath.display());
-        match check_create(&ofilepath) {
+        match check_create(ofilepath) {
             Ok(_) => {}
             Err(e) => {
                 let msg = format!(
@@ -287,21 +432,139 @@ num_threads: Option<u64>
                     e
                 );
                 error!("{}", msg);
-                panic!("{}", msg);
+                return Err(msg.into());
             }
         };
         let _pointer_results = search_for_pointers(&mut ptr_search, data_interface.clone());
+        if outputs.classify || outputs.filter.is_some() {
+            let di = match data_interface.read() {
+                Ok(di) => di,
+                Err(e) => return Err(format!("Poisoned data interface: {}", e).into()),
+            };
+            let lua_pages = outputs.lua_pages.as_deref().unwrap_or(&[]);
+            let classifier = Classifier::new(&di).with_lua_pages(lua_pages);
+            ptr_search.classify_comments(&classifier)?;
+        }
         let ptr_comment_results_filename = ofilepath.join("pointer_comments.json");
-        ptr_search.write_comments(ptr_comment_results_filename);
+        ptr_search.write_filtered_comments(ptr_comment_results_filename, outputs.filter.as_ref())?;
+        if outputs.graph {
+            let lua_pages = outputs.lua_pages.as_deref();
+            write_pointer_graph(ofilepath, &ptr_search, data_interface.clone(), lua_pages)?;
+        }
+        if let Some(path_targets) = &outputs.path_targets {
+            write_pointer_paths(ofilepath, &ptr_search, data_interface.clone(), path_targets)?;
+        }
+        if outputs.rtti {
+            write_rtti_objects(ofilepath, &ptr_search, data_interface.clone())?;
+        }
     }
     return Ok(());
 }
 
+fn write_pointer_graph(
+    ofilepath: &Path,
+    ptr_search: &PointerSearch,
+    data_interface: Arc<RwLock<Box<DataInterface>>>,
+    o_allocations: Option<&[(u64, u64)]>,
+) -> Result<(), Box<dyn StdErr>> {
+    let di = match data_interface.read() {
+        Ok(di) => di,
+        Err(e) => return Err(format!("Poisoned data interface: {}", e).into()),
+    };
+    let xrefs = match ptr_search.xrefs.read() {
+        Ok(xrefs) => xrefs,
+        Err(e) => return Err(format!("Poisoned pointer index: {}", e).into()),
+    };
+    let graph = match o_allocations {
+        Some(allocations) => PointerGraph::from_allocations(&di, &xrefs, allocations),
+        None => PointerGraph::from_mem_ranges(&di, &xrefs),
+    };
+    graph.save_dot(&ofilepath.join("pointer_graph.dot"))?;
+    graph.save_graphml(&ofilepath.join("pointer_graph.graphml"))?;
+    return Ok(());
+}
+
+fn write_rtti_objects(
+    ofilepath: &Path,
+    ptr_search: &PointerSearch,
+    data_interface: Arc<RwLock<Box<DataInterface>>>,
+) -> Result<(), Box<dyn StdErr>> {
+    let di = match data_interface.read() {
+        Ok(di) => di,
+        Err(e) => return Err(format!("Poisoned data interface: {}", e).into()),
+    };
+    let xrefs = match ptr_search.xrefs.read() {
+        Ok(xrefs) => xrefs,
+        Err(e) => return Err(format!("Poisoned pointer index: {}", e).into()),
+    };
+    let objects = RttiResolver::new(&di).annotate_objects(&xrefs);
+    let output_filename = ofilepath.join("rtti_objects.json");
+    let mut writer = BufWriter::new(File::create(&output_filename)?);
+    for object in objects.iter() {
+        let value = json!({
+            "object": format!("{:08x}", object.object),
+            "vptr": format!("{:08x}", object.vptr),
+            "section": object.section,
+            "class": object.class.name,
+            "mangled": object.class.mangled,
+            "vtable": format!("{:08x}", object.class.vtable),
+            "offset": format!("{:x}", object.class.offset),
+        });
+        writeln!(writer, "{}", value)?;
+    }
+    writer.flush()?;
+    info!("Wrote {} objects to: {}", objects.len(), output_filename.display());
+    return Ok(());
+}
+
+fn write_pointer_paths(
+    ofilepath: &Path,
+    ptr_search: &PointerSearch,
+    data_interface: Arc<RwLock<Box<DataInterface>>>,
+    path_targets: &PathTargets,
+) -> Result<(), Box<dyn StdErr>> {
+    let di = match data_interface.read() {
+        Ok(di) => di,
+        Err(e) => return Err(format!("Poisoned data interface: {}", e).into()),
+    };
+    let xrefs = match ptr_search.xrefs.read() {
+        Ok(xrefs) => xrefs,
+        Err(e) => return Err(format!("Poisoned pointer index: {}", e).into()),
+    };
+    let target = path_targets.target;
+    let paths = if path_targets.rescans.is_empty() {
+        path_targets.search.find_paths(&di, &xrefs, target)
+    } else {
+        let rescans: Vec<(&DataInterface, u64)> = path_targets
+            .rescans
+            .iter()
+            .map(|(other, other_target)| (other, *other_target))
+            .collect();
+        path_targets.search.find_stable_paths(&di, &xrefs, target, &rescans)
+    };
+    let output_filename = ofilepath.join("pointer_paths.json");
+    let mut writer = BufWriter::new(File::create(&output_filename)?);
+    for path in paths.iter() {
+        let value = json!({
+            "path": path.to_string(),
+            "module": path.module,
+            "base_offset": format!("{:08x}", path.base_offset),
+            "offsets": path.offsets.iter().map(|o| format!("{:x}", o)).collect::<Vec<String>>(),
+            "target": format!("{:08x}", target),
+        });
+        writeln!(writer, "{}", value)?;
+    }
+    writer.flush()?;
+    info!("Wrote {} pointer paths to: {}", paths.len(), output_filename.display());
+    return Ok(());
+}
+
 fn perform_luapage_search(
     o_outputdir: Option<PathBuf>,
     data_interface: Arc<RwLock<Box<DataInterface>>>,
-    num_threads: Option<u64>
-) -> Result<(), Box<dyn StdErr>> {
+    num_threads: Option<u64>,
+    region_filter: RegionFilter,
+) -> Result<Vec<SearchResult>, Box<dyn StdErr>> {
 
     let max_threads = match num_threads {
         Some(v) =>v,
@@ -309,11 +572,12 @@ fn perform_luapage_search(
     };
     let mut lp_search = LuaPageSearch::new(None, None, data_interface.clone(), None, None);
     lp_search.max_threads = max_threads;
+    lp_search.region_filter = region_filter;
 
     if o_outputdir.is_some() {
         let ofilepath = o_outputdir.as_ref().unwrap();
         info!("Checking for output directory: {}", ofilepath.display());
-        match check_create(&ofilepath) {
+        match check_create(ofilepath) {
             Ok(_) => {}
             Err(e) => {
                 let msg = format!(
@@ -322,14 +586,15 @@ fn perform_luapage_search(
                     e
                 );
                 error!("{}", msg);
-                panic!("{}", msg);
+                return Err(msg.into());
             }
         };
-        let _lua_page = search_for_luapages(&mut lp_search, data_interface.clone());
+        let lua_pages = search_for_luapages(&mut lp_search, data_interface.clone());
         let lp_comment_results_filename = ofilepath.join("luapage_comments.json");
-        lp_search.write_comments(lp_comment_results_filename);
+        lp_search.write_comments(lp_comment_results_filename)?;
+        return Ok(lua_pages);
     }
-    return Ok(());
+    return Ok(Vec::new());
 
 }
 
@@ -338,6 +603,7 @@ fn perform_regex_searches(
     epattern: String,
     o_outputdir: Option<PathBuf>,
     data_interface: Arc<RwLock<Box<DataInterface>>>,
+    region_filter: RegionFilter,
 ) -> Result<(), Box<dyn StdErr>> {
     println!("Enter the command");
     debug!(
@@ -348,7 +614,7 @@ fn perform_regex_searches(
     if o_outputdir.is_some() {
         let ofilepath = o_outputdir.as_ref().unwrap();
         info!("Checking for output directory: {}", ofilepath.display());
-        match check_create(&ofilepath) {
+        match check_create(ofilepath) {
             Ok(_) => {}
             Err(e) => {
                 let msg = format!(
@@ -357,7 +623,7 @@ fn perform_regex_searches(
                     e
                 );
                 error!("{}", msg);
-                panic!("{}", msg);
+                return Err(msg.into());
             }
         };
 
@@ -365,12 +631,185 @@ fn perform_regex_searches(
 
 
         let full_dump_results = search_regex_all(spattern.clone(), epattern.clone(), data_interface.clone());
-        let range_results = search_regex_ranges(spattern.clone(), epattern.clone(), data_interface.clone());
+        let range_results = search_regex_ranges(spattern.clone(), epattern.clone(), data_interface.clone(), &region_filter);
 
         let fd_results_filename = ofilepath.join("full_dump_roblox_assets.json");
-        write_search_results(fd_results_filename, &full_dump_results);
+        write_search_results(fd_results_filename, &full_dump_results)?;
         let mr_results_filename = ofilepath.join("memory_ranges_roblox_assets.json");
-        write_search_results(mr_results_filename, &range_results);
+        write_search_results(mr_results_filename, &range_results)?;
+    }
+    Ok(())
+}
+
+fn load_data_interface(args: &Arguments) -> Result<DataInterface, Box<dyn StdErr>> {
+    if let Some(pid) = args.pid {
+        debug!("Attaching to the memory of process: {}.", pid);
+        return match DataInterface::from_live_process(pid) {
+            Ok(di) => Ok(di),
+            Err(e) => {
+                let msg = format!("Failed to attach to process: {}. {}", pid, e);
+                error!("{}", msg);
+                Err(msg.into())
+            }
+        };
+    }
+
+    let dmp = match &args.dmp {
+        Some(dmp) => dmp,
+        None => return Err("Either --dmp or --pid is required".into()),
+    };
+    let index_path = match &args.index {
+        Some(Some(path)) => path.clone(),
+        Some(None) => default_index_path(dmp),
+        None => return Ok(load_dump(args, dmp)?),
+    };
+    let source = index_source(args)?;
+    let build = || load_dump(args, dmp);
+    let di = DataInterface::from_index_or_build(dmp, &index_path, &source, build)?;
+    Ok(di)
+}
+
+// what the memory ranges were built from, a saved index is only reused for the same inputs
+fn index_source(args: &Arguments) -> Result<String, Box<dyn StdErr>> {
+    if let Some(dtb) = args.dtb {
+        return Ok(format!("dtb:{:x}", dtb));
+    }
+    let mut source = String::from("headers");
+    for (kind, o_path) in [
+        ("r2", &args.r2_sections),
+        ("vol_memmap", &args.vol_memmap),
+        ("vol_vadinfo", &args.vol_vadinfo),
+    ] {
+        if let Some(path) = o_path {
+            let fingerprint = DumpFingerprint::from_path(path)?;
+            source = format!("{}:{}:{}", source, kind, fingerprint.digest);
+        }
+    }
+    Ok(source)
+}
+
+fn load_path_targets(args: &Arguments, target: u64) -> Result<PathTargets, Box<dyn StdErr>> {
+    if args.rescan_dmp.len() != args.rescan_target.len() {
+        let msg = format!(
+            "Expected a --rescan-target for each of the {} --rescan-dmp files, got {}",
+            args.rescan_dmp.len(),
+            args.rescan_target.len()
+        );
+        error!("{}", msg);
+        return Err(msg.into());
+    }
+    let mut rescans = Vec::new();
+    for (dmp, rescan_target) in args.rescan_dmp.iter().zip(args.rescan_target.iter()) {
+        debug!("Loading the rescan dump: {:#?}.", dmp.as_os_str());
+        let mut di = match DataInterface::from_dump_file(dmp) {
+            Ok(di) => di,
+            Err(e) => {
+                error!("Failed to load the rescan dump: {}. {}", dmp.display(), e);
+                return Err(e.into());
+            }
+        };
+        if let Some(arch) = args.arch {
+            let endian = di.vmem_info.endian.clone();
+            di.vmem_info.set_arch(arch, endian);
+        }
+        rescans.push((di, *rescan_target));
+    }
+    let max_offset = args.max_offset.unwrap_or(DEFAULT_MAX_OFFSET);
+    return Ok(PathTargets {
+        target,
+        search: PointerPathSearch::new(args.max_depth, max_offset),
+        rescans,
+    });
+}
+
+fn load_dump(args: &Arguments, dmp: &Path) -> Result<DataInterface, MemError> {
+    if let Some(dtb) = args.dtb {
+        debug!(
+            "Walking the page tables at dtb: {:08x} in: {:#?}.",
+            dtb,
+            dmp.as_os_str()
+        );
+        return match DataInterface::from_physical_image(dmp, dtb) {
+            Ok(di) => Ok(di),
+            Err(e) => {
+                error!("Failed to translate: {}. {}", dmp.display(), e);
+                Err(e)
+            }
+        };
+    }
+    if let Some(vol_memmap) = &args.vol_memmap {
+        debug!(
+            "Loading Volatility memmap from: {:#?}.",
+            vol_memmap.as_os_str()
+        );
+        let o_vadinfo = args.vol_vadinfo.as_deref();
+        return match DataInterface::from_volatility(dmp, vol_memmap, o_vadinfo) {
+            Ok(di) => Ok(di),
+            Err(e) => {
+                let msg = format!(
+                    "Failed to load Volatility output: {}. {}",
+                    vol_memmap.display(),
+                    e
+                );
+                error!("{}", msg);
+                Err(e)
+            }
+        };
+    }
+    match &args.r2_sections {
+        Some(r2_sections) => {
+            debug!(
+                "Loading radare info from: {:#?}.",
+                r2_sections.as_os_str()
+            );
+            let infos = match RadareMemoryInfos::from_radare_json(r2_sections) {
+                Ok(infos) => infos,
+                Err(e) => {
+                    error!("Failed to load radare info: {}. {}", r2_sections.display(), e);
+                    return Err(e);
+                }
+            };
+
+            debug!(
+                "Creating MemRanges and Loading dump file into memory: {:#?}.",
+                dmp.as_os_str()
+            );
+            DataInterface::new_from_radare_info(dmp, &infos, None)
+        }
+        None => {
+            debug!(
+                "Creating MemRanges from the headers of the dump: {:#?}.",
+                dmp.as_os_str()
+            );
+            match DataInterface::from_dump_file(dmp) {
+                Ok(di) => Ok(di),
+                Err(e) => {
+                    error!("Failed to load dump: {}. {}", dmp.display(), e);
+                    Err(e)
+                }
+            }
+        }
+    }
+}
+
+fn check_sections(di: &DataInterface, strict: bool) -> Result<(), Box<dyn StdErr>> {
+    let diagnostics = di.section_diagnostics();
+    let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+    for diagnostic in diagnostics.iter() {
+        if diagnostic.is_error() {
+            error!("{}", diagnostic);
+        } else {
+            info!("{}", diagnostic);
+        }
+    }
+    info!(
+        "Checked {} memory ranges: {} errors, {} warnings.",
+        di.mem_ranges.count(),
+        errors,
+        diagnostics.len() - errors
+    );
+    if strict && errors > 0 {
+        return Err(format!("The memory ranges have {} errors", errors).into());
     }
     Ok(())
 }
@@ -378,62 +817,87 @@ fn perform_regex_searches(
 fn main() -> Result<(), Box<dyn StdErr>> {
     let args = Arguments::parse();
 
-    let regex_start: Regex = match args.regex_start {
+    let regex_start: Regex = match &args.regex_start {
         Some(pattern) => match Regex::new(pattern.as_str()) {
             Ok(r) => r,
-            Err(e) => panic!("Invalid regular expression provided: '{}', {}", pattern, e),
+            Err(e) => {
+                let msg = format!("Invalid regular expression provided: '{}', {}", pattern, e);
+                return Err(msg.into());
+            }
         },
         None => Regex::new(ROBLOX_REGEX_START).unwrap(),
     };
 
-    let regex_end: Regex = match args.regex_end {
+    let regex_end: Regex = match &args.regex_end {
         Some(pattern) => match Regex::new(pattern.as_str()) {
             Ok(r) => r,
-            Err(e) => panic!("Invalid regular expression provided: '{}', {}", pattern, e),
+            Err(e) => {
+                let msg = format!("Invalid regular expression provided: '{}', {}", pattern, e);
+                return Err(msg.into());
+            }
         },
         None => Regex::new(ROBLOX_REGEX_END).unwrap(),
     };
 
-    let log_conf = match args.log_conf {
+    let log_conf = match args.log_conf.clone() {
         Some(path) => path,
         None => PathBuf::from("../logging_config.yaml"),
     };
     log4rs::init_file(log_conf, Default::default()).unwrap();
 
-    debug!(
-        "Loading radare info from: {:#?}.",
-        args.r2_sections.as_os_str()
-    );
-    let infos = RadareMemoryInfos::from_radare_json(&args.r2_sections);
-
-    debug!(
-        "Creating MemRanges and Loading dump file into memory: {:#?}.",
-        args.dmp.as_os_str()
+    let mut di = load_data_interface(&args)?;
+    if let Some(arch) = args.arch {
+        let endian = di.vmem_info.endian.clone();
+        di.vmem_info.set_arch(arch, endian);
+    }
+    if args.check_sections || args.strict_sections {
+        check_sections(&di, args.strict_sections)?;
+    }
+    info!(
+        "Searching a {} address space with a word size of {}.",
+        di.vmem_info.arch, di.vmem_info.word_sz
     );
-
-    let data_interface = Arc::new(RwLock::new(Box::new(DataInterface::new_from_radare_info(&args.dmp, &infos, None))));
+    let data_interface = Arc::new(RwLock::new(Box::new(di)));
 
     if args.regex_searches {
-        let _ = perform_regex_searches(
+        perform_regex_searches(
             regex_start.to_string(),
             regex_end.to_string(),
             args.output_path.clone(),
-            data_interface.clone()
-        );
+            data_interface.clone(),
+            args.regions.clone().unwrap_or_else(RegionFilter::all),
+        )?;
     }
+    let mut o_lua_pages: Option<Vec<(u64, u64)>> = None;
     if args.luapage_search {
-        let _ = perform_luapage_search(
+        let lua_pages = perform_luapage_search(
             args.output_path.clone(),
             data_interface.clone(),
             args.num_threads,
-        );
+            args.regions.clone().unwrap_or_default(),
+        )?;
+        o_lua_pages = Some(lua_pages.iter().map(|sr| (sr.vaddr, sr.size)).collect());
     }
     if args.pointer_search {
-        let _ = perform_pointer_search(
+        let o_path_targets = match args.pointer_paths {
+            Some(target) => Some(load_path_targets(&args, target)?),
+            None => None,
+        };
+        let outputs = PointerOutputs {
+            graph: args.pointer_graph,
+            lua_pages: o_lua_pages,
+            path_targets: o_path_targets,
+            classify: args.classify_pointers,
+            filter: args.pointer_filter.clone(),
+            rtti: args.rtti,
+        };
+        perform_pointer_search(
             args.output_path.clone(),
             data_interface.clone(),
             args.num_threads,
-        );
+            args.regions.clone().unwrap_or_default(),
+            outputs,
+        )?;
     }
 
     Ok(())
@@ -0,0 +1,101 @@
+// Throughput of the pointer-range membership test run on every word by the pointer searches,
+// the per page HashMap it replaced against the page bitmap of `PageLookup`.
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use mem_analysis::pagelookup::PageLookup;
+use mem_analysis::pointers::PointerRange;
+use std::collections::{HashMap, HashSet};
+
+const PAGE_SIZE: u64 = 0x1000;
+const PAGE_MASK: u64 = !(PAGE_SIZE - 1);
+const RANGE_COUNT: usize = 2000;
+const WORD_COUNT: usize = 1 << 20;
+
+// xorshift64, keeps the layout the same between runs
+fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+// ranges of 1 to 256 pages spread over a 47 bit user address space
+fn synthetic_ranges() -> Vec<PointerRange> {
+    let mut state = 0x9e37_79b9_7f4a_7c15;
+    let mut taken: HashSet<u64> = HashSet::new();
+    let mut ranges = Vec::new();
+    while ranges.len() < RANGE_COUNT {
+        let vstart = (next_random(&mut state) & 0x7fff_ffff_ffff) & PAGE_MASK;
+        if !taken.insert(vstart >> 24) {
+            continue;
+        }
+        let size = (1 + next_random(&mut state) % 256) * PAGE_SIZE;
+        let pstart = ranges.len() as u64 * 0x100_0000;
+        ranges.push(PointerRange::new(pstart, pstart + size, vstart, vstart + size).unwrap());
+    }
+    ranges
+}
+
+// a quarter of the words point into a range, the rest are small integers and noise
+fn synthetic_words(ranges: &[PointerRange]) -> Vec<u64> {
+    let mut state = 0x2545_f491_4f6c_dd1d;
+    (0..WORD_COUNT)
+        .map(|_| {
+            let r = next_random(&mut state);
+            match r % 4 {
+                0 => {
+                    let range = &ranges[(r >> 8) as usize % ranges.len()];
+                    range.vstart + (r >> 32) % (range.vend - range.vstart)
+                }
+                1 => r % 0x10000,
+                _ => r,
+            }
+        })
+        .collect()
+}
+
+fn bench_ptr_lookup(c: &mut Criterion) {
+    let ranges = synthetic_ranges();
+    let words = synthetic_words(&ranges);
+
+    let mut page_map: HashMap<u64, Box<PointerRange>> = HashMap::new();
+    let mut page_lookup = PageLookup::new(PAGE_SIZE);
+    for range in ranges.iter() {
+        let mut page = range.vstart & PAGE_MASK;
+        while page < range.vend {
+            page_map.insert(page, Box::new(range.clone()));
+            page += PAGE_SIZE;
+        }
+        page_lookup.insert(range);
+    }
+
+    let mut group = c.benchmark_group("ptr_lookup");
+    group.throughput(Throughput::Elements(words.len() as u64));
+    group.bench_function("hashmap_pages", |b| {
+        b.iter(|| {
+            words
+                .iter()
+                .filter(|w| page_map.contains_key(&(**w & PAGE_MASK)))
+                .count()
+        })
+    });
+    group.bench_function("page_bitmap", |b| {
+        b.iter(|| {
+            words
+                .iter()
+                .filter(|w| page_lookup.contains(black_box(**w)))
+                .count()
+        })
+    });
+    group.bench_function("interval_table", |b| {
+        b.iter(|| {
+            words
+                .iter()
+                .filter(|w| page_lookup.get(black_box(**w)).is_some())
+                .count()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_ptr_lookup);
+criterion_main!(benches);
@@ -1,22 +1,76 @@
+use crate::compressed::open_decompressed;
+use crate::error::MemError;
+use crate::live::LiveProcessMemory;
+use crate::memory::BackendType;
 use log::{debug, error};
-use std::fs::{File, OpenOptions};
+use memmap2::Mmap;
+use rangemap::RangeMap;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::fs::File;
 use std::io::Read;
-use std::path::PathBuf;
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Read-only mapping of a dump file.  The pages are loaded on demand by the OS, so dumps
+/// larger than physical memory can be searched.
+pub struct MappedFile {
+    map: Mmap,
+}
+
+impl MappedFile {
+    pub fn open(file: &File) -> std::io::Result<Self> {
+        // SAFETY: the mapping is read-only and the dump files are not expected to change
+        // while they are being analyzed.
+        let map = unsafe { Mmap::map(file)? };
+        Ok(MappedFile { map })
+    }
+}
+
+impl Deref for MappedFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.map[..]
+    }
+}
+
+impl Debug for MappedFile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("MappedFile")
+            .field("len", &self.map.len())
+            .finish()
+    }
+}
+
+impl PartialEq for MappedFile {
+    fn eq(&self, other: &Self) -> bool {
+        self.map.as_ptr() == other.map.as_ptr() && self.map.len() == other.map.len()
+    }
+}
+
+impl Eq for MappedFile {}
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct DataBuffer {
     pub filename: Option<String>,
     pub size: u64,
     pub data: Option<Vec<u8>>,
-    // pub vaddr_to_paddr_start : RangeMap<u64, u64>,
-    // pub paddr_to_vaddr_start : RangeMap<u64, u64>,
+    pub mmap: Option<Arc<MappedFile>>,
+    pub backend: Option<BackendType>,
+    pub live: Option<Arc<LiveProcessMemory>>,
+    // the values are the (wrapping) offsets to add to an address to translate it, storing
+    // offsets rather than start addresses keeps RangeMap from merging unrelated neighbours
+    pub vaddr_to_paddr: RangeMap<u64, u64>,
+    pub paddr_to_vaddr: RangeMap<u64, u64>,
 }
 
 impl DataBuffer {
     pub fn get_shared_buffer(&self) -> Option<&[u8]> {
-        match &self.data {
-            Some(buffer) => Some(&buffer[0..]),
-            None => None,
+        match (&self.data, &self.mmap) {
+            (Some(buffer), _) => Some(&buffer[0..]),
+            (None, Some(mapped)) => Some(&mapped[0..]),
+            (None, None) => None,
         }
     }
 
@@ -33,80 +87,259 @@ impl DataBuffer {
     // }
 
     pub fn get_shared_slice_from(&self, paddr: u64, size: Option<u64>) -> Option<&[u8]> {
-        if paddr > self.size || self.data.is_none() {
+        if let Some(live) = &self.live {
+            // the "physical" address of live memory is the virtual address in the process
+            return live.get_slice(paddr, size);
+        }
+        let buffer = self.get_shared_buffer()?;
+        if paddr > self.size {
             return None;
         }
 
-        if size.is_none() {
-            return match &self.data {
-                Some(buffer) => Some(&buffer[paddr as usize..]),
-                None => None,
-            };
+        let sz = match size {
+            Some(sz) => sz,
+            None => return Some(&buffer[paddr as usize..]),
+        };
+        match paddr.checked_add(sz) {
+            Some(end) if end <= self.size => Some(&buffer[paddr as usize..end as usize]),
+            _ => None,
+        }
+    }
+
+    /// Up to `size` bytes at `paddr`, cut short at the end of the data or, for live memory,
+    /// at the first block that can't be read.  `None` if no byte can be read.
+    pub fn get_partial_slice_from(&self, paddr: u64, size: u64) -> Option<&[u8]> {
+        if let Some(live) = &self.live {
+            return live.get_partial_slice(paddr, size);
         }
-        let sz = size.unwrap();
-        if paddr + sz > self.size {
+        let buffer = self.get_shared_buffer()?;
+        if paddr >= self.size {
             return None;
         }
-        return match &self.data {
-            Some(buffer) => Some(&buffer[paddr as usize..(paddr + sz) as usize]),
-            None => None,
+        let end = paddr.saturating_add(size).min(self.size);
+        Some(&buffer[paddr as usize..end as usize])
+    }
+
+    /// Map the file read-only instead of copying it into memory, this is what the `File`
+    /// backend uses.
+    pub fn map_data(&mut self) -> Result<(), MemError> {
+        let filename = match &self.filename {
+            Some(filename) => filename.clone(),
+            None => {
+                error!("No filename provided for the backend data buffer.");
+                return Err(MemError::unsupported("a data buffer without a file"));
+            }
         };
+        debug!("Mapping data buffer from file: {}.", filename);
+        let file = open_decompressed(Path::new(&filename))?;
+        self.map_file(&file)
     }
 
-    pub fn load_data(&mut self) {
-        if self.filename.is_none() {
-            error!("No filename provided for the backend data buffer.");
-            return;
+    fn map_file(&mut self, file: &File) -> Result<(), MemError> {
+        self.size = file.metadata()?.len();
+        self.data = None;
+        self.mmap = None;
+        if self.size == 0 {
+            // zero length files can't be mapped on every platform
+            self.data = Some(Vec::new());
+            return Ok(());
         }
-        let r_file = OpenOptions::new()
-            .read(true)
-            .write(false)
-            .open(self.filename.as_ref().unwrap());
-
-        debug!(
-            "Loading data buffer from file: {}.",
-            self.filename.as_ref().unwrap()
-        );
-        assert_eq!(true, r_file.is_ok());
-        let mut file = r_file.unwrap();
-        self.size = file.metadata().unwrap().len();
+        self.mmap = Some(Arc::new(MappedFile::open(file)?));
+        debug!("Mapped {} bytes.", self.size);
+        Ok(())
+    }
+
+    pub fn load_data(&mut self) -> Result<(), MemError> {
+        let filename = match &self.filename {
+            Some(filename) => filename.clone(),
+            None => {
+                error!("No filename provided for the backend data buffer.");
+                return Err(MemError::unsupported("a data buffer without a file"));
+            }
+        };
+        debug!("Loading data buffer from file: {}.", filename);
+        let file = open_decompressed(Path::new(&filename))?;
+        self.read_file(&file)
+    }
+
+    fn read_file(&mut self, mut file: &File) -> Result<(), MemError> {
+        self.size = file.metadata()?.len();
         let mut data = Vec::with_capacity(self.size as usize);
-        let read_bytes = file.read_to_end(&mut data);
-        assert_eq!(true, read_bytes.is_ok());
-        assert_eq!(*(read_bytes.as_ref().unwrap()) >= (0 as usize), true);
-        debug!(
-            "Load {} bytes from from file: {}.",
-            read_bytes.unwrap(),
-            self.filename.as_ref().unwrap()
-        );
+        let read_bytes = file.read_to_end(&mut data)?;
+        debug!("Load {} bytes.", read_bytes);
+        self.size = data.len() as u64;
+        self.mmap = None;
         self.data = Some(data);
+        Ok(())
     }
 
-    pub fn from_pathbuf(ifilename: &PathBuf, load_data: bool) -> Self {
-        debug!(
-            "Creating a data buffer from path: {}.",
-            ifilename.as_os_str().to_str().unwrap()
-        );
+    pub fn from_pathbuf(ifilename: &Path, load_data: bool) -> Result<Self, MemError> {
+        DataBuffer::from_pathbuf_with_backend(ifilename, load_data, BackendType::File)
+    }
+
+    /// `BackendType::File` maps the file, `BackendType::FullBuffer` reads it into memory.
+    pub fn from_pathbuf_with_backend(
+        ifilename: &Path,
+        load_data: bool,
+        backend: BackendType,
+    ) -> Result<Self, MemError> {
+        debug!("Creating a data buffer from path: {}.", ifilename.display());
         let mut db = DataBuffer {
-            filename: Some(ifilename.as_os_str().to_str().expect("REASON").to_string()),
+            filename: Some(ifilename.as_os_str().to_string_lossy().to_string()),
             size: 0,
-            // vaddr_to_paddr_start: RangeMap::new(),
-            // paddr_to_vaddr_start: RangeMap::new(),
             data: None,
+            mmap: None,
+            backend: Some(backend),
+            live: None,
+            vaddr_to_paddr: RangeMap::new(),
+            paddr_to_vaddr: RangeMap::new(),
         };
 
-        if load_data && db.filename.is_some() {
-            db.load_data();
+        if load_data {
+            match db.backend {
+                Some(BackendType::File) => db.map_data()?,
+                _ => db.load_data()?,
+            }
+        }
+        Ok(db)
+    }
+    /// Create a buffer over the memory of a running process.  Nothing is read until a slice
+    /// is requested, and the paddr used for lookups is the virtual address in the process.
+    pub fn from_live_process(pid: u32) -> Result<Self, MemError> {
+        let live = LiveProcessMemory::attach(pid)?;
+        Ok(DataBuffer {
+            filename: Some(format!("/proc/{}/mem", pid)),
+            size: live.maps.iter().map(|m| m.end).max().unwrap_or(0),
+            data: None,
+            mmap: None,
+            backend: Some(BackendType::LiveProcess),
+            live: Some(Arc::new(live)),
+            vaddr_to_paddr: RangeMap::new(),
+            paddr_to_vaddr: RangeMap::new(),
+        })
+    }
+
+    pub fn new() -> Self {
+        DataBuffer {
+            filename: None,
+            size: 0,
+            data: None,
+            mmap: None,
+            backend: None,
+            live: None,
+            vaddr_to_paddr: RangeMap::new(),
+            paddr_to_vaddr: RangeMap::new(),
         }
+    }
+
+    pub fn from_slice(buffer: &[u8]) -> Self {
+        let mut db = DataBuffer::new();
+        db.load_data_slice(buffer);
+        db
+    }
+
+    pub fn from_vec(buffer: Vec<u8>) -> Self {
+        let mut db = DataBuffer::new();
+        db.load_data_vec(buffer);
         db
     }
-    pub fn load_data_slice(&self, _buffer: &[u8]) {}
-    pub fn load_data_vec(&self, _buffer: &Vec<u8>) {}
-    pub fn load_data_path(&self, _path: &PathBuf) {}
-    pub fn load_data_file(&self, _path: &File) {}
 
-    pub fn add_vaddr_mapping(&mut self, _vaddr: u64, _paddr: u64, _size: u64) {
-        // self.vaddr_to_paddr_start.insert(vaddr .. vaddr + size, paddr);
-        // self.paddr_to_vaddr_start.insert(paddr .. paddr + size, vaddr);
+    /// Copy `buffer` into the data buffer.
+    pub fn load_data_slice(&mut self, buffer: &[u8]) {
+        self.load_data_vec(buffer.to_vec());
+        self.backend = Some(BackendType::SliceBuffer);
+    }
+
+    /// Take ownership of `buffer` as the data buffer without copying it.
+    pub fn load_data_vec(&mut self, buffer: Vec<u8>) {
+        debug!("Loading data buffer from {} bytes in memory.", buffer.len());
+        self.size = buffer.len() as u64;
+        self.data = Some(buffer);
+        self.mmap = None;
+        self.live = None;
+        self.backend = Some(BackendType::FullBuffer);
+    }
+
+    /// Replace the data with the contents of `path`, honoring the current backend type
+    /// (mapped for `File`, the default, read into memory otherwise).
+    pub fn load_data_path(&mut self, path: &Path) -> Result<(), MemError> {
+        self.filename = Some(path.as_os_str().to_string_lossy().to_string());
+        self.live = None;
+        match self.backend {
+            Some(BackendType::File) | None => {
+                self.backend = Some(BackendType::File);
+                self.map_data()
+            }
+            _ => {
+                self.backend = Some(BackendType::FullBuffer);
+                self.load_data()
+            }
+        }
+    }
+
+    /// Replace the data with the contents of an already open file, honoring the current
+    /// backend type like `load_data_path`.
+    pub fn load_data_file(&mut self, file: &File) -> Result<(), MemError> {
+        self.live = None;
+        match self.backend {
+            Some(BackendType::File) | None => {
+                self.backend = Some(BackendType::File);
+                self.map_file(file)
+            }
+            _ => {
+                self.backend = Some(BackendType::FullBuffer);
+                self.read_file(file)
+            }
+        }
+    }
+
+    /// Record that `size` bytes at `vaddr` are stored at `paddr` in this buffer.  Fails when
+    /// either span runs past the end of the address space.
+    pub fn add_vaddr_mapping(
+        &mut self,
+        vaddr: u64,
+        paddr: u64,
+        size: u64,
+    ) -> Result<(), MemError> {
+        if size == 0 {
+            return Ok(());
+        }
+        let vend = vaddr
+            .checked_add(size)
+            .ok_or(MemError::OutOfRange { addr: vaddr, size })?;
+        let pend = paddr
+            .checked_add(size)
+            .ok_or(MemError::OutOfRange { addr: paddr, size })?;
+        self.vaddr_to_paddr.insert(vaddr..vend, paddr.wrapping_sub(vaddr));
+        self.paddr_to_vaddr.insert(paddr..pend, vaddr.wrapping_sub(paddr));
+        Ok(())
+    }
+
+    pub fn get_paddr_from_vaddr(&self, vaddr: u64) -> Option<u64> {
+        self.vaddr_to_paddr
+            .get(&vaddr)
+            .map(|delta| vaddr.wrapping_add(*delta))
+    }
+
+    pub fn get_vaddr_from_paddr(&self, paddr: u64) -> Option<u64> {
+        self.paddr_to_vaddr
+            .get(&paddr)
+            .map(|delta| paddr.wrapping_add(*delta))
+    }
+
+    /// Slice of `size` bytes at `vaddr` using the vaddr mapping table.  The read has to stay
+    /// inside a single mapping.
+    pub fn get_shared_slice_vaddr(&self, vaddr: u64, size: u64) -> Option<&[u8]> {
+        let (range, delta) = self.vaddr_to_paddr.get_key_value(&vaddr)?;
+        if vaddr.checked_add(size)? > range.end {
+            return None;
+        }
+        self.get_shared_slice_from(vaddr.wrapping_add(*delta), Some(size))
+    }
+}
+
+impl Default for DataBuffer {
+    fn default() -> Self {
+        DataBuffer::new()
     }
 }
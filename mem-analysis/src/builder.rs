@@ -0,0 +1,143 @@
+use crate::buffer::DataBuffer;
+use crate::data_interface::{DataInterface, VMemInfo, ENDIAN};
+use crate::error::MemError;
+use crate::memory::{BackendType, MemRange, MemRanges};
+use crate::region::{MemState, MemType, Protection};
+
+use log::debug;
+
+#[derive(Debug, Clone)]
+struct RegionSpec {
+    vaddr: u64,
+    perm: Protection,
+    name: String,
+    bytes: Vec<u8>,
+    state: MemState,
+    mem_type: MemType,
+}
+
+/// Builds a `DataInterface` over a fabricated address space, e.g.
+/// `AddressSpaceBuilder::new().region(0x10000, "-rw-", "heap", &heap).build()?`.
+/// Every region is placed at a page aligned offset of a single in-memory buffer, so the
+/// `MemRanges`, `VMemInfo` and buffer mappings all agree like they would for a real dump.
+#[derive(Debug, Clone)]
+pub struct AddressSpaceBuilder {
+    regions: Vec<RegionSpec>,
+    word_sz: u8,
+    alignment: u8,
+    endian: ENDIAN,
+}
+
+impl Default for AddressSpaceBuilder {
+    fn default() -> Self {
+        AddressSpaceBuilder::new()
+    }
+}
+
+impl AddressSpaceBuilder {
+    pub fn new() -> Self {
+        let vmem_info = VMemInfo::new();
+        AddressSpaceBuilder {
+            regions: Vec::new(),
+            word_sz: vmem_info.word_sz,
+            alignment: vmem_info.alignment,
+            endian: vmem_info.endian,
+        }
+    }
+
+    /// Map `bytes` at `vaddr` with a radare style permission string (e.g. `-rw-`) as committed
+    /// private memory.
+    pub fn region(self, vaddr: u64, perm: &str, name: &str, bytes: &[u8]) -> Self {
+        self.region_with_type(vaddr, perm, name, bytes, MemType::Private)
+    }
+
+    pub fn region_with_type(
+        mut self,
+        vaddr: u64,
+        perm: &str,
+        name: &str,
+        bytes: &[u8],
+        mem_type: MemType,
+    ) -> Self {
+        self.regions.push(RegionSpec {
+            vaddr,
+            perm: Protection::from_perm_str(perm),
+            name: name.to_string(),
+            bytes: bytes.to_vec(),
+            state: MemState::Commit,
+            mem_type,
+        });
+        self
+    }
+
+    pub fn word_sz(mut self, word_sz: u8) -> Self {
+        self.word_sz = word_sz;
+        self
+    }
+
+    pub fn alignment(mut self, alignment: u8) -> Self {
+        self.alignment = alignment;
+        self
+    }
+
+    pub fn endian(mut self, endian: ENDIAN) -> Self {
+        self.endian = endian;
+        self
+    }
+
+    /// Lay out the regions and build the interface.  Fails if two regions overlap.
+    pub fn build(mut self) -> Result<DataInterface, MemError> {
+        let mut vmem_info = VMemInfo::new();
+        vmem_info.word_sz = self.word_sz;
+        vmem_info.alignment = self.alignment;
+        vmem_info.endian = self.endian.clone();
+        let page_size = vmem_info.page_size;
+
+        self.regions.sort_by_key(|r| r.vaddr);
+        for pair in self.regions.windows(2) {
+            let end = pair[0].vaddr.checked_add(pair[0].bytes.len() as u64);
+            if !matches!(end, Some(end) if end <= pair[1].vaddr) {
+                return Err(MemError::invalid_format(format!(
+                    "Region {} at {:08x} overlaps region {} at {:08x}",
+                    pair[0].name, pair[0].vaddr, pair[1].name, pair[1].vaddr
+                )));
+            }
+        }
+
+        let mut data: Vec<u8> = Vec::new();
+        let mut mem_ranges = MemRanges::new();
+        for region in self.regions.iter() {
+            let paddr = data.len() as u64;
+            let size = region.bytes.len() as u64;
+            debug!(
+                "Placing synthetic region {} vaddr: {:08x} at paddr: {:08x} size: {:08x}",
+                region.name, region.vaddr, paddr, size
+            );
+            data.extend_from_slice(&region.bytes);
+            // keep the next region page aligned in the backing buffer
+            let padded = (data.len() as u64).div_ceil(page_size) * page_size;
+            data.resize(padded as usize, 0);
+            let mut mr = MemRange::new(
+                region.perm,
+                region.name.clone(),
+                region.vaddr,
+                paddr,
+                size,
+                size,
+                None,
+                Some(BackendType::SliceBuffer),
+            );
+            mr.state = region.state;
+            mr.mem_type = region.mem_type;
+            mem_ranges.add_mem_range(mr);
+        }
+
+        let mut buffer = DataBuffer::from_vec(data);
+        buffer.backend = Some(BackendType::SliceBuffer);
+        Ok(DataInterface::new_from_mem_ranges(
+            buffer,
+            mem_ranges,
+            Some(vmem_info),
+        ))
+    }
+}
@@ -0,0 +1,274 @@
+use crate::error::MemError;
+use byteorder::{ByteOrder, LittleEndian};
+use flate2::read::MultiGzDecoder;
+use memmap2::{Mmap, MmapMut};
+use std::fs::{create_dir_all, rename, File, OpenOptions};
+use std::io::{copy, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::UNIX_EPOCH;
+use xz2::read::XzDecoder;
+
+use log::debug;
+
+/// Directory the decompressed dumps are cached in, the system temp directory when unset.
+pub const CACHE_DIR_ENV: &str = "MEM_ANALYSIS_CACHE_DIR";
+
+const ZSTD_MAGIC: &[u8; 4] = b"\x28\xb5\x2f\xfd";
+const GZIP_MAGIC: &[u8; 2] = b"\x1f\x8b";
+const XZ_MAGIC: &[u8; 6] = b"\xfd7zXZ\x00";
+
+// https://github.com/facebook/zstd/blob/dev/contrib/seekable_format/zstd_seekable_compression_format.md
+const SEEKABLE_MAGIC: u32 = 0x8f92_eab1;
+const SEEKABLE_FOOTER_SIZE: u64 = 9;
+const SKIPPABLE_HEADER_SIZE: u64 = 8;
+const SEEKABLE_CHECKSUM_FLAG: u8 = 0x80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Zstd,
+    Gzip,
+    Xz,
+}
+
+impl Compression {
+    /// Recognize the compression from the magic at the start of the file, `None` for
+    /// uncompressed data.
+    pub fn from_magic(magic: &[u8]) -> Option<Self> {
+        if magic.starts_with(ZSTD_MAGIC) {
+            Some(Compression::Zstd)
+        } else if magic.starts_with(GZIP_MAGIC) {
+            Some(Compression::Gzip)
+        } else if magic.starts_with(XZ_MAGIC) {
+            Some(Compression::Xz)
+        } else {
+            None
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Result<Option<Self>, MemError> {
+        let mut magic = Vec::with_capacity(XZ_MAGIC.len());
+        File::open(path)?
+            .take(XZ_MAGIC.len() as u64)
+            .read_to_end(&mut magic)?;
+        Ok(Compression::from_magic(&magic))
+    }
+}
+
+// (offset in the compressed file, compressed size, decompressed size)
+type SeekableFrame = (u64, u64, u64);
+
+/// The frames listed by the seek table of a seekable zstd file, `None` if the file does
+/// not end with one.
+fn seekable_frames(data: &[u8]) -> Option<Vec<SeekableFrame>> {
+    let len = data.len() as u64;
+    if len < SEEKABLE_FOOTER_SIZE + SKIPPABLE_HEADER_SIZE {
+        return None;
+    }
+    let footer = &data[(len - SEEKABLE_FOOTER_SIZE) as usize..];
+    if LittleEndian::read_u32(&footer[5..9]) != SEEKABLE_MAGIC {
+        return None;
+    }
+    let num_frames = LittleEndian::read_u32(&footer[0..4]) as u64;
+    let entry_size = if footer[4] & SEEKABLE_CHECKSUM_FLAG != 0 {
+        12
+    } else {
+        8
+    };
+    let table_size = num_frames.checked_mul(entry_size)?;
+    let table_start = len.checked_sub(SEEKABLE_FOOTER_SIZE + table_size)?;
+    let skippable_start = table_start.checked_sub(SKIPPABLE_HEADER_SIZE)?;
+
+    let mut frames = Vec::with_capacity(num_frames as usize);
+    let mut offset: u64 = 0;
+    for idx in 0..num_frames {
+        let pos = (table_start + idx * entry_size) as usize;
+        let c_size = LittleEndian::read_u32(&data[pos..pos + 4]) as u64;
+        let d_size = LittleEndian::read_u32(&data[pos + 4..pos + 8]) as u64;
+        frames.push((offset, c_size, d_size));
+        offset += c_size;
+    }
+    // the frames have to account for everything in front of the seek table
+    if offset != skippable_start {
+        return None;
+    }
+    Some(frames)
+}
+
+// decode all the frames into `out` with one thread per group of frames, the seek table is only
+// used to split the work, not to decode single frames on demand
+fn decode_seekable(data: &[u8], frames: &[SeekableFrame], out: &mut [u8]) -> Result<(), MemError> {
+    let num_threads = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(frames.len().max(1));
+    let per_thread = frames.len().div_ceil(num_threads);
+
+    let mut jobs: Vec<(&[SeekableFrame], &mut [u8])> = Vec::new();
+    let mut rest = out;
+    for group in frames.chunks(per_thread.max(1)) {
+        let group_size: u64 = group.iter().map(|f| f.2).sum();
+        let (chunk, tail) = rest.split_at_mut(group_size as usize);
+        jobs.push((group, chunk));
+        rest = tail;
+    }
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = jobs
+            .into_iter()
+            .map(|(group, chunk)| {
+                scope.spawn(move || -> Result<(), String> {
+                    let mut pos: usize = 0;
+                    for (offset, c_size, d_size) in group.iter() {
+                        let src = &data[*offset as usize..(*offset + *c_size) as usize];
+                        let dst = &mut chunk[pos..pos + *d_size as usize];
+                        let written = zstd::bulk::decompress_to_buffer(src, dst)
+                            .map_err(|e| format!("frame at {:08x}: {}", offset, e))?;
+                        if written as u64 != *d_size {
+                            return Err(format!(
+                                "frame at {:08x} decompressed to {} bytes, expected {}",
+                                offset, written, d_size
+                            ));
+                        }
+                        pos += *d_size as usize;
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+        for handle in handles {
+            match handle.join() {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    let msg = format!("Failed to decode seekable zstd {}", e);
+                    return Err(MemError::invalid_format(msg));
+                }
+                Err(_) => {
+                    let msg = "A seekable zstd decode thread panicked";
+                    return Err(MemError::invalid_format(msg));
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+fn decode_stream(path: &Path, compression: Compression, out: &mut File) -> Result<(), MemError> {
+    let input = BufReader::new(File::open(path)?);
+    let mut reader: Box<dyn Read> = match compression {
+        Compression::Zstd => Box::new(zstd::stream::read::Decoder::new(input)?),
+        Compression::Gzip => Box::new(MultiGzDecoder::new(input)),
+        Compression::Xz => Box::new(XzDecoder::new_multi_decoder(input)),
+    };
+    let mut writer = BufWriter::new(out);
+    copy(&mut reader, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn decode_to(path: &Path, compression: Compression, target: &Path) -> Result<(), MemError> {
+    let mut out = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(target)?;
+
+    if compression == Compression::Zstd {
+        let input = File::open(path)?;
+        // SAFETY: read-only mapping of the input, which is not modified while decoding.
+        let data = unsafe { Mmap::map(&input)? };
+        if let Some(frames) = seekable_frames(&data) {
+            let total: u64 = frames.iter().map(|f| f.2).sum();
+            debug!(
+                "Decoding {} seekable zstd frames ({} bytes) in parallel.",
+                frames.len(),
+                total
+            );
+            out.set_len(total)?;
+            if total > 0 {
+                // SAFETY: the file was just created for this decode and is not shared yet.
+                let mut map = unsafe { MmapMut::map_mut(&out)? };
+                decode_seekable(&data, &frames, &mut map)?;
+                map.flush()?;
+            }
+            return Ok(());
+        }
+    }
+    debug!(
+        "Stream decoding {:?} input: {}",
+        compression,
+        path.display()
+    );
+    decode_stream(path, compression, &mut out)
+}
+
+// the cache entry is keyed on the path, size and modification time of the compressed file
+fn cache_path(path: &Path) -> Result<PathBuf, MemError> {
+    let meta = path.metadata()?;
+    // a clock before the epoch only makes the key less unique
+    let mtime = meta
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let canonical = path.canonicalize()?;
+    let key = format!("{}:{}:{}", canonical.display(), meta.len(), mtime);
+    let digest = md5::compute(key.as_bytes());
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let dir = match std::env::var_os(CACHE_DIR_ENV) {
+        Some(dir) => PathBuf::from(dir),
+        None => std::env::temp_dir().join("mem-analysis-cache"),
+    };
+    Ok(dir.join(format!("{}-{:x}.raw", stem, digest)))
+}
+
+/// The path of the uncompressed contents of `path`.  Compressed files are decoded once into
+/// the cache directory (see `CACHE_DIR_ENV`) and the cached copy is reused afterwards, other
+/// files are returned as is.
+///
+/// The whole dump is decoded before anything can be read from it, so the first run pays for a
+/// full decode and needs the uncompressed size in free space in the cache directory.  Random
+/// access is over the decoded copy; seekable zstd input is decoded faster because its frames
+/// are decoded in parallel, but it is not decoded frame by frame on demand.
+pub fn decompressed_path(path: &Path) -> Result<PathBuf, MemError> {
+    let compression = match Compression::from_path(path)? {
+        Some(compression) => compression,
+        None => return Ok(path.to_path_buf()),
+    };
+    let cached = cache_path(path)?;
+    if cached.is_file() {
+        debug!(
+            "Using the decompressed copy of {}: {}",
+            path.display(),
+            cached.display()
+        );
+        return Ok(cached);
+    }
+    if let Some(dir) = cached.parent() {
+        create_dir_all(dir)?;
+    }
+    // decode next to the cache entry and only move it in place once complete
+    let partial = cached.with_extension(format!("partial.{}", std::process::id()));
+    debug!(
+        "Decompressing {:?} input {} into: {}",
+        compression,
+        path.display(),
+        cached.display()
+    );
+    if let Err(e) = decode_to(path, compression, &partial) {
+        let _ = std::fs::remove_file(&partial);
+        let msg = format!("Unable to decompress {}: {}", path.display(), e);
+        return Err(MemError::invalid_format(msg));
+    }
+    rename(&partial, &cached)?;
+    Ok(cached)
+}
+
+/// Open `path`, or the decompressed copy of it if it is compressed.
+pub fn open_decompressed(path: &Path) -> Result<File, MemError> {
+    Ok(File::open(decompressed_path(path)?)?)
+}
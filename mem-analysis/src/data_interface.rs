@@ -1,15 +1,31 @@
 use crate::buffer::DataBuffer;
+use crate::compressed::decompressed_path;
+use crate::elfcore::{ElfCoreRegionInfos, ELF_MAGIC};
+use crate::error::MemError;
+use crate::lime::{LimeRangeInfos, LIME_MAGIC};
+use crate::mdmp::MinidumpRegionInfos;
+use crate::pagelookup::PageLookup;
+use crate::pagetable::{PageTableRegionInfos, PhysicalRuns};
 use crate::memory::{MemRange, MemRanges};
 use crate::pointers::PointerRange;
 use crate::radare::RadareMemoryInfos;
+use crate::reader::{VirtualCursor, WordArgs};
+use crate::volatility::{VolatilityMemmap, VolatilityRegionInfos, VolatilityVadInfos};
+use binread::{BinRead, Endian, ReadOptions};
 use byteorder::{BigEndian, ByteOrder, LittleEndian};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::fs::File;
+use std::io::{Cursor, Read};
+use std::path::Path;
+use std::str::FromStr;
 use std::mem::{size_of};
 
-use log::debug;
+use log::{debug, error};
 use serde;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+
+pub const MINIDUMP_MAGIC: &[u8; 4] = b"MDMP";
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct ReadValue {
@@ -17,53 +33,131 @@ pub struct ReadValue {
     pub value: u64,
 }
 
-#[derive(Debug, PartialEq, Clone, Serialize)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, Default)]
 pub enum ENDIAN {
     BIG,
+    #[default]
     LITTLE,
 }
 
-#[derive(Debug, Clone, Serialize)]
+/// CPU architecture of the dumped process, this decides the pointer width used by the searches.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize, Default)]
+pub enum Arch {
+    X86,
+    X64,
+    Arm,
+    Arm64,
+    #[default]
+    Unknown,
+}
+
+impl Arch {
+    /// Pointer width in bytes, unknown architectures are assumed to be 64-bit.
+    pub fn word_sz(&self) -> u8 {
+        match self {
+            Arch::X86 | Arch::Arm => 4,
+            Arch::X64 | Arch::Arm64 | Arch::Unknown => 8,
+        }
+    }
+
+    /// Architecture this tool was built for, used when the dump does not say.
+    pub fn host() -> Self {
+        if cfg!(target_arch = "x86") {
+            Arch::X86
+        } else if cfg!(target_arch = "x86_64") {
+            Arch::X64
+        } else if cfg!(target_arch = "arm") {
+            Arch::Arm
+        } else if cfg!(target_arch = "aarch64") {
+            Arch::Arm64
+        } else {
+            Arch::Unknown
+        }
+    }
+}
+
+impl Display for Arch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let name = match self {
+            Arch::X86 => "x86",
+            Arch::X64 => "x64",
+            Arch::Arm => "arm",
+            Arch::Arm64 => "arm64",
+            Arch::Unknown => "unknown",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Arch {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "x86" | "i386" | "i686" | "wow64" => Ok(Arch::X86),
+            "x64" | "x86_64" | "amd64" => Ok(Arch::X64),
+            "arm" | "arm32" => Ok(Arch::Arm),
+            "arm64" | "aarch64" => Ok(Arch::Arm64),
+            _ => Err(format!("Unknown architecture: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VMemInfo {
     pub page_mask: u64,
     pub page_size: u64,
     pub word_sz: u8,
     pub alignment: u8,
     pub ptr_ranges: Box<HashMap<u64, Box<PointerRange>>>,
-    pub ptr_lookup: Box<HashMap<u64, Box<PointerRange>>>,
+    /// Page to range lookup, rebuilt from `ptr_ranges` rather than saved.
+    #[serde(skip)]
+    pub ptr_lookup: Box<PageLookup>,
     pub endian: ENDIAN,
+    pub arch: Arch,
+}
+
+impl Default for VMemInfo {
+    fn default() -> Self {
+        VMemInfo::new()
+    }
 }
 
 #[warn(dead_code)]
 impl VMemInfo {
-    fn new() -> Self {
+    pub fn new() -> Self {
         VMemInfo {
             page_mask: 0xfffffffffffff000 as u64,
             page_size: 4096 as u64,
             word_sz: 8,
             alignment: 8,
             ptr_ranges: Box::new(HashMap::new()),
-            ptr_lookup: Box::new(HashMap::new()),
+            ptr_lookup: Box::new(PageLookup::new(4096)),
             endian: ENDIAN::LITTLE,
+            arch: Arch::Unknown,
         }
     }
 
+    pub fn from_arch(arch: Arch, endian: ENDIAN) -> Self {
+        let mut vmem_info = VMemInfo::new();
+        vmem_info.set_arch(arch, endian);
+        vmem_info
+    }
+
+    /// Set the architecture along with the matching word size and pointer alignment.
+    pub fn set_arch(&mut self, arch: Arch, endian: ENDIAN) {
+        self.arch = arch;
+        self.endian = endian;
+        let word_sz = self.set_word_sz(Some(arch.word_sz()));
+        self.set_alignment(Some(word_sz));
+    }
+
     fn get_vaddr_base(&self, vaddr: &u64) -> Option<u64> {
-        let cpage = self.get_page(vaddr);
-        if self.ptr_lookup.contains_key(&cpage) {
-            let ptr_range = &self.ptr_lookup.get(&cpage).unwrap();
-            return Some(ptr_range.vstart);
-        }
-        return None;
+        return self.ptr_lookup.get(*vaddr).map(|ptr_range| ptr_range.vstart);
     }
 
     fn get_paddr_base_from_vaddr(&self, vaddr: &u64) -> Option<u64> {
-        let cpage = self.get_page(vaddr);
-        if self.ptr_lookup.contains_key(&cpage) {
-            let ptr_range = &self.ptr_lookup.get(&cpage).unwrap();
-            return Some(ptr_range.pstart);
-        }
-        return None;
+        return self.ptr_lookup.get(*vaddr).map(|ptr_range| ptr_range.pstart);
     }
 
     fn convert_vaddr_to_paddr(&self, vaddr: &u64) -> Option<u64> {
@@ -77,12 +171,7 @@ impl VMemInfo {
 
     }
 
-    fn get_page(&self, addr: &u64) -> u64 {
-        return addr & self.page_mask;
-    }
-
-    #[warn(dead_code)]
-    fn set_alignment(&mut self, o_alignment: Option<u8>) -> u8 {
+    pub fn set_alignment(&mut self, o_alignment: Option<u8>) -> u8 {
         let alignment: u8 = match o_alignment {
             Some(a) => match a {
                 0 => 0,
@@ -99,8 +188,7 @@ impl VMemInfo {
         return self.alignment;
     }
 
-    #[warn(dead_code)]
-    fn set_word_sz(&mut self, o_word_sz: Option<u8>) -> u8 {
+    pub fn set_word_sz(&mut self, o_word_sz: Option<u8>) -> u8 {
         let word_sz: u8 = match o_word_sz {
             Some(a) => match a {
                 2 => 2,
@@ -122,35 +210,40 @@ impl VMemInfo {
     }
 
     pub fn contains_pointer_range(&self, vaddr: &u64) -> bool {
-        let cpage = vaddr & self.page_mask;
-        if self.ptr_ranges.contains_key(&cpage) {
+        if self.ptr_lookup.contains(*vaddr) {
             return true;
         }
-        return self.ptr_lookup.contains_key(&cpage);
+        let cpage = vaddr & self.page_mask;
+        return self.ptr_ranges.contains_key(&cpage);
     }
 
     pub fn get_pointer_range_by_vaddr(&self, vaddr: u64) -> Option<Box<PointerRange>> {
-        let cpage = vaddr & self.page_mask;
-        if !self.ptr_lookup.contains_key(&cpage) {
-            return None;
-        }
-        return Some(self.ptr_lookup.get(&cpage).unwrap().clone());
+        return self.ptr_lookup.get(vaddr).map(|ptr_range| Box::new(ptr_range.clone()));
     }
 
-    pub fn create_pointer_range(&mut self, paddr: u64, vaddr: u64, size: u64) -> Box<PointerRange> {
+    pub fn create_pointer_range(
+        &mut self,
+        paddr: u64,
+        vaddr: u64,
+        size: u64,
+    ) -> Result<Box<PointerRange>, MemError> {
         debug!(
             "Creating a pointer range for paddr: {:08x} vaddr: {:08x} of size: {:08x}",
             paddr, vaddr, size
         );
-        let ptr_range = Box::new(PointerRange::new(paddr, paddr + size, vaddr, vaddr + size));
+        let (pend, vend) = match (paddr.checked_add(size), vaddr.checked_add(size)) {
+            (Some(pend), Some(vend)) => (pend, vend),
+            _ => return Err(MemError::OutOfRange { addr: vaddr, size }),
+        };
+        let ptr_range = Box::new(PointerRange::new(paddr, pend, vaddr, vend)?);
         self.add_pointer_range(ptr_range.clone());
-        return ptr_range.clone();
+        return Ok(ptr_range);
     }
 
     pub fn add_pointer_range(&mut self, ptr_range: Box<PointerRange>) -> bool {
-        let mut cpage = ptr_range.vstart & self.page_mask;
+        let cpage = ptr_range.vstart & self.page_mask;
 
-        if self.ptr_lookup.contains_key(&cpage) {
+        if self.ptr_lookup.contains(cpage) {
             return false;
         }
         debug!(
@@ -159,15 +252,11 @@ impl VMemInfo {
         );
         self.ptr_ranges.insert(cpage, ptr_range.clone());
 
-        let vaddr_end = ptr_range.vend;
         debug!(
             "Updating the lookup cache: {:08x} for {:08x}",
             ptr_range.vstart, cpage
         );
-        while cpage < vaddr_end {
-            self.ptr_lookup.insert(cpage, ptr_range.clone());
-            cpage = (self.page_size + cpage) & self.page_mask
-        }
+        self.ptr_lookup.insert(&ptr_range);
         debug!(
             "Done updating the lookup cache: {:08x} with a len() : {:08x}",
             ptr_range.vstart,
@@ -175,6 +264,21 @@ impl VMemInfo {
         );
         return true;
     }
+
+    /// Recreate the per page lookup from `ptr_ranges`, e.g. after loading a saved index
+    /// which leaves the lookup out.
+    pub fn rebuild_ptr_lookup(&mut self) {
+        let mut ptr_lookup = PageLookup::new(self.page_size);
+        // same order as `new_from_mem_ranges` adds them, so shared pages resolve the same way
+        let mut ptr_ranges: Vec<&PointerRange> =
+            self.ptr_ranges.values().map(|r| r.as_ref()).collect();
+        ptr_ranges.sort_by_key(|r| r.vstart);
+        for ptr_range in ptr_ranges {
+            ptr_lookup.insert(ptr_range);
+        }
+        *self.ptr_lookup = ptr_lookup;
+    }
+
     pub fn get_pointer_range_vec(&self) -> Vec<Box<PointerRange>> {
         let mut v_ranges = Vec::new();
         for (_key, pr) in self.ptr_ranges.iter() {
@@ -219,26 +323,196 @@ impl DataInterface {
     }
 
     pub fn new_from_radare_info(
-        bin_file: &PathBuf,
+        bin_file: &Path,
         radare_infos: &RadareMemoryInfos,
         o_vmem_info: Option<VMemInfo>,
+    ) -> Result<Self, MemError> {
+        let mem_ranges = MemRanges::from_radare_infos(radare_infos);
+        Ok(DataInterface::new_from_mem_ranges(
+            DataBuffer::from_pathbuf(bin_file, true)?,
+            mem_ranges,
+            o_vmem_info,
+        ))
+    }
+
+    /// Build the interface straight from a Windows minidump.  The memory ranges come from the
+    /// Memory64List/MemoryList and MemoryInfoList streams, so no radare2 sections file is needed.
+    pub fn from_minidump(bin_file: &Path) -> Result<Self, MemError> {
+        let buffer = DataBuffer::from_pathbuf(bin_file, true)?;
+        let infos = match buffer.get_shared_buffer() {
+            Some(data) => MinidumpRegionInfos::from_minidump_bytes(data)?,
+            None => {
+                let msg = format!("Unable to read minidump: {}", bin_file.display());
+                return Err(MemError::invalid_format(msg));
+            }
+        };
+        debug!(
+            "Loaded {} memory regions from minidump: {}",
+            infos.items.len(),
+            bin_file.display()
+        );
+        let mem_ranges = MemRanges::from_minidump_infos(&infos);
+        let vmem_info = VMemInfo::from_arch(infos.arch, ENDIAN::LITTLE);
+        Ok(DataInterface::new_from_mem_ranges(buffer, mem_ranges, Some(vmem_info)))
+    }
+
+    /// Build the interface from a Linux ELF core file.  Each PT_LOAD segment becomes a memory
+    /// range and the NT_FILE note provides the names of file backed mappings.
+    pub fn from_elf_core(bin_file: &Path) -> Result<Self, MemError> {
+        let buffer = DataBuffer::from_pathbuf(bin_file, true)?;
+        let infos = match buffer.get_shared_buffer() {
+            Some(data) => ElfCoreRegionInfos::from_elf_core_bytes(data)?,
+            None => {
+                let msg = format!("Unable to read core file: {}", bin_file.display());
+                return Err(MemError::invalid_format(msg));
+            }
+        };
+        debug!(
+            "Loaded {} segments from ELF core file: {}",
+            infos.items.len(),
+            bin_file.display()
+        );
+        let mem_ranges = MemRanges::from_elf_core_infos(&infos);
+        let vmem_info = VMemInfo::from_arch(infos.arch, infos.endian.clone());
+        Ok(DataInterface::new_from_mem_ranges(buffer, mem_ranges, Some(vmem_info)))
+    }
+
+    /// Build the interface from a physical memory image and the Volatility3 `windows.memmap`
+    /// (and optionally `windows.vadinfo`) JSON output for one process.  The memmap gives the
+    /// image offset of every resident page, the VADs provide protections and mapped file names.
+    pub fn from_volatility(
+        image_file: &Path,
+        memmap_json: &Path,
+        o_vadinfo_json: Option<&Path>,
+    ) -> Result<Self, MemError> {
+        let memmap = VolatilityMemmap::from_memmap_json(memmap_json)?;
+        let vads = match o_vadinfo_json {
+            Some(path) => Some(VolatilityVadInfos::from_vadinfo_json(path)?),
+            None => None,
+        };
+        let infos = VolatilityRegionInfos::from_volatility(&memmap, vads.as_ref());
+        debug!(
+            "Loaded {} memory runs from Volatility output: {}",
+            infos.items.len(),
+            memmap_json.display()
+        );
+        let buffer = DataBuffer::from_pathbuf(image_file, true)?;
+        let mem_ranges = MemRanges::from_volatility_infos(&infos);
+        Ok(DataInterface::new_from_mem_ranges(buffer, mem_ranges, None))
+    }
+
+    /// Physical memory of a LiME capture, the virtual addresses are the physical addresses.
+    pub fn from_lime(bin_file: &Path) -> Result<Self, MemError> {
+        let buffer = DataBuffer::from_pathbuf(bin_file, true)?;
+        let infos = match buffer.get_shared_buffer() {
+            Some(data) => LimeRangeInfos::from_lime_bytes(data)?,
+            None => {
+                let msg = format!("Unable to read LiME file: {}", bin_file.display());
+                return Err(MemError::invalid_format(msg));
+            }
+        };
+        debug!(
+            "Loaded {} ranges from LiME file: {}",
+            infos.items.len(),
+            bin_file.display()
+        );
+        let mem_ranges = MemRanges::from_lime_infos(&infos);
+        Ok(DataInterface::new_from_mem_ranges(buffer, mem_ranges, None))
+    }
+
+    /// Build the interface for one process of a physical memory image by walking the x86-64
+    /// page tables at `dtb` (the CR3 value of the process).  The image is either raw or a
+    /// LiME capture.
+    pub fn from_physical_image(image_file: &Path, dtb: u64) -> Result<Self, MemError> {
+        let buffer = DataBuffer::from_pathbuf(image_file, true)?;
+        let runs = match buffer.get_shared_buffer() {
+            Some(data) if data.starts_with(LIME_MAGIC) => {
+                LimeRangeInfos::from_lime_bytes(data)?.physical_runs()
+            }
+            Some(_) => PhysicalRuns::identity(buffer.size),
+            None => {
+                let msg = format!("Unable to read image: {}", image_file.display());
+                return Err(MemError::invalid_format(msg));
+            }
+        };
+        DataInterface::from_physical_runs(buffer, &runs, dtb)
+    }
+
+    /// Like `from_physical_image` for images where `runs` locate physical memory in the file.
+    pub fn from_physical_runs(
+        buffer: DataBuffer,
+        runs: &PhysicalRuns,
+        dtb: u64,
+    ) -> Result<Self, MemError> {
+        let infos = PageTableRegionInfos::from_physical_image(&buffer, runs, dtb);
+        if infos.items.is_empty() {
+            let msg = format!("No pages are mapped by the page tables at dtb: {:08x}", dtb);
+            return Err(MemError::invalid_format(msg));
+        }
+        let mem_ranges = MemRanges::from_page_table_infos(&infos);
+        let vmem_info = VMemInfo::from_arch(Arch::X64, ENDIAN::LITTLE);
+        Ok(DataInterface::new_from_mem_ranges(buffer, mem_ranges, Some(vmem_info)))
+    }
+
+    /// Attach to a running process.  Regions come from `/proc/<pid>/maps` and their bytes are
+    /// read lazily from `/proc/<pid>/mem` when a search first touches them.
+    pub fn from_live_process(pid: u32) -> Result<Self, MemError> {
+        let buffer = DataBuffer::from_live_process(pid)?;
+        let (mem_ranges, vmem_info) = match &buffer.live {
+            Some(live) => (
+                MemRanges::from_proc_maps(&live.maps),
+                Some(VMemInfo::from_arch(live.arch, live.endian.clone())),
+            ),
+            None => (MemRanges::new(), None),
+        };
+        Ok(DataInterface::new_from_mem_ranges(buffer, mem_ranges, vmem_info))
+    }
+
+    /// Pick the loader based on the magic at the start of the file.
+    pub fn from_dump_file(bin_file: &Path) -> Result<Self, MemError> {
+        // compressed dumps are sniffed (and later mapped) through their decompressed copy
+        let bin_file = &decompressed_path(bin_file)?;
+        let mut magic = [0u8; 4];
+        File::open(bin_file)?.read_exact(&mut magic)?;
+        if &magic == MINIDUMP_MAGIC {
+            DataInterface::from_minidump(bin_file)
+        } else if &magic == ELF_MAGIC {
+            DataInterface::from_elf_core(bin_file)
+        } else if &magic == LIME_MAGIC {
+            DataInterface::from_lime(bin_file)
+        } else {
+            Err(MemError::unsupported(format!(
+                "Unrecognized dump format for {}, a sections file is required",
+                bin_file.display()
+            )))
+        }
+    }
+
+    pub fn new_from_mem_ranges(
+        mut buffer: DataBuffer,
+        mem_ranges: MemRanges,
+        o_vmem_info: Option<VMemInfo>,
     ) -> Self {
         let mut vmem_info = match o_vmem_info {
-            Some(v) => Box::new(v.clone()),
+            Some(v) => Box::new(v),
             None => Box::new(VMemInfo::new()),
         };
-        let mem_ranges = Box::new(MemRanges::from_radare_infos(&radare_infos));
 
         for (_k, mr) in mem_ranges.vmem_ranges.iter() {
             let size = &mr.size;
             let paddr = &mr.paddr_start;
             let vaddr = &mr.vaddr_start;
-            vmem_info.create_pointer_range(*paddr, *vaddr, *size);
+            if let Err(e) = vmem_info.create_pointer_range(*paddr, *vaddr, *size) {
+                error!("Skipping the pointer range of {}: {}", mr.name, e);
+            }
+            if let Err(e) = buffer.add_vaddr_mapping(*vaddr, *paddr, (*size).min(mr.vsize)) {
+                error!("Skipping the address mapping of {}: {}", mr.name, e);
+            }
         }
         DataInterface {
-            buffer: Box::new(DataBuffer::from_pathbuf(bin_file, true)),
-            mem_ranges: mem_ranges,
-            vmem_info: vmem_info,
+            buffer: Box::new(buffer),
+            mem_ranges: Box::new(mem_ranges),
+            vmem_info,
         }
     }
 
@@ -248,9 +522,8 @@ impl DataInterface {
             return None;
         }
         let mr: Box<MemRange> = o_mr.unwrap();
-        let paddr_start = mr.paddr_start;
-        let psize = mr.size;
-        if paddr + read_size < paddr_start + psize {
+        let offset = paddr - mr.paddr_start;
+        if offset.checked_add(read_size)? < mr.size {
             return self.buffer.get_shared_slice_from(paddr, Some(read_size));
         }
         return None;
@@ -265,17 +538,11 @@ impl DataInterface {
     }
 
     pub fn shared_slice_vaddr(&self, vaddr: u64, read_size: u64) -> Option<&[u8]> {
-        let o_sb = self.shared_buffer_vaddr(vaddr);
-        if o_sb.is_none() {
-            return None;
-        }
-
-        let sb = o_sb.unwrap();
-        let mr: Box<MemRange> = self.mem_ranges.get_vaddr_range(vaddr).unwrap();
+        let mr: Box<MemRange> = self.mem_ranges.get_vaddr_range(vaddr)?;
 
         let offset = vaddr - mr.vaddr_start;
-        if mr.paddr_start + offset + read_size < mr.paddr_start + mr.size {
-            return Some(&sb[offset as usize..(offset + read_size) as usize]);
+        if offset.checked_add(read_size)? < mr.size.min(mr.vsize) {
+            return self.buffer.get_shared_slice_from(mr.paddr_start + offset, Some(read_size));
         }
         return None;
     }
@@ -289,23 +556,80 @@ impl DataInterface {
 
         let paddr_base = mr.paddr_start;
         let vsize = mr.vsize;
-        let vaddr_base = mr.vaddr_start;
-        assert_eq!(true, vaddr_base <= vaddr && vaddr < vaddr_base + vsize);
-        return self.buffer.get_shared_slice_from(paddr_base, Some(vsize));
+        if vaddr < mr.vaddr_start || vaddr - mr.vaddr_start >= vsize {
+            return None;
+        }
+        // only the first `size` bytes of the range are backed by the file (e.g. ELF segments
+        // where p_filesz < p_memsz), and live memory stops at the first unreadable block
+        return self.buffer.get_partial_slice_from(paddr_base, vsize.min(mr.size));
+    }
+
+    /// Up to `size` bytes starting at `vaddr`, which may lie anywhere in its range.  Cut short
+    /// like `shared_buffer_vaddr` at the end of the backed part of the range, so a search over
+    /// one slice of a large range does not fetch the rest of it.
+    pub fn shared_buffer_vaddr_at(&self, vaddr: u64, size: u64) -> Option<&[u8]> {
+        let mr = self.mem_ranges.get_vaddr_range(vaddr)?;
+        let offset = vaddr.checked_sub(mr.vaddr_start)?;
+        let backed = mr.vsize.min(mr.size);
+        if offset >= backed {
+            return None;
+        }
+        let paddr = mr.paddr_start.checked_add(offset)?;
+        self.buffer.get_partial_slice_from(paddr, size.min(backed - offset))
     }
 
     pub fn shared_buffer_paddr(&self, paddr: u64) -> Option<&[u8]> {
         return self.buffer.get_shared_slice_from(paddr, None);
     }
 
-    // pub fn read_struct(&self, vaddr: u64, stype: impl BinRead) -> Option<impl BinRead> {
-    //     let o_sbo = self.shared_buffer_vaddr(vaddr);
-    //     let offset = self.
-    //     let result = stype::read(&sbo);
-    // }
+    fn read_options(&self) -> ReadOptions {
+        let mut options = ReadOptions::default();
+        options.endian = Endian::from(&self.vmem_info.endian);
+        options
+    }
+
+    /// Decode a `T` at `vaddr` using the endianness and word size of the address space.  The
+    /// structure may straddle contiguous memory ranges.
+    pub fn read_struct<T: BinRead>(&self, vaddr: u64) -> Option<T>
+    where
+        T::Args: WordArgs,
+    {
+        let mut cursor = VirtualCursor::new(self, vaddr);
+        let args = T::Args::from_word_sz(self.vmem_info.word_sz);
+        return T::read_options(&mut cursor, &self.read_options(), args).ok();
+    }
+
+    /// Decode `count` consecutive `T` starting at `vaddr`.
+    pub fn read_array<T: BinRead>(&self, vaddr: u64, count: usize) -> Option<Vec<T>>
+    where
+        T::Args: WordArgs,
+    {
+        let mut cursor = VirtualCursor::new(self, vaddr);
+        let options = self.read_options();
+        let mut items = Vec::with_capacity(count);
+        for _ in 0..count {
+            let args = T::Args::from_word_sz(self.vmem_info.word_sz);
+            items.push(T::read_options(&mut cursor, &options, args).ok()?);
+        }
+        return Some(items);
+    }
+
+    /// Decode a `T` from the start of `buffer`, like `read_struct` but for bytes that were
+    /// already pulled out of the dump.
+    pub fn parse_struct<T: BinRead>(&self, buffer: &[u8]) -> Option<T>
+    where
+        T::Args: WordArgs,
+    {
+        let mut cursor = Cursor::new(buffer);
+        let args = T::Args::from_word_sz(self.vmem_info.word_sz);
+        return T::read_options(&mut cursor, &self.read_options(), args).ok();
+    }
 
     pub fn can_read_buffer(&self, buffer: &[u8], pos: u64, size: u64) -> bool {
-        return (pos + size) < (buffer.len() as u64);
+        return match pos.checked_add(size) {
+            Some(end) => end < (buffer.len() as u64),
+            None => false,
+        };
     }
 
     pub fn can_read_buffer_at(&self, vaddr: u64, size: u64) -> bool {
@@ -314,9 +638,13 @@ impl DataInterface {
             return false;
         }
         let mr: Box<MemRange> = o_mr.unwrap().clone();
-        let vaddr_base = mr.vaddr_start;
-        let vaddr_end = mr.vaddr_start + mr.vsize;
-        return vaddr_base <= vaddr && vaddr + size < vaddr_end;
+        if vaddr < mr.vaddr_start {
+            return false;
+        }
+        return match (vaddr - mr.vaddr_start).checked_add(size) {
+            Some(end) => end < mr.vsize,
+            None => false,
+        };
     }
 
     pub fn read_word_size_value(&self, buffer: &[u8]) -> Option<ReadValue> {
@@ -369,15 +697,19 @@ impl DataInterface {
             // debug!("Failed to find the range for vaddr: {:08x}", vaddr  );
             return None;
         }
-        // debug!("Attempting to get the shared memory for vaddr: {:08x}", vaddr  );
-        let o_fbuffer = self.shared_buffer_vaddr(vaddr);
+        // only the word itself, rather than the whole range it is in
+        let pos = vaddr - o_vaddr_base.unwrap();
+        let mr = self.mem_ranges.get_vaddr_range(vaddr)?;
+        let word_sz = self.vmem_info.word_sz as u64;
+        if pos.checked_add(word_sz)? > mr.size.min(mr.vsize) {
+            return None;
+        }
+        let o_fbuffer = self.buffer.get_shared_slice_from(mr.paddr_start + pos, Some(word_sz));
         if o_fbuffer.is_none() {
             // debug!("Failed to get the shared memory for vaddr: {:08x}", vaddr  );
             return None;
         }
-        let pos = vaddr - o_vaddr_base.unwrap();
-        // debug!("Attempting to read the shared memory for vaddr: buffer[{:08x}] ({})", pos, vaddr  );
-        return self.read_word_size_value_at_pos(o_fbuffer.unwrap(), pos);
+        return self.read_word_size_value_at_pos(o_fbuffer.unwrap(), 0);
     }
 
     pub fn get_vaddr_end(&self, vaddr: u64) -> Option<u64> {
@@ -386,7 +718,7 @@ impl DataInterface {
             return None;
         }
         let mr: Box<MemRange> = o_mr.unwrap().clone();
-        return Some(mr.vaddr_start + mr.vsize);
+        return mr.vaddr_start.checked_add(mr.vsize);
     }
 
     pub fn read_i64(&self, buffer: &[u8], o_endian: Option<ENDIAN>) -> Option<i64> {
@@ -0,0 +1,126 @@
+use crate::data_interface::DataInterface;
+use crate::memory::{BackendType, MemRange, MemRanges};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiagnosticKind {
+    /// The virtual range overlaps an earlier section, which replaced part of it.
+    VaddrOverlap { other: String, other_vaddr: u64 },
+    /// The section data (partly) lies past the end of the dump file.
+    PaddrPastEof { file_size: u64 },
+    /// The number of bytes in the file differs from the size of the virtual range.
+    SizeMismatch,
+    /// The section is empty, it is still added with a size of one byte.
+    ZeroSize,
+    /// The virtual or physical range runs past the end of the address space, the section is
+    /// skipped.
+    AddressOverflow,
+}
+
+/// A problem with one section of a section map.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SectionDiagnostic {
+    pub kind: DiagnosticKind,
+    pub name: String,
+    pub vaddr: u64,
+    pub paddr: u64,
+    pub size: u64,
+    pub vsize: u64,
+}
+
+impl SectionDiagnostic {
+    pub fn new(kind: DiagnosticKind, mr: &MemRange) -> Self {
+        SectionDiagnostic {
+            kind,
+            name: mr.name.clone(),
+            vaddr: mr.vaddr_start,
+            paddr: mr.paddr_start,
+            size: mr.size,
+            vsize: mr.vsize,
+        }
+    }
+
+    /// Fewer bytes in the file than the virtual size is normal for partially captured
+    /// segments (e.g. ELF core files), everything else means the section map is wrong.
+    pub fn severity(&self) -> Severity {
+        match self.kind {
+            DiagnosticKind::SizeMismatch if self.size < self.vsize => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity() == Severity::Error
+    }
+}
+
+impl Display for SectionDiagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let problem = match &self.kind {
+            DiagnosticKind::VaddrOverlap { other, other_vaddr } => {
+                format!("overlaps {} at vaddr={:08x}", other, other_vaddr)
+            }
+            DiagnosticKind::PaddrPastEof { file_size } => {
+                format!("extends past the end of the file ({:08x})", file_size)
+            }
+            DiagnosticKind::SizeMismatch => "size and vsize differ".to_string(),
+            DiagnosticKind::ZeroSize => "is empty".to_string(),
+            DiagnosticKind::AddressOverflow => "wraps the address space".to_string(),
+        };
+        write!(
+            f,
+            "{:?}: {} {} (paddr={:08x} size={:08x} vaddr={:08x} vsize={:08x})",
+            self.severity(),
+            self.name,
+            problem,
+            self.paddr,
+            self.size,
+            self.vaddr,
+            self.vsize
+        )
+    }
+}
+
+impl MemRanges {
+    /// The problems found while adding the sections plus the ones that depend on the dump,
+    /// `file_size` is `None` when there is no file to check against (e.g. a live process).
+    pub fn validate(&self, file_size: Option<u64>) -> Vec<SectionDiagnostic> {
+        let mut diagnostics = self.diagnostics.clone();
+        // a section split by an overlapping one shows up once per remaining piece
+        let mut seen = HashSet::new();
+        for mr in self.get_mem_ranges().iter() {
+            if !seen.insert((mr.vaddr_start, mr.paddr_start)) {
+                continue;
+            }
+            if mr.size != mr.vsize {
+                diagnostics.push(SectionDiagnostic::new(DiagnosticKind::SizeMismatch, mr));
+            }
+            if let Some(file_size) = file_size {
+                if mr.paddr_start.saturating_add(mr.size) > file_size {
+                    let kind = DiagnosticKind::PaddrPastEof { file_size };
+                    diagnostics.push(SectionDiagnostic::new(kind, mr));
+                }
+            }
+        }
+        diagnostics
+    }
+}
+
+impl DataInterface {
+    /// Validate the memory ranges against the dump they were loaded for.
+    pub fn section_diagnostics(&self) -> Vec<SectionDiagnostic> {
+        let file_size = match self.buffer.backend {
+            Some(BackendType::LiveProcess) => None,
+            _ => Some(self.buffer.size),
+        };
+        self.mem_ranges.validate(file_size)
+    }
+}
@@ -0,0 +1,353 @@
+use crate::data_interface::{Arch, ENDIAN};
+use crate::error::MemError;
+use crate::region::{MemState, MemType, Protection};
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use rangemap::RangeMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::path::Path;
+
+use log::debug;
+
+pub const ELF_MAGIC: &[u8; 4] = b"\x7fELF";
+
+const ELFCLASS32: u8 = 1;
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+const ELFDATA2MSB: u8 = 2;
+const ET_CORE: u16 = 4;
+
+const EM_386: u16 = 3;
+const EM_ARM: u16 = 40;
+const EM_X86_64: u16 = 62;
+const EM_AARCH64: u16 = 183;
+
+const PT_LOAD: u32 = 1;
+const PT_NOTE: u32 = 4;
+
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+
+const NT_FILE: u32 = 0x4649_4c45;
+
+/// A single PT_LOAD segment of an ELF core file.  The fields mirror `RadareMemoryInfo`, the
+/// `paddr` is the offset of the segment in the core file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElfCoreRegionInfo {
+    pub name: String,
+    pub size: u64,
+    pub vsize: u64,
+    pub perm: Protection,
+    pub paddr: u64,
+    pub vaddr: u64,
+    pub state: MemState,
+    pub mem_type: MemType,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ElfCoreRegionInfos {
+    pub items: Vec<ElfCoreRegionInfo>,
+    pub arch: Arch,
+    pub endian: ENDIAN,
+}
+
+impl Display for ElfCoreRegionInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "{} perms={} paddr={:08x} size={:08x} vaddr={:08x} vsize={:08x}",
+            self.name, self.perm, self.paddr, self.size, self.vaddr, self.vsize
+        )
+    }
+}
+
+/// Convert ELF program header flags into a `Protection`.
+pub fn flags_to_perm(p_flags: u32) -> Protection {
+    let mut perm = Protection::NONE;
+    if p_flags & PF_R != 0 {
+        perm |= Protection::READ;
+    }
+    if p_flags & PF_W != 0 {
+        perm |= Protection::WRITE;
+    }
+    if p_flags & PF_X != 0 {
+        perm |= Protection::EXECUTE;
+    }
+    perm
+}
+
+/// Architecture and byte order from the identification bytes and `e_machine` of an ELF
+/// header, works for any ELF file (core, executable or shared object).
+pub fn elf_header_arch(data: &[u8]) -> Option<(Arch, ENDIAN)> {
+    if data.len() < 20 || &data[0..4] != ELF_MAGIC {
+        return None;
+    }
+    let (endian, e_machine) = match data[5] {
+        ELFDATA2LSB => (ENDIAN::LITTLE, LittleEndian::read_u16(&data[18..20])),
+        ELFDATA2MSB => (ENDIAN::BIG, BigEndian::read_u16(&data[18..20])),
+        _ => return None,
+    };
+    let arch = match (e_machine, data[4]) {
+        (EM_386, _) => Arch::X86,
+        (EM_X86_64, ELFCLASS32) => Arch::X86, // x32 uses 4 byte pointers
+        (EM_X86_64, _) => Arch::X64,
+        (EM_ARM, _) => Arch::Arm,
+        (EM_AARCH64, ELFCLASS32) => Arch::Arm,
+        (EM_AARCH64, _) => Arch::Arm64,
+        _ => Arch::Unknown,
+    };
+    Some((arch, endian))
+}
+
+#[derive(Debug, Clone)]
+struct ProgramHeader {
+    p_type: u32,
+    p_flags: u32,
+    p_offset: u64,
+    p_vaddr: u64,
+    p_filesz: u64,
+    p_memsz: u64,
+}
+
+/// Byte order and word size aware reader over the raw core file.
+struct ElfReader<'a> {
+    data: &'a [u8],
+    is_64: bool,
+    big_endian: bool,
+}
+
+impl<'a> ElfReader<'a> {
+    fn bytes(&self, offset: u64, size: u64) -> Result<&'a [u8], MemError> {
+        let end = offset
+            .checked_add(size)
+            .filter(|end| *end <= self.data.len() as u64)
+            .ok_or(MemError::OutOfRange { addr: offset, size })?;
+        Ok(&self.data[offset as usize..end as usize])
+    }
+
+    fn u16(&self, offset: u64) -> Result<u16, MemError> {
+        let buf = self.bytes(offset, 2)?;
+        Ok(if self.big_endian {
+            BigEndian::read_u16(buf)
+        } else {
+            LittleEndian::read_u16(buf)
+        })
+    }
+
+    fn u32(&self, offset: u64) -> Result<u32, MemError> {
+        let buf = self.bytes(offset, 4)?;
+        Ok(if self.big_endian {
+            BigEndian::read_u32(buf)
+        } else {
+            LittleEndian::read_u32(buf)
+        })
+    }
+
+    fn u64(&self, offset: u64) -> Result<u64, MemError> {
+        let buf = self.bytes(offset, 8)?;
+        Ok(if self.big_endian {
+            BigEndian::read_u64(buf)
+        } else {
+            LittleEndian::read_u64(buf)
+        })
+    }
+
+    fn word(&self, offset: u64) -> Result<u64, MemError> {
+        if self.is_64 {
+            self.u64(offset)
+        } else {
+            self.u32(offset).map(|v| v as u64)
+        }
+    }
+
+    fn word_size(&self) -> u64 {
+        if self.is_64 {
+            8
+        } else {
+            4
+        }
+    }
+
+    fn program_header(&self, offset: u64) -> Result<ProgramHeader, MemError> {
+        // the whole header has to be in the file, the field offsets below can not overflow
+        self.bytes(offset, if self.is_64 { 56 } else { 32 })?;
+        if self.is_64 {
+            Ok(ProgramHeader {
+                p_type: self.u32(offset)?,
+                p_flags: self.u32(offset + 4)?,
+                p_offset: self.u64(offset + 8)?,
+                p_vaddr: self.u64(offset + 16)?,
+                p_filesz: self.u64(offset + 32)?,
+                p_memsz: self.u64(offset + 40)?,
+            })
+        } else {
+            Ok(ProgramHeader {
+                p_type: self.u32(offset)?,
+                p_offset: self.u32(offset + 4)? as u64,
+                p_vaddr: self.u32(offset + 8)? as u64,
+                p_filesz: self.u32(offset + 16)? as u64,
+                p_memsz: self.u32(offset + 20)? as u64,
+                p_flags: self.u32(offset + 24)?,
+            })
+        }
+    }
+
+    /// Parse the NT_FILE note into vaddr range -> mapped file name.
+    fn parse_nt_file(&self, desc: &[u8], names: &mut RangeMap<u64, String>) {
+        let reader = ElfReader {
+            data: desc,
+            is_64: self.is_64,
+            big_endian: self.big_endian,
+        };
+        let wsz = reader.word_size();
+        let (count, _page_size) = match (reader.word(0), reader.word(wsz)) {
+            (Ok(c), Ok(p)) => (c, p),
+            _ => return,
+        };
+        // a corrupt count can not describe more entries than the note holds
+        let count = count.min(desc.len() as u64 / (3 * wsz));
+        let mut names_pos = 2 * wsz + count * 3 * wsz;
+        for idx in 0..count {
+            let entry = 2 * wsz + idx * 3 * wsz;
+            let (start, end) = match (reader.word(entry), reader.word(entry + wsz)) {
+                (Ok(s), Ok(e)) => (s, e),
+                _ => return,
+            };
+            if names_pos >= desc.len() as u64 {
+                return;
+            }
+            let rest = &desc[names_pos as usize..];
+            let name_len = rest.iter().position(|b| *b == 0).unwrap_or(rest.len());
+            let name = String::from_utf8_lossy(&rest[..name_len]).to_string();
+            names_pos += name_len as u64 + 1;
+            if start < end {
+                names.insert(start..end, name);
+            }
+        }
+    }
+
+    fn parse_notes(&self, ph: &ProgramHeader, names: &mut RangeMap<u64, String>) {
+        let align = |v: u64| (v + 3) & !3;
+        let notes = match self.bytes(ph.p_offset, ph.p_filesz) {
+            Ok(n) => n,
+            Err(_) => return,
+        };
+        let reader = ElfReader {
+            data: notes,
+            is_64: self.is_64,
+            big_endian: self.big_endian,
+        };
+        let mut pos: u64 = 0;
+        while pos + 12 <= notes.len() as u64 {
+            let (namesz, descsz, ntype) = match (
+                reader.u32(pos),
+                reader.u32(pos + 4),
+                reader.u32(pos + 8),
+            ) {
+                (Ok(n), Ok(d), Ok(t)) => (n as u64, d as u64, t),
+                _ => return,
+            };
+            let desc_pos = pos + 12 + align(namesz);
+            if ntype == NT_FILE {
+                if let Ok(desc) = reader.bytes(desc_pos, descsz) {
+                    self.parse_nt_file(desc, names);
+                }
+            }
+            pos = desc_pos + align(descsz);
+        }
+    }
+}
+
+impl ElfCoreRegionInfos {
+    pub fn from_elf_core_path(path: &Path) -> Result<ElfCoreRegionInfos, MemError> {
+        let data = std::fs::read(path)?;
+        ElfCoreRegionInfos::from_elf_core_bytes(&data)
+    }
+
+    /// Parse the PT_LOAD program headers of an ELF core file that is already held in memory.
+    /// Mapping names come from the NT_FILE note, anonymous mappings get a generic name.
+    pub fn from_elf_core_bytes(data: &[u8]) -> Result<ElfCoreRegionInfos, MemError> {
+        if data.len() < 16 || &data[0..4] != ELF_MAGIC {
+            return Err(MemError::invalid_format(
+                "Not an ELF file, the magic does not match",
+            ));
+        }
+        let is_64 = match data[4] {
+            ELFCLASS32 => false,
+            ELFCLASS64 => true,
+            c => return Err(MemError::unsupported(format!("ELF class: {}", c))),
+        };
+        let big_endian = match data[5] {
+            ELFDATA2LSB => false,
+            ELFDATA2MSB => true,
+            d => return Err(MemError::unsupported(format!("ELF data encoding: {}", d))),
+        };
+        let reader = ElfReader {
+            data,
+            is_64,
+            big_endian,
+        };
+
+        let e_type = reader.u16(16)?;
+        if e_type != ET_CORE {
+            return Err(MemError::invalid_format(format!(
+                "ELF file is not a core file (e_type={})",
+                e_type
+            )));
+        }
+        let (e_phoff, e_phentsize, e_phnum) = if is_64 {
+            (reader.u64(32)?, reader.u16(54)?, reader.u16(56)?)
+        } else {
+            (reader.u32(28)? as u64, reader.u16(42)?, reader.u16(44)?)
+        };
+
+        let mut headers = Vec::new();
+        for idx in 0..e_phnum as u64 {
+            let offset = e_phoff
+                .checked_add(idx * e_phentsize as u64)
+                .ok_or(MemError::OutOfRange {
+                    addr: e_phoff,
+                    size: e_phnum as u64 * e_phentsize as u64,
+                })?;
+            headers.push(reader.program_header(offset)?);
+        }
+
+        let mut names: RangeMap<u64, String> = RangeMap::new();
+        for ph in headers.iter().filter(|ph| ph.p_type == PT_NOTE) {
+            reader.parse_notes(ph, &mut names);
+        }
+        debug!(
+            "Found {} program headers and {} file mappings in the core file.",
+            headers.len(),
+            names.len()
+        );
+
+        let mut infos = ElfCoreRegionInfos::default();
+        if let Some((arch, endian)) = elf_header_arch(data) {
+            infos.arch = arch;
+            infos.endian = endian;
+        }
+        for (section, ph) in headers.iter().filter(|ph| ph.p_type == PT_LOAD).enumerate() {
+            // segments that were not dumped have no bytes in the file
+            let size = ph
+                .p_filesz
+                .min((data.len() as u64).saturating_sub(ph.p_offset));
+            // file backed mappings are named by the NT_FILE note, everything else is anonymous
+            let (name, mem_type) = match names.get(&ph.p_vaddr) {
+                Some(name) => (name.clone(), MemType::Mapped),
+                None => (format!("Memory_Section_{}", section), MemType::Private),
+            };
+            infos.items.push(ElfCoreRegionInfo {
+                name,
+                size,
+                vsize: ph.p_memsz,
+                perm: flags_to_perm(ph.p_flags),
+                paddr: ph.p_offset,
+                vaddr: ph.p_vaddr,
+                state: MemState::Commit,
+                mem_type,
+            });
+        }
+        Ok(infos)
+    }
+}
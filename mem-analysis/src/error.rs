@@ -0,0 +1,99 @@
+use std::error::Error as StdErr;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::Error as IoError;
+
+/// Errors returned by the loaders and readers of this crate.
+#[derive(Debug)]
+pub enum MemError {
+    Io(IoError),
+    /// A sections, Volatility or index file that could not be deserialized.
+    Json(serde_json::Error),
+    Index(bincode::Error),
+    /// A structure read with `binread` from a dump or from memory.
+    Parse(binread::Error),
+    Minidump(minidump::Error),
+    /// The input is not in the format it was expected to be in.
+    InvalidFormat(String),
+    /// A read of `size` bytes at `addr` is not backed by the dump.
+    OutOfRange {
+        addr: u64,
+        size: u64,
+    },
+    Unsupported(String),
+}
+
+pub type MemResult<T> = Result<T, MemError>;
+
+impl MemError {
+    pub fn invalid_format<S: Into<String>>(msg: S) -> Self {
+        MemError::InvalidFormat(msg.into())
+    }
+
+    pub fn unsupported<S: Into<String>>(msg: S) -> Self {
+        MemError::Unsupported(msg.into())
+    }
+}
+
+impl Display for MemError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            MemError::Io(e) => write!(f, "I/O error: {}", e),
+            MemError::Json(e) => write!(f, "Invalid JSON: {}", e),
+            MemError::Index(e) => write!(f, "Invalid index: {}", e),
+            MemError::Parse(e) => write!(f, "Parse error: {}", e),
+            MemError::Minidump(e) => write!(f, "Invalid minidump: {}", e),
+            MemError::InvalidFormat(msg) => write!(f, "{}", msg),
+            MemError::OutOfRange { addr, size } => {
+                write!(
+                    f,
+                    "Read of {:x} bytes at {:08x} is out of range",
+                    size, addr
+                )
+            }
+            MemError::Unsupported(msg) => write!(f, "Unsupported: {}", msg),
+        }
+    }
+}
+
+impl StdErr for MemError {
+    fn source(&self) -> Option<&(dyn StdErr + 'static)> {
+        match self {
+            MemError::Io(e) => Some(e),
+            MemError::Json(e) => Some(e),
+            MemError::Index(e) => Some(e),
+            MemError::Parse(e) => Some(e),
+            MemError::Minidump(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<IoError> for MemError {
+    fn from(e: IoError) -> Self {
+        MemError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for MemError {
+    fn from(e: serde_json::Error) -> Self {
+        MemError::Json(e)
+    }
+}
+
+impl From<bincode::Error> for MemError {
+    fn from(e: bincode::Error) -> Self {
+        MemError::Index(e)
+    }
+}
+
+impl From<binread::Error> for MemError {
+    fn from(e: binread::Error) -> Self {
+        MemError::Parse(e)
+    }
+}
+
+impl From<minidump::Error> for MemError {
+    fn from(e: minidump::Error) -> Self {
+        MemError::Minidump(e)
+    }
+}
@@ -0,0 +1,161 @@
+use crate::buffer::DataBuffer;
+use crate::data_interface::{DataInterface, VMemInfo};
+use crate::diagnostics::SectionDiagnostic;
+use crate::error::MemError;
+use crate::memory::{MemRange, MemRanges};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use log::{debug, error};
+
+/// Bumped whenever the layout of `AddressSpaceIndex` or the naming of the memory ranges
+/// changes, older files are rebuilt.
+pub const INDEX_VERSION: u32 = 5;
+
+// bytes hashed per read while fingerprinting
+const HASH_BUFFER_SIZE: usize = 0x100000;
+
+/// Identifies a dump by its size and the BLAKE3 digest of all of its bytes, so any edit to
+/// the dump invalidates an index built for it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DumpFingerprint {
+    pub size: u64,
+    pub digest: String,
+}
+
+impl DumpFingerprint {
+    pub fn from_path(path: &Path) -> Result<DumpFingerprint, MemError> {
+        let file = File::open(path)?;
+        let mut reader = BufReader::with_capacity(HASH_BUFFER_SIZE, file);
+        let mut hasher = blake3::Hasher::new();
+        let size = std::io::copy(&mut reader, &mut hasher)?;
+        Ok(DumpFingerprint {
+            size,
+            digest: hasher.finalize().to_hex().to_string(),
+        })
+    }
+}
+
+/// The memory ranges and pointer ranges built for a dump, saved next to it so later runs
+/// can skip parsing the sections and rebuilding the lookups.  `source` describes how the
+/// ranges were produced (e.g. the sections file), an index is only reused for the same one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddressSpaceIndex {
+    pub version: u32,
+    pub fingerprint: DumpFingerprint,
+    pub source: String,
+    pub mem_ranges: Vec<MemRange>,
+    pub diagnostics: Vec<SectionDiagnostic>,
+    pub vmem_info: VMemInfo,
+}
+
+impl AddressSpaceIndex {
+    pub fn from_data_interface(
+        di: &DataInterface,
+        fingerprint: DumpFingerprint,
+        source: &str,
+    ) -> AddressSpaceIndex {
+        // the page lookup is not serialized, `into_data_interface` rebuilds it
+        let vmem_info = (*di.vmem_info).clone();
+        AddressSpaceIndex {
+            version: INDEX_VERSION,
+            fingerprint,
+            source: source.to_string(),
+            mem_ranges: di
+                .mem_ranges
+                .get_mem_ranges()
+                .into_iter()
+                .map(|mr| *mr)
+                .collect(),
+            diagnostics: di.mem_ranges.diagnostics.clone(),
+            vmem_info,
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), MemError> {
+        let writer = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(writer, self)?;
+        debug!(
+            "Saved the index of {} memory ranges to: {}",
+            self.mem_ranges.len(),
+            path.display()
+        );
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<AddressSpaceIndex, MemError> {
+        let reader = BufReader::new(File::open(path)?);
+        let index: AddressSpaceIndex = bincode::deserialize_from(reader)?;
+        Ok(index)
+    }
+
+    pub fn matches(&self, fingerprint: &DumpFingerprint, source: &str) -> bool {
+        self.version == INDEX_VERSION && &self.fingerprint == fingerprint && self.source == source
+    }
+
+    /// Rebuild the interface over `buffer` without going through the loaders again.
+    pub fn into_data_interface(self, mut buffer: DataBuffer) -> DataInterface {
+        let mut mem_ranges = MemRanges::new();
+        for mr in self.mem_ranges.into_iter() {
+            let size = mr.size.min(mr.vsize);
+            if let Err(e) = buffer.add_vaddr_mapping(mr.vaddr_start, mr.paddr_start, size) {
+                error!("Skipping {} from the index: {}", mr.name, e);
+                continue;
+            }
+            mem_ranges.add_mem_range(mr);
+        }
+        // the ranges were already checked when the index was built
+        mem_ranges.diagnostics = self.diagnostics;
+        let mut vmem_info = self.vmem_info;
+        vmem_info.rebuild_ptr_lookup();
+        DataInterface {
+            buffer: Box::new(buffer),
+            mem_ranges: Box::new(mem_ranges),
+            vmem_info: Box::new(vmem_info),
+        }
+    }
+}
+
+/// The default sidecar of a dump, `<dump>.idx`.
+pub fn default_index_path(dump: &Path) -> PathBuf {
+    let mut name = dump.as_os_str().to_os_string();
+    name.push(".idx");
+    PathBuf::from(name)
+}
+
+impl DataInterface {
+    /// Load the interface for `dump` from the index at `index_path` if it was saved for the
+    /// same dump contents and `source`.  Otherwise build it with `build` and save a new index,
+    /// failing to save only logs.
+    pub fn from_index_or_build<F>(
+        dump: &Path,
+        index_path: &Path,
+        source: &str,
+        build: F,
+    ) -> Result<DataInterface, MemError>
+    where
+        F: FnOnce() -> Result<DataInterface, MemError>,
+    {
+        let fingerprint = DumpFingerprint::from_path(dump)?;
+        match AddressSpaceIndex::load(index_path) {
+            Ok(index) if index.matches(&fingerprint, source) => {
+                debug!("Reusing the index: {}", index_path.display());
+                let buffer = DataBuffer::from_pathbuf(dump, true)?;
+                return Ok(index.into_data_interface(buffer));
+            }
+            Ok(_) => debug!(
+                "The index {} is stale, rebuilding it.",
+                index_path.display()
+            ),
+            Err(e) => debug!("No usable index at {}: {}", index_path.display(), e),
+        }
+        let di = build()?;
+        let index = AddressSpaceIndex::from_data_interface(&di, fingerprint, source);
+        if let Err(e) = index.save(index_path) {
+            error!("Unable to save the index {}: {}", index_path.display(), e);
+        }
+        Ok(di)
+    }
+}
@@ -1,6 +1,20 @@
 #![warn(missing_debug_implementations)]
 pub mod buffer;
+pub mod builder;
+pub mod compressed;
 pub mod data_interface;
+pub mod diagnostics;
+pub mod elfcore;
+pub mod error;
+pub mod index;
+pub mod lime;
+pub mod live;
+pub mod mdmp;
 pub mod memory;
+pub mod pagelookup;
+pub mod pagetable;
 pub mod pointers;
 pub mod radare;
+pub mod reader;
+pub mod region;
+pub mod volatility;
@@ -0,0 +1,116 @@
+use crate::error::MemError;
+use crate::pagetable::PhysicalRuns;
+use crate::region::{MemState, MemType, Protection};
+use binread::{BinRead, BinReaderExt};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::{Cursor, Seek, SeekFrom};
+use std::path::Path;
+
+use log::debug;
+
+/// "EMiL", the magic of every LiME range header.
+pub const LIME_MAGIC: &[u8; 4] = b"EMiL";
+
+pub const LIME_HEADER_SIZE: u64 = 32;
+
+/// The header in front of every range of physical memory in a LiME file.
+#[derive(BinRead, Debug, Clone, PartialEq, Eq)]
+#[br(little, magic = b"EMiL")]
+pub struct LimeHeader {
+    pub version: u32,
+    pub s_addr: u64,
+    // inclusive
+    pub e_addr: u64,
+    pub reserved: [u8; 8],
+}
+
+/// One range of physical memory.  The fields mirror `RadareMemoryInfo`, `vaddr` is the
+/// physical address and `paddr` the offset of the data in the LiME file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimeRangeInfo {
+    pub name: String,
+    pub size: u64,
+    pub vsize: u64,
+    pub perm: Protection,
+    pub paddr: u64,
+    pub vaddr: u64,
+    pub state: MemState,
+    pub mem_type: MemType,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LimeRangeInfos {
+    pub items: Vec<LimeRangeInfo>,
+}
+
+impl Display for LimeRangeInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "{} perms={} paddr={:08x} size={:08x} vaddr={:08x} vsize={:08x}",
+            self.name, self.perm, self.paddr, self.size, self.vaddr, self.vsize
+        )
+    }
+}
+
+impl LimeRangeInfos {
+    pub fn from_lime_file(path: &Path) -> Result<LimeRangeInfos, MemError> {
+        let data = std::fs::read(path)?;
+        LimeRangeInfos::from_lime_bytes(&data)
+    }
+
+    /// Walk the range headers of a LiME file.  A range whose data runs past the end of the
+    /// file (a truncated capture) is kept with the bytes that are present.
+    pub fn from_lime_bytes(data: &[u8]) -> Result<LimeRangeInfos, MemError> {
+        let file_size = data.len() as u64;
+        let mut cursor = Cursor::new(data);
+        let mut infos = LimeRangeInfos::default();
+        let mut pos: u64 = 0;
+        while pos + LIME_HEADER_SIZE <= file_size {
+            cursor.seek(SeekFrom::Start(pos))?;
+            let header: LimeHeader = match cursor.read_le() {
+                Ok(header) => header,
+                Err(e) if infos.items.is_empty() => {
+                    return Err(MemError::invalid_format(format!("Not a LiME file: {}", e)))
+                }
+                Err(e) => {
+                    debug!("Stopping at an invalid LiME header at {:08x}: {}", pos, e);
+                    break;
+                }
+            };
+            if header.e_addr < header.s_addr {
+                return Err(MemError::invalid_format(format!(
+                    "Invalid LiME range {:08x}-{:08x} at {:08x}",
+                    header.s_addr, header.e_addr, pos
+                )));
+            }
+            let vsize = header.e_addr - header.s_addr + 1;
+            let paddr = pos + LIME_HEADER_SIZE;
+            let size = vsize.min(file_size - paddr);
+            infos.items.push(LimeRangeInfo {
+                name: format!("Memory_Section_{}", infos.items.len()),
+                size,
+                vsize,
+                // physical memory has no protection, keep it visible to the searches
+                perm: Protection::READ | Protection::WRITE,
+                paddr,
+                vaddr: header.s_addr,
+                state: MemState::Unknown,
+                mem_type: MemType::Unknown,
+            });
+            pos = paddr.saturating_add(vsize);
+        }
+        debug!("Loaded {} ranges from the LiME file.", infos.items.len());
+        Ok(infos)
+    }
+
+    /// Where each range of physical memory is in the file, for translating virtual addresses
+    /// with `PageTableWalker`.
+    pub fn physical_runs(&self) -> PhysicalRuns {
+        let mut runs = PhysicalRuns::new();
+        for info in self.items.iter() {
+            runs.add_run(info.vaddr, info.paddr, info.size);
+        }
+        runs
+    }
+}
@@ -0,0 +1,318 @@
+use crate::data_interface::{Arch, ENDIAN};
+use crate::elfcore::elf_header_arch;
+use crate::error::MemError;
+use crate::region::{MemType, Protection};
+use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use memmap2::{MmapOptions, MmapRaw};
+
+#[cfg(unix)]
+use std::os::unix::fs::FileExt;
+
+use log::debug;
+
+/// One line of `/proc/<pid>/maps`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcMapsEntry {
+    pub start: u64,
+    pub end: u64,
+    pub perm: String,
+    pub offset: u64,
+    pub inode: u64,
+    pub pathname: String,
+}
+
+impl Display for ProcMapsEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "{:08x}-{:08x} {} {:08x} {} {}",
+            self.start, self.end, self.perm, self.offset, self.inode, self.pathname
+        )
+    }
+}
+
+impl ProcMapsEntry {
+    pub fn size(&self) -> u64 {
+        self.end - self.start
+    }
+
+    /// Convert the maps permissions (e.g. `rw-p`), the trailing `p`/`s` is not a permission.
+    pub fn protection(&self) -> Protection {
+        let rwx: String = self.perm.chars().take(3).collect();
+        Protection::from_perm_str(&rwx)
+    }
+
+    /// Mappings backed by a file have an inode, anonymous memory (heap, stacks) does not.
+    pub fn mem_type(&self) -> MemType {
+        if self.inode != 0 {
+            MemType::Mapped
+        } else {
+            MemType::Private
+        }
+    }
+}
+
+pub fn parse_proc_maps_line(line: &str) -> Option<ProcMapsEntry> {
+    let mut fields = line.split_whitespace();
+    let (start, end) = fields.next()?.split_once('-')?;
+    let perm = fields.next()?.to_string();
+    let offset = u64::from_str_radix(fields.next()?, 16).ok()?;
+    let _dev = fields.next()?;
+    let inode = fields.next()?.parse::<u64>().ok()?;
+    // the path may contain spaces
+    let pathname = fields.collect::<Vec<&str>>().join(" ");
+    Some(ProcMapsEntry {
+        start: u64::from_str_radix(start, 16).ok()?,
+        end: u64::from_str_radix(end, 16).ok()?,
+        perm,
+        offset,
+        inode,
+        pathname,
+    })
+}
+
+pub fn parse_proc_maps(text: &str) -> Vec<ProcMapsEntry> {
+    text.lines().filter_map(parse_proc_maps_line).collect()
+}
+
+// The executable's ELF header tells whether the process is 32 or 64-bit, fall back to the
+// host architecture when it can't be read.
+fn process_arch(proc_dir: &std::path::Path) -> (Arch, ENDIAN) {
+    let mut header = [0u8; 20];
+    let read = File::open(proc_dir.join("exe")).and_then(|mut f| f.read_exact(&mut header));
+    match read.ok().and_then(|_| elf_header_arch(&header)) {
+        Some(arch) => arch,
+        None if cfg!(target_endian = "big") => (Arch::host(), ENDIAN::BIG),
+        None => (Arch::host(), ENDIAN::LITTLE),
+    }
+}
+
+// bytes read from `/proc/<pid>/mem` at a time
+const BLOCK_SIZE: u64 = 0x10000;
+
+const BLOCK_EMPTY: u8 = 0;
+const BLOCK_LOADED: u8 = 1;
+const BLOCK_UNREADABLE: u8 = 2;
+
+struct LiveRegion {
+    start: u64,
+    size: u64,
+    // address space reserved on first access, only the blocks that were read take up memory;
+    // `None` if the reservation failed
+    cache: OnceLock<Option<MmapRaw>>,
+    blocks: Vec<AtomicU8>,
+    // serializes the reads, a loaded block is never written again
+    loading: Mutex<()>,
+}
+
+impl LiveRegion {
+    fn new(start: u64, size: u64) -> Self {
+        let count = size.div_ceil(BLOCK_SIZE);
+        LiveRegion {
+            start,
+            size,
+            cache: OnceLock::new(),
+            blocks: (0..count).map(|_| AtomicU8::new(BLOCK_EMPTY)).collect(),
+            loading: Mutex::new(()),
+        }
+    }
+
+    fn cache(&self) -> Option<&MmapRaw> {
+        self.cache
+            .get_or_init(|| {
+                let len = usize::try_from(self.size).ok()?;
+                match MmapOptions::new().len(len).no_reserve_swap().map_anon() {
+                    Ok(map) => Some(MmapRaw::from(map)),
+                    Err(e) => {
+                        debug!(
+                            "Unable to reserve {:08x} bytes for {:08x}: {}",
+                            self.size, self.start, e
+                        );
+                        None
+                    }
+                }
+            })
+            .as_ref()
+    }
+}
+
+/// Memory of a running process.  Regions are enumerated from `/proc/<pid>/maps` up front but
+/// their bytes are only read from `/proc/<pid>/mem` in blocks of `BLOCK_SIZE`, the first time
+/// a block is accessed.
+pub struct LiveProcessMemory {
+    pub pid: u32,
+    pub arch: Arch,
+    pub endian: ENDIAN,
+    pub maps: Vec<ProcMapsEntry>,
+    mem: File,
+    regions: Vec<LiveRegion>,
+}
+
+impl Debug for LiveProcessMemory {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("LiveProcessMemory")
+            .field("pid", &self.pid)
+            .field("arch", &self.arch)
+            .field("regions", &self.regions.len())
+            .finish()
+    }
+}
+
+impl PartialEq for LiveProcessMemory {
+    fn eq(&self, other: &Self) -> bool {
+        self.pid == other.pid
+    }
+}
+
+impl Eq for LiveProcessMemory {}
+
+impl LiveProcessMemory {
+    pub fn attach(pid: u32) -> Result<Self, MemError> {
+        let proc_dir = PathBuf::from(format!("/proc/{}", pid));
+        let maps_text = std::fs::read_to_string(proc_dir.join("maps"))?;
+        let maps = parse_proc_maps(&maps_text);
+        let mem = File::open(proc_dir.join("mem"))?;
+        let (arch, endian) = process_arch(&proc_dir);
+        debug!(
+            "Attached to {} pid {} with {} mapped regions.",
+            arch,
+            pid,
+            maps.len()
+        );
+        let regions = maps
+            .iter()
+            .map(|m| LiveRegion::new(m.start, m.size()))
+            .collect();
+        Ok(LiveProcessMemory {
+            pid,
+            arch,
+            endian,
+            maps,
+            mem,
+            regions,
+        })
+    }
+
+    /// Read the bytes at `vaddr` straight from the process, without caching them.  Returns
+    /// the number of bytes read, which is short when the read runs into unreadable memory.
+    #[cfg(unix)]
+    pub fn read_at(&self, vaddr: u64, buf: &mut [u8]) -> usize {
+        let mut pos: usize = 0;
+        while pos < buf.len() {
+            match self.mem.read_at(&mut buf[pos..], vaddr + pos as u64) {
+                Ok(0) => break,
+                Ok(n) => pos += n,
+                Err(_) => break,
+            }
+        }
+        pos
+    }
+
+    #[cfg(not(unix))]
+    pub fn read_at(&self, _vaddr: u64, _buf: &mut [u8]) -> usize {
+        0
+    }
+
+    // make sure the block is in the cache, false if it could not be read in full
+    fn load_block(&self, region: &LiveRegion, cache: &MmapRaw, block: usize) -> bool {
+        match region.blocks[block].load(Ordering::Acquire) {
+            BLOCK_LOADED => return true,
+            BLOCK_UNREADABLE => return false,
+            _ => {}
+        }
+        let _guard = match region.loading.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        // another thread may have read it while we waited
+        match region.blocks[block].load(Ordering::Acquire) {
+            BLOCK_LOADED => return true,
+            BLOCK_UNREADABLE => return false,
+            _ => {}
+        }
+        let offset = block as u64 * BLOCK_SIZE;
+        let len = BLOCK_SIZE.min(region.size - offset) as usize;
+        // SAFETY: the block lies inside the mapping and is still empty, no slice handed out
+        // by `get_slice` covers it and other loads are excluded by `loading`
+        let buf = unsafe {
+            std::slice::from_raw_parts_mut(cache.as_mut_ptr().add(offset as usize), len)
+        };
+        let read = self.read_at(region.start + offset, buf);
+        let state = if read == len {
+            BLOCK_LOADED
+        } else {
+            debug!(
+                "Unable to read {:08x} bytes at {:08x} from pid {}.",
+                len,
+                region.start + offset,
+                self.pid
+            );
+            BLOCK_UNREADABLE
+        };
+        region.blocks[block].store(state, Ordering::Release);
+        state == BLOCK_LOADED
+    }
+
+    /// Return the bytes at `vaddr`, reading the blocks of the containing region that are not
+    /// cached yet.  The read must stay inside a single mapping; when `size` is `None` the rest
+    /// of the mapping is returned, up to the first block that can not be read.
+    pub fn get_slice(&self, vaddr: u64, size: Option<u64>) -> Option<&[u8]> {
+        match size {
+            Some(sz) => self.read_slice(vaddr, sz, false),
+            None => self.read_slice(vaddr, u64::MAX, true),
+        }
+    }
+
+    /// Return up to `size` bytes at `vaddr`, the result is short when the mapping ends or a
+    /// block can not be read first (e.g. a guard page).  `None` if no byte can be read.
+    pub fn get_partial_slice(&self, vaddr: u64, size: u64) -> Option<&[u8]> {
+        self.read_slice(vaddr, size, true)
+    }
+
+    fn read_slice(&self, vaddr: u64, size: u64, partial: bool) -> Option<&[u8]> {
+        let idx = match self.regions.binary_search_by(|r| r.start.cmp(&vaddr)) {
+            Ok(idx) => idx,
+            Err(0) => return None,
+            Err(idx) => idx - 1,
+        };
+        let region = &self.regions[idx];
+        let offset = vaddr - region.start;
+        if offset >= region.size {
+            return None;
+        }
+        let end = match offset.checked_add(size) {
+            Some(end) if end <= region.size => end,
+            _ if partial => region.size,
+            _ => return None,
+        };
+        let cache = region.cache()?;
+        let mut readable = offset;
+        if end > offset {
+            let first = (offset / BLOCK_SIZE) as usize;
+            let last = ((end - 1) / BLOCK_SIZE) as usize;
+            for block in first..=last {
+                if !self.load_block(region, cache, block) {
+                    break;
+                }
+                readable = ((block as u64 + 1) * BLOCK_SIZE).min(end);
+            }
+        }
+        if readable < end && (!partial || readable == offset) {
+            return None;
+        }
+        // SAFETY: every block of `offset..readable` is loaded and is never written again
+        let data = unsafe {
+            std::slice::from_raw_parts(
+                cache.as_ptr().add(offset as usize),
+                (readable - offset) as usize,
+            )
+        };
+        Some(data)
+    }
+}
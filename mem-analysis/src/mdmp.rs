@@ -0,0 +1,234 @@
+use crate::data_interface::Arch;
+use crate::error::MemError;
+use crate::region::{MemState, MemType, Protection, STACK_NAME_PREFIX};
+use minidump::system_info::Cpu;
+use minidump::{
+    Minidump, MinidumpMemory64List, MinidumpMemoryInfoList, MinidumpMemoryList, MinidumpModuleList,
+    MinidumpSystemInfo, MinidumpThreadList, Module,
+};
+use rangemap::RangeMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::path::Path;
+
+use log::{debug, warn};
+
+/// A single committed memory region recovered from the Memory64List/MemoryList and
+/// MemoryInfoList streams of a minidump.  The fields mirror `RadareMemoryInfo` so the
+/// result can be used as a drop in replacement for `r2 -qc 'iSj'` output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinidumpRegionInfo {
+    pub name: String,
+    pub size: u64,
+    pub vsize: u64,
+    pub perm: Protection,
+    pub paddr: u64,
+    pub vaddr: u64,
+    pub state: MemState,
+    pub mem_type: MemType,
+    pub alloc_protection: Protection,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MinidumpRegionInfos {
+    pub items: Vec<MinidumpRegionInfo>,
+    pub arch: Arch,
+}
+
+impl Display for MinidumpRegionInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "{} perms={} paddr={:08x} size={:08x} vaddr={:08x} vsize={:08x}",
+            self.name, self.perm, self.paddr, self.size, self.vaddr, self.vsize
+        )
+    }
+}
+
+/// Map the SystemInfo CPU onto an `Arch`.  A 32-bit process running under WoW64 reports the
+/// 64-bit CPU of the host, so the presence of `wow64.dll` switches the result to x86.
+pub fn cpu_to_arch(cpu: Cpu, is_wow64: bool) -> Arch {
+    match cpu {
+        Cpu::X86 => Arch::X86,
+        Cpu::X86_64 if is_wow64 => Arch::X86,
+        Cpu::X86_64 => Arch::X64,
+        Cpu::Arm => Arch::Arm,
+        Cpu::Arm64 => Arch::Arm64,
+        _ => Arch::Unknown,
+    }
+}
+
+// The MemoryInfo attributes of a region: protection, state, type and allocation protection.
+type RegionAttrs = (Protection, MemState, MemType, Protection);
+
+// Used when the dump carries no MemoryInfoList.  Marking the memory as rw keeps the pointer
+// and lua_Page searches (which only scan writable memory) working on those dumps.
+const UNKNOWN_ATTRS: RegionAttrs = (
+    Protection::NONE.union(Protection::READ).union(Protection::WRITE),
+    MemState::Unknown,
+    MemType::Unknown,
+    Protection::NONE,
+);
+
+impl MinidumpRegionInfos {
+    pub fn from_minidump_path(path: &Path) -> Result<MinidumpRegionInfos, MemError> {
+        let data = std::fs::read(path)?;
+        MinidumpRegionInfos::from_minidump_bytes(&data)
+    }
+
+    /// Parse the memory streams of a minidump that is already held in memory.  The `paddr`
+    /// of each region is the offset of its bytes inside `data`.
+    pub fn from_minidump_bytes(data: &[u8]) -> Result<MinidumpRegionInfos, MemError> {
+        let dump = Minidump::read(data)?;
+        let file_start = data.as_ptr() as u64;
+
+        // vaddr range -> (attributes, info index)
+        let mut perms: RangeMap<u64, (RegionAttrs, usize)> = RangeMap::new();
+        if let Ok(info_list) = dump.get_stream::<MinidumpMemoryInfoList>() {
+            for (idx, info) in info_list.iter().enumerate() {
+                if let Some(range) = info.memory_range() {
+                    let attrs = (
+                        Protection::from_windows(info.protection.bits() as u64),
+                        MemState::from_windows(info.state.bits() as u64),
+                        MemType::from_windows(info.ty.bits() as u64),
+                        Protection::from_windows(info.allocation_protection.bits() as u64),
+                    );
+                    perms.insert(range.start..range.end.saturating_add(1), (attrs, idx));
+                }
+            }
+        }
+        let has_infos = !perms.is_empty();
+
+        let mut modules: RangeMap<u64, String> = RangeMap::new();
+        let mut is_wow64 = false;
+        if let Ok(module_list) = dump.get_stream::<MinidumpModuleList>() {
+            for module in module_list.iter() {
+                if module.size() == 0 {
+                    continue;
+                }
+                let code_file = module.code_file();
+                let name = code_file
+                    .rsplit(['\\', '/'])
+                    .next()
+                    .unwrap_or(&code_file)
+                    .to_string();
+                is_wow64 |= name.eq_ignore_ascii_case("wow64.dll");
+                match module.base_address().checked_add(module.size()) {
+                    Some(end) => modules.insert(module.base_address()..end, name),
+                    None => warn!(
+                        "Ignoring module {} at {:016x}, its size {:x} wraps the address space.",
+                        name,
+                        module.base_address(),
+                        module.size()
+                    ),
+                }
+            }
+        }
+
+        // the stack memory of each thread, named after the thread id
+        let mut stacks: RangeMap<u64, String> = RangeMap::new();
+        if let Ok(thread_list) = dump.get_stream::<MinidumpThreadList>() {
+            for thread in thread_list.threads.iter() {
+                let start = thread.raw.stack.start_of_memory_range;
+                let size = thread.raw.stack.memory.data_size as u64;
+                if size > 0 {
+                    let name = format!("{}{}", STACK_NAME_PREFIX, thread.raw.thread_id);
+                    stacks.insert(start..start.saturating_add(size), name);
+                }
+            }
+        }
+        // modules first, then thread stacks, then a generic name
+        let region_name = |start: u64, end: u64, default_name: &String| -> String {
+            if let Some(name) = modules.get(&start) {
+                return name.clone();
+            }
+            match stacks.overlapping(&(start..end.max(start.saturating_add(1)))).next() {
+                Some((_range, name)) => name.clone(),
+                None => default_name.clone(),
+            }
+        };
+
+        // (vaddr, paddr, size) for every captured region
+        let mut captured: Vec<(u64, u64, u64)> = Vec::new();
+        if let Ok(memory_list) = dump.get_stream::<MinidumpMemory64List>() {
+            for memory in memory_list.iter() {
+                let paddr = memory.bytes.as_ptr() as u64 - file_start;
+                captured.push((memory.base_address, paddr, memory.bytes.len() as u64));
+            }
+        }
+        if let Ok(memory_list) = dump.get_stream::<MinidumpMemoryList>() {
+            for memory in memory_list.iter() {
+                let paddr = memory.bytes.as_ptr() as u64 - file_start;
+                captured.push((memory.base_address, paddr, memory.bytes.len() as u64));
+            }
+        }
+        captured.sort();
+        debug!("Found {} memory regions in the minidump.", captured.len());
+
+        let mut infos = MinidumpRegionInfos::default();
+        if let Ok(system_info) = dump.get_stream::<MinidumpSystemInfo>() {
+            infos.arch = cpu_to_arch(system_info.cpu, is_wow64);
+        }
+        debug!("Minidump architecture: {} (wow64: {}).", infos.arch, is_wow64);
+
+        for (section, (vaddr, paddr, size)) in captured.iter().enumerate() {
+            let default_name = format!("Memory_Section_{}", section);
+            // a range that wraps the address space is kept whole, MemRanges reports and
+            // skips it
+            let o_vend = vaddr.checked_add(*size);
+            let vend = match o_vend {
+                Some(vend) if has_infos && *size > 0 => vend,
+                _ => {
+                    let (perm, state, mem_type, alloc_protection) = UNKNOWN_ATTRS;
+                    let name = match o_vend {
+                        Some(vend) => region_name(*vaddr, vend, &default_name),
+                        None => default_name,
+                    };
+                    infos.items.push(MinidumpRegionInfo {
+                        name,
+                        size: *size,
+                        vsize: *size,
+                        perm,
+                        paddr: *paddr,
+                        vaddr: *vaddr,
+                        state,
+                        mem_type,
+                        alloc_protection,
+                    });
+                    continue;
+                }
+            };
+
+            // a descriptor may span several MemoryInfo regions, so split it wherever the
+            // protection changes and treat any gaps as unknown
+            let mut cursor = *vaddr;
+            let mut pieces: Vec<(u64, u64, RegionAttrs)> = Vec::new();
+            for (range, (attrs, _)) in perms.overlapping(&(*vaddr..vend)) {
+                let start = range.start.max(*vaddr);
+                let end = range.end.min(vend);
+                if cursor < start {
+                    pieces.push((cursor, start, UNKNOWN_ATTRS));
+                }
+                pieces.push((start, end, *attrs));
+                cursor = end;
+            }
+            if cursor < vend {
+                pieces.push((cursor, vend, UNKNOWN_ATTRS));
+            }
+
+            for (start, end, (perm, state, mem_type, alloc_protection)) in pieces {
+                infos.items.push(MinidumpRegionInfo {
+                    name: region_name(start, end, &default_name),
+                    size: end - start,
+                    vsize: end - start,
+                    perm,
+                    paddr: paddr + (start - vaddr),
+                    vaddr: start,
+                    state,
+                    mem_type,
+                    alloc_protection,
+                });
+            }
+        }
+        Ok(infos)
+    }
+}
@@ -1,29 +1,42 @@
 use crate::buffer::DataBuffer;
+use crate::diagnostics::{DiagnosticKind, SectionDiagnostic};
+use crate::elfcore::{ElfCoreRegionInfo, ElfCoreRegionInfos};
+use crate::lime::{LimeRangeInfo, LimeRangeInfos};
+use crate::live::ProcMapsEntry;
+use crate::mdmp::{MinidumpRegionInfo, MinidumpRegionInfos};
+use crate::pagetable::{PageTableRegionInfo, PageTableRegionInfos};
 use crate::radare::{RadareMemoryInfo, RadareMemoryInfos};
+use crate::region::{MemState, MemType, Protection, RegionFilter, STACK_NAME_PREFIX};
+use crate::volatility::{VolatilityRegionInfo, VolatilityRegionInfos};
 use rangemap::RangeMap;
 use serde;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter, Result as FmtResult};
 
 use log::debug;
 
-#[derive(Debug, PartialEq, Eq, Deserialize, Clone)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
 pub enum BackendType {
     File,
     SliceBuffer,
     FullBuffer,
+    LiveProcess,
 }
 
-#[derive(Debug, Clone, Eq)]
+#[derive(Debug, Clone, Eq, Serialize, Deserialize)]
 pub struct MemRange {
     pub vaddr_start: u64,
     pub paddr_start: u64,
     pub vsize: u64,
     pub size: u64,
+    #[serde(skip)]
     pub data: Option<DataBuffer>,
-    pub perm: String,
+    pub perm: Protection,
     pub name: String,
     pub backend: Option<BackendType>,
+    pub state: MemState,
+    pub mem_type: MemType,
+    pub alloc_protection: Protection,
     // source: SourceMeta<'a>,
 }
 
@@ -56,6 +69,17 @@ impl Display for MemRange {
 }
 
 impl MemRange {
+    /// The protection as the radare style string (e.g. `-rw-`) that `perm` held before it
+    /// became a `Protection`.
+    pub fn perm_str(&self) -> String {
+        self.perm.to_string()
+    }
+
+    /// Thread stacks named from the minidump thread list or `/proc/<pid>/maps` (`[stack]`).
+    pub fn is_stack(&self) -> bool {
+        return self.name.starts_with(STACK_NAME_PREFIX) || self.name.starts_with("[stack");
+    }
+
     pub fn from_radare_info(radare_info: &RadareMemoryInfo) -> Self {
         MemRange {
             vaddr_start: radare_info.vaddr,
@@ -63,14 +87,119 @@ impl MemRange {
             vsize: radare_info.vsize,
             size: radare_info.size,
             data: None,
-            perm: radare_info.perm.clone(),
+            perm: Protection::from_perm_str(&radare_info.perm),
             name: radare_info.name.clone(),
             backend: None,
+            state: radare_info.state,
+            mem_type: radare_info.mem_type,
+            alloc_protection: radare_info.alloc_protection,
+        }
+    }
+
+    pub fn from_minidump_info(minidump_info: &MinidumpRegionInfo) -> Self {
+        MemRange {
+            vaddr_start: minidump_info.vaddr,
+            paddr_start: minidump_info.paddr,
+            vsize: minidump_info.vsize,
+            size: minidump_info.size,
+            data: None,
+            perm: minidump_info.perm,
+            name: minidump_info.name.clone(),
+            backend: None,
+            state: minidump_info.state,
+            mem_type: minidump_info.mem_type,
+            alloc_protection: minidump_info.alloc_protection,
+        }
+    }
+
+    pub fn from_elf_core_info(elf_info: &ElfCoreRegionInfo) -> Self {
+        MemRange {
+            vaddr_start: elf_info.vaddr,
+            paddr_start: elf_info.paddr,
+            vsize: elf_info.vsize,
+            size: elf_info.size,
+            data: None,
+            perm: elf_info.perm,
+            name: elf_info.name.clone(),
+            backend: None,
+            state: elf_info.state,
+            mem_type: elf_info.mem_type,
+            alloc_protection: elf_info.perm,
+        }
+    }
+
+    pub fn from_volatility_info(vol_info: &VolatilityRegionInfo) -> Self {
+        MemRange {
+            vaddr_start: vol_info.vaddr,
+            paddr_start: vol_info.paddr,
+            vsize: vol_info.vsize,
+            size: vol_info.size,
+            data: None,
+            perm: vol_info.perm,
+            name: vol_info.name.clone(),
+            backend: None,
+            state: vol_info.state,
+            mem_type: vol_info.mem_type,
+            alloc_protection: vol_info.perm,
+        }
+    }
+
+    pub fn from_lime_info(lime_info: &LimeRangeInfo) -> Self {
+        MemRange {
+            vaddr_start: lime_info.vaddr,
+            paddr_start: lime_info.paddr,
+            vsize: lime_info.vsize,
+            size: lime_info.size,
+            data: None,
+            perm: lime_info.perm,
+            name: lime_info.name.clone(),
+            backend: None,
+            state: lime_info.state,
+            mem_type: lime_info.mem_type,
+            alloc_protection: lime_info.perm,
+        }
+    }
+
+    pub fn from_page_table_info(pt_info: &PageTableRegionInfo) -> Self {
+        MemRange {
+            vaddr_start: pt_info.vaddr,
+            paddr_start: pt_info.paddr,
+            vsize: pt_info.vsize,
+            size: pt_info.size,
+            data: None,
+            perm: pt_info.perm,
+            name: pt_info.name.clone(),
+            backend: None,
+            // the tables only list resident pages and say nothing about how they were allocated
+            state: MemState::Commit,
+            mem_type: MemType::Unknown,
+            alloc_protection: pt_info.perm,
+        }
+    }
+
+    pub fn from_proc_maps_entry(entry: &ProcMapsEntry) -> Self {
+        let name = if entry.pathname.is_empty() {
+            format!("anon_{:08x}", entry.start)
+        } else {
+            entry.pathname.clone()
+        };
+        MemRange {
+            vaddr_start: entry.start,
+            paddr_start: entry.start,
+            vsize: entry.size(),
+            size: entry.size(),
+            data: None,
+            perm: entry.protection(),
+            name,
+            backend: Some(BackendType::LiveProcess),
+            state: MemState::Commit,
+            mem_type: entry.mem_type(),
+            alloc_protection: entry.protection(),
         }
     }
 
     pub fn new(
-        perm: String,
+        perm: Protection,
         name: String,
         vaddr_start: u64,
         paddr_start: u64,
@@ -88,23 +217,27 @@ impl MemRange {
             perm,
             name,
             backend,
+            state: MemState::Unknown,
+            mem_type: MemType::Unknown,
+            alloc_protection: perm,
         }
     }
 }
 
 impl Memory for MemRange {
     fn vaddr_in_range(&self, addr: u64) -> bool {
-        if self.vaddr_start <= addr && addr <= self.vaddr_start + self.vsize {
+        // compare offsets, the end of a range read from the dump may not fit in a u64
+        if self.vaddr_start <= addr && addr - self.vaddr_start <= self.vsize {
             return true;
         }
         false
     }
 
     fn paddr_in_range(&self, paddr: u64) -> bool {
-        if self.paddr_start <= paddr && paddr <= self.paddr_start + self.size {
-            return false;
+        if self.paddr_start <= paddr && paddr - self.paddr_start <= self.size {
+            return true;
         }
-        self.vaddr_in_range(self.get_vaddr_from_paddr(paddr).unwrap())
+        false
     }
 
     fn get_size(&self) -> u64 {
@@ -133,16 +266,14 @@ impl Memory for MemRange {
         if !self.paddr_in_range(paddr) {
             return None;
         }
-        let addr = (paddr - self.paddr_start) + self.vaddr_start;
-        Some(addr)
+        (paddr - self.paddr_start).checked_add(self.vaddr_start)
     }
 
     fn get_paddr_from_vaddr(&self, vaddr: u64) -> Option<u64> {
         if !self.vaddr_in_range(vaddr) {
             return None;
         }
-        let addr = (vaddr - self.vaddr_start) + self.paddr_start;
-        Some(addr)
+        (vaddr - self.vaddr_start).checked_add(self.paddr_start)
     }
 
     // fn set_data(&self) -> () {}
@@ -152,6 +283,8 @@ impl Memory for MemRange {
 pub struct MemRanges {
     pub vmem_ranges: RangeMap<u64, Box<MemRange>>,
     pub pmem_ranges: RangeMap<u64, Box<MemRange>>,
+    /// Problems found while adding ranges, see `validate` for the complete list.
+    pub diagnostics: Vec<SectionDiagnostic>,
     // pub vmem_ranges: RangeMap<u64, &'a MemRange<'a>>,
     // pub pmem_ranges: RangeMap<u64, &'a MemRange<'a>>,
 }
@@ -169,6 +302,7 @@ impl MemRanges {
         MemRanges {
             vmem_ranges: RangeMap::new(),
             pmem_ranges: RangeMap::new(),
+            diagnostics: Vec::new(),
         }
     }
 
@@ -178,11 +312,32 @@ impl MemRanges {
         // debug!("adding {} to the memranges.", mr);
         let vsz: u64 = if mr.vsize == 0 { 1 } else { mr.vsize };
         let sz: u64 = if mr.size == 0 { 1 } else { mr.size };
+        if mr.size == 0 || mr.vsize == 0 {
+            self.diagnostics
+                .push(SectionDiagnostic::new(DiagnosticKind::ZeroSize, &mr));
+        }
+        let vend = mr.vaddr_start.checked_add(vsz);
+        let pend = mr.paddr_start.checked_add(sz);
+        let (vend, pend) = match (vend, pend) {
+            (Some(vend), Some(pend)) => (vend, pend),
+            _ => {
+                self.diagnostics
+                    .push(SectionDiagnostic::new(DiagnosticKind::AddressOverflow, &mr));
+                return;
+            }
+        };
+        // the RangeMap silently replaces whatever the new range overlaps
+        let vrange = mr.vaddr_start..vend;
+        for (_range, other) in self.vmem_ranges.overlapping(&vrange) {
+            let kind = DiagnosticKind::VaddrOverlap {
+                other: other.name.clone(),
+                other_vaddr: other.vaddr_start,
+            };
+            self.diagnostics.push(SectionDiagnostic::new(kind, &mr));
+        }
         let my_mr = Box::new(mr.clone());
-        self.vmem_ranges
-            .insert(mr.vaddr_start..mr.vaddr_start + vsz, my_mr.clone());
-        self.pmem_ranges
-            .insert(mr.paddr_start..mr.paddr_start + sz, my_mr.clone());
+        self.vmem_ranges.insert(vrange, my_mr.clone());
+        self.pmem_ranges.insert(mr.paddr_start..pend, my_mr.clone());
     }
 
     pub fn get_paddr_range(&self, paddr: u64) -> Option<Box<MemRange>> {
@@ -225,6 +380,80 @@ impl MemRanges {
         mrs
     }
 
+    pub fn from_minidump_infos(minidump: &MinidumpRegionInfos) -> MemRanges {
+        let mut mrs = MemRanges::new();
+        debug!("Loading {} minidump memory regions.", minidump.items.len());
+        for info in minidump.items.iter() {
+            let mr = MemRange::from_minidump_info(info);
+            mrs.add_mem_range(mr);
+        }
+        mrs
+    }
+
+    pub fn from_elf_core_infos(elf_core: &ElfCoreRegionInfos) -> MemRanges {
+        let mut mrs = MemRanges::new();
+        debug!("Loading {} ELF core segments.", elf_core.items.len());
+        for info in elf_core.items.iter() {
+            let mr = MemRange::from_elf_core_info(info);
+            mrs.add_mem_range(mr);
+        }
+        mrs
+    }
+
+    pub fn from_volatility_infos(volatility: &VolatilityRegionInfos) -> MemRanges {
+        let mut mrs = MemRanges::new();
+        debug!("Loading {} Volatility memory runs.", volatility.items.len());
+        for info in volatility.items.iter() {
+            let mr = MemRange::from_volatility_info(info);
+            mrs.add_mem_range(mr);
+        }
+        mrs
+    }
+
+    pub fn from_lime_infos(lime: &LimeRangeInfos) -> MemRanges {
+        let mut mrs = MemRanges::new();
+        debug!("Loading {} LiME ranges.", lime.items.len());
+        for info in lime.items.iter() {
+            let mr = MemRange::from_lime_info(info);
+            mrs.add_mem_range(mr);
+        }
+        mrs
+    }
+
+    pub fn from_page_table_infos(page_tables: &PageTableRegionInfos) -> MemRanges {
+        let mut mrs = MemRanges::new();
+        debug!(
+            "Loading {} page table memory runs.",
+            page_tables.items.len()
+        );
+        for info in page_tables.items.iter() {
+            let mr = MemRange::from_page_table_info(info);
+            mrs.add_mem_range(mr);
+        }
+        mrs
+    }
+
+    pub fn from_proc_maps(maps: &[ProcMapsEntry]) -> MemRanges {
+        let mut mrs = MemRanges::new();
+        debug!("Loading {} live process mappings.", maps.len());
+        // guard/reserved mappings can't be read and can be huge, so leave them out
+        for entry in maps.iter().filter(|m| m.protection().is_readable()) {
+            mrs.add_mem_range(MemRange::from_proc_maps_entry(entry));
+        }
+        mrs
+    }
+
+    /// The memory ranges a search with `filter` should visit.
+    pub fn get_filtered_mem_ranges(&self, filter: &RegionFilter) -> Vec<Box<MemRange>> {
+        let mut res = Vec::new();
+        for (_range, mr) in self.vmem_ranges.iter() {
+            if filter.matches(mr) {
+                res.push(mr.clone())
+            }
+        }
+        return res;
+    }
+
     pub fn get_mem_ranges(&self) -> Vec<Box<MemRange>> {
         let mut res = Vec::new();
         for (_range, mr) in self.vmem_ranges.iter() {
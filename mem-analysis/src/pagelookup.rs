@@ -0,0 +1,148 @@
+use crate::pointers::PointerRange;
+
+// a second level bitmap covers 2^18 pages (1 GiB of 4 KiB pages) in 32 KiB
+const CHUNK_BITS: u32 = 18;
+const CHUNK_WORDS: usize = (1 << CHUNK_BITS) / 64;
+// first level entries for a 48 bit address space with 4 KiB pages, pages above it are only
+// found through the interval table
+const MAX_CHUNKS: u64 = 1 << (48 - 12 - CHUNK_BITS);
+
+/// Maps every page of the added `PointerRange`s back to its range.  The ranges are kept as a
+/// sorted table of page intervals for lookups, and a two-level page bitmap answers the
+/// membership test of the pointer searches without touching the table.  A page shared by
+/// two ranges belongs to the one added last.
+#[derive(Debug, Clone)]
+pub struct PageLookup {
+    page_shift: u32,
+    // sorted, non-overlapping page aligned [start, end) -> index into `ranges`
+    intervals: Vec<(u64, u64, usize)>,
+    ranges: Vec<PointerRange>,
+    chunks: Vec<Option<Box<[u64]>>>,
+    // a range lies above what the bitmap covers
+    has_high_pages: bool,
+}
+
+impl Default for PageLookup {
+    fn default() -> Self {
+        PageLookup::new(4096)
+    }
+}
+
+impl PageLookup {
+    pub fn new(page_size: u64) -> Self {
+        PageLookup {
+            page_shift: page_size.max(1).trailing_zeros(),
+            intervals: Vec::new(),
+            ranges: Vec::new(),
+            chunks: Vec::new(),
+            has_high_pages: false,
+        }
+    }
+
+    /// Number of pages that map to a range.
+    pub fn len(&self) -> u64 {
+        self.intervals
+            .iter()
+            .map(|(start, end, _)| ((end - start - 1) >> self.page_shift) + 1)
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        let page_size = 1 << self.page_shift;
+        *self = PageLookup::new(page_size);
+    }
+
+    fn page_start(&self, vaddr: u64) -> u64 {
+        (vaddr >> self.page_shift) << self.page_shift
+    }
+
+    // the end of the page holding `vaddr - 1`, the last page of the address space ends at
+    // `u64::MAX` which leaves out only its last byte
+    fn page_end(&self, vaddr: u64) -> u64 {
+        let page_size = 1u64 << self.page_shift;
+        match vaddr.checked_add(page_size - 1) {
+            Some(end) => self.page_start(end),
+            None => u64::MAX,
+        }
+    }
+
+    /// Map the pages of `ptr_range`, taking over the pages it shares with earlier ranges.
+    pub fn insert(&mut self, ptr_range: &PointerRange) {
+        let start = self.page_start(ptr_range.vstart);
+        let end = self.page_end(ptr_range.vend);
+        if end <= start {
+            return;
+        }
+        let idx = self.ranges.len();
+        self.ranges.push(ptr_range.clone());
+
+        let lo = self.intervals.partition_point(|(_, e, _)| *e <= start);
+        let hi = self.intervals.partition_point(|(s, _, _)| *s < end);
+        let mut replacement: Vec<(u64, u64, usize)> = Vec::with_capacity(3);
+        if lo < hi {
+            let (first_start, _, first_idx) = self.intervals[lo];
+            if first_start < start {
+                replacement.push((first_start, start, first_idx));
+            }
+        }
+        replacement.push((start, end, idx));
+        if lo < hi {
+            let (_, last_end, last_idx) = self.intervals[hi - 1];
+            if last_end > end {
+                replacement.push((end, last_end, last_idx));
+            }
+        }
+        self.intervals.splice(lo..hi, replacement);
+        self.mark_pages(start, end);
+    }
+
+    fn mark_pages(&mut self, start: u64, end: u64) {
+        let first = start >> self.page_shift;
+        let last = (end - 1) >> self.page_shift;
+        for page in first..=last {
+            let chunk = page >> CHUNK_BITS;
+            if chunk >= MAX_CHUNKS {
+                self.has_high_pages = true;
+                break;
+            }
+            let chunk = chunk as usize;
+            if self.chunks.len() <= chunk {
+                self.chunks.resize(chunk + 1, None);
+            }
+            let bits = self.chunks[chunk].get_or_insert_with(|| vec![0u64; CHUNK_WORDS].into());
+            let bit = (page & ((1 << CHUNK_BITS) - 1)) as usize;
+            bits[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    /// Whether the page of `vaddr` belongs to a range, the hot path of the pointer searches.
+    #[inline]
+    pub fn contains(&self, vaddr: u64) -> bool {
+        let page = vaddr >> self.page_shift;
+        let chunk = page >> CHUNK_BITS;
+        if chunk >= MAX_CHUNKS {
+            return self.has_high_pages && self.get(vaddr).is_some();
+        }
+        match self.chunks.get(chunk as usize) {
+            Some(Some(bits)) => {
+                let bit = (page & ((1 << CHUNK_BITS) - 1)) as usize;
+                bits[bit / 64] & (1 << (bit % 64)) != 0
+            }
+            _ => false,
+        }
+    }
+
+    /// The range the page of `vaddr` belongs to.
+    pub fn get(&self, vaddr: u64) -> Option<&PointerRange> {
+        let pos = self.intervals.partition_point(|(_, end, _)| *end <= vaddr);
+        let (start, _, idx) = self.intervals.get(pos)?;
+        if *start > vaddr {
+            return None;
+        }
+        Some(&self.ranges[*idx])
+    }
+}
@@ -0,0 +1,268 @@
+use crate::buffer::DataBuffer;
+use crate::region::Protection;
+use byteorder::{ByteOrder, LittleEndian};
+use rangemap::RangeMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use log::debug;
+
+const ENTRY_PRESENT: u64 = 1 << 0;
+const ENTRY_WRITABLE: u64 = 1 << 1;
+const ENTRY_USER: u64 = 1 << 2;
+const ENTRY_LARGE: u64 = 1 << 7;
+const ENTRY_NO_EXECUTE: u64 = 1 << 63;
+
+// bits 12..51 of an entry (and of CR3) hold the physical address of the next level
+const ADDRESS_MASK: u64 = 0x000f_ffff_ffff_f000;
+const ENTRIES_PER_TABLE: u64 = 512;
+
+/// Where the bytes of physical memory are in the image file.  A raw image is a single run
+/// starting at physical address 0, formats like LiME store several runs back to back.
+#[derive(Debug, Clone, Default)]
+pub struct PhysicalRuns {
+    // physical range -> (file offset - physical address), wrapping
+    runs: RangeMap<u64, u64>,
+}
+
+impl PhysicalRuns {
+    pub fn new() -> Self {
+        PhysicalRuns {
+            runs: RangeMap::new(),
+        }
+    }
+
+    /// Physical memory `0..size` stored as is, i.e. a raw image of `size` bytes.
+    pub fn identity(size: u64) -> Self {
+        let mut runs = PhysicalRuns::new();
+        runs.add_run(0, 0, size);
+        runs
+    }
+
+    pub fn add_run(&mut self, phys: u64, file_offset: u64, size: u64) {
+        if size == 0 {
+            return;
+        }
+        self.runs.insert(
+            phys..phys.saturating_add(size),
+            file_offset.wrapping_sub(phys),
+        );
+    }
+
+    pub fn count(&self) -> usize {
+        self.runs.iter().count()
+    }
+
+    pub fn file_offset(&self, phys: u64) -> Option<u64> {
+        self.runs.get(&phys).map(|delta| phys.wrapping_add(*delta))
+    }
+
+    /// The backed parts of `phys..phys + size` as `(phys, file offset, size)` tuples.
+    pub fn file_ranges(&self, phys: u64, size: u64) -> Vec<(u64, u64, u64)> {
+        let end = phys.saturating_add(size);
+        self.runs
+            .overlapping(&(phys..end))
+            .map(|(range, delta)| {
+                let start = range.start.max(phys);
+                let stop = range.end.min(end);
+                (start, start.wrapping_add(*delta), stop - start)
+            })
+            .collect()
+    }
+}
+
+/// A contiguous run of translated pages.  The fields mirror `RadareMemoryInfo`, the `paddr` is
+/// the offset of the run in the image file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageTableRegionInfo {
+    pub name: String,
+    pub size: u64,
+    pub vsize: u64,
+    pub perm: Protection,
+    pub paddr: u64,
+    pub vaddr: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PageTableRegionInfos {
+    pub items: Vec<PageTableRegionInfo>,
+    pub dtb: u64,
+}
+
+impl Display for PageTableRegionInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "{} perms={} paddr={:08x} size={:08x} vaddr={:08x} vsize={:08x}",
+            self.name, self.perm, self.paddr, self.size, self.vaddr, self.vsize
+        )
+    }
+}
+
+/// Walks the 4-level x86-64 page tables of one address space, `dtb` being the CR3 value
+/// (directory table base) of the process.
+#[derive(Debug)]
+pub struct PageTableWalker<'a> {
+    buffer: &'a DataBuffer,
+    runs: &'a PhysicalRuns,
+    /// Only translate user mode pages of the lower half, the kernel half is shared by every
+    /// process and is not where the Luau heaps are.
+    pub user_only: bool,
+}
+
+// protection accumulated down the levels of the walk
+#[derive(Debug, Clone, Copy)]
+struct EntryFlags {
+    writable: bool,
+    user: bool,
+    no_execute: bool,
+}
+
+impl EntryFlags {
+    fn add(self, entry: u64) -> Self {
+        EntryFlags {
+            writable: self.writable && entry & ENTRY_WRITABLE != 0,
+            user: self.user && entry & ENTRY_USER != 0,
+            no_execute: self.no_execute || entry & ENTRY_NO_EXECUTE != 0,
+        }
+    }
+
+    fn protection(&self) -> Protection {
+        let mut perm = Protection::READ;
+        if self.writable {
+            perm |= Protection::WRITE;
+        }
+        if !self.no_execute {
+            perm |= Protection::EXECUTE;
+        }
+        perm
+    }
+}
+
+// sign extend bit 47 so the upper half addresses are canonical
+fn canonical(vaddr: u64) -> u64 {
+    if vaddr & (1 << 47) != 0 {
+        vaddr | 0xffff_0000_0000_0000
+    } else {
+        vaddr
+    }
+}
+
+impl<'a> PageTableWalker<'a> {
+    pub fn new(buffer: &'a DataBuffer, runs: &'a PhysicalRuns) -> Self {
+        PageTableWalker {
+            buffer,
+            runs,
+            user_only: true,
+        }
+    }
+
+    fn read_entry(&self, phys: u64) -> Option<u64> {
+        let offset = self.runs.file_offset(phys)?;
+        let slice = self.buffer.get_shared_slice_from(offset, Some(8))?;
+        Some(LittleEndian::read_u64(slice))
+    }
+
+    /// Translate every present page reachable from `dtb`.  Pages that are adjacent both
+    /// virtually and in the image, with the same protection, are merged into one region.
+    pub fn walk(&self, dtb: u64) -> PageTableRegionInfos {
+        // (vaddr, file offset, size, perm)
+        let mut pages: Vec<(u64, u64, u64, Protection)> = Vec::new();
+        let flags = EntryFlags {
+            writable: true,
+            user: true,
+            no_execute: false,
+        };
+        self.walk_table(dtb & ADDRESS_MASK, 4, 0, flags, &mut pages);
+
+        let mut runs: Vec<(u64, u64, u64, Protection)> = Vec::new();
+        for page in pages.into_iter() {
+            if let Some(last) = runs.last_mut() {
+                if last.0 + last.2 == page.0 && last.1 + last.2 == page.1 && last.3 == page.3 {
+                    last.2 += page.2;
+                    continue;
+                }
+            }
+            runs.push(page);
+        }
+
+        let mut infos = PageTableRegionInfos {
+            items: Vec::new(),
+            dtb,
+        };
+        for (section, (vaddr, paddr, size, perm)) in runs.into_iter().enumerate() {
+            infos.items.push(PageTableRegionInfo {
+                name: format!("Memory_Section_{}", section),
+                size,
+                vsize: size,
+                perm,
+                paddr,
+                vaddr,
+            });
+        }
+        debug!(
+            "Translated {} memory runs from dtb: {:08x}.",
+            infos.items.len(),
+            dtb
+        );
+        infos
+    }
+
+    // level 4 is the PML4, level 1 the page table
+    fn walk_table(
+        &self,
+        table: u64,
+        level: u32,
+        vbase: u64,
+        flags: EntryFlags,
+        pages: &mut Vec<(u64, u64, u64, Protection)>,
+    ) {
+        let shift = 12 + 9 * (level - 1);
+        let page_size = 1u64 << shift;
+        let count = if level == 4 && self.user_only {
+            ENTRIES_PER_TABLE / 2
+        } else {
+            ENTRIES_PER_TABLE
+        };
+        for idx in 0..count {
+            let entry = match self.read_entry(table + idx * 8) {
+                Some(entry) => entry,
+                None => return,
+            };
+            if entry & ENTRY_PRESENT == 0 {
+                continue;
+            }
+            let entry_flags = flags.add(entry);
+            if self.user_only && !entry_flags.user {
+                continue;
+            }
+            let vaddr = canonical(vbase | (idx << shift));
+            // PS is reserved in the PML4 and means a 1GB / 2MB page in the PDPT / PD
+            let is_leaf = level == 1 || (entry & ENTRY_LARGE != 0 && level < 4);
+            if !is_leaf {
+                self.walk_table(entry & ADDRESS_MASK, level - 1, vaddr, entry_flags, pages);
+                continue;
+            }
+            let phys = entry & ADDRESS_MASK & !(page_size - 1);
+            let perm = entry_flags.protection();
+            // a large page may only be partly present in the image
+            for (run_phys, offset, size) in self.runs.file_ranges(phys, page_size) {
+                pages.push((vaddr + (run_phys - phys), offset, size, perm));
+            }
+        }
+    }
+}
+
+impl PageTableRegionInfos {
+    pub fn from_physical_image(
+        buffer: &DataBuffer,
+        runs: &PhysicalRuns,
+        dtb: u64,
+    ) -> PageTableRegionInfos {
+        debug!(
+            "Walking the page tables at dtb: {:08x} over {} physical runs.",
+            dtb,
+            runs.count()
+        );
+        PageTableWalker::new(buffer, runs).walk(dtb)
+    }
+}
@@ -1,42 +1,15 @@
-use multimap::MultiMap;
-use serde::Serialize;
-use std::collections::HashMap;
-use std::error::Error as StdErr;
+use crate::error::MemError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter, Result as FmtResult};
 
-#[derive(Debug, PartialEq, Clone, Eq, Serialize)]
-pub struct PointerIndex {
-    pub sources: HashMap<u64, u64>,
-    pub sinks: MultiMap<u64, u64>,
-}
-
-impl PointerIndex {
-    pub fn new() -> Self {
-        PointerIndex {
-            sources: HashMap::new(),
-            sinks: MultiMap::new(),
-        }
-    }
-    pub fn add_sink(&mut self, source: u64, sink: u64) -> Result<bool, Box<dyn StdErr>> {
-        self.sinks.insert(sink, source);
-        return Ok(true);
-    }
-
-    pub fn add_source(&mut self, source: u64, sink: u64) -> Result<bool, Box<dyn StdErr>> {
-        self.sources.insert(source, sink);
-        return Ok(true);
-    }
-}
-
-#[derive(Debug, PartialEq, Clone, Eq, Serialize)]
+#[derive(Debug, PartialEq, Clone, Eq, Serialize, Deserialize)]
 pub struct PointerRange {
     pub pstart: u64,
     pub pend: u64,
 
     pub vstart: u64,
     pub vend: u64,
-
-    pub pointer_index: PointerIndex,
 }
 
 impl Display for PointerRange {
@@ -50,16 +23,19 @@ impl Display for PointerRange {
 }
 
 impl PointerRange {
-    pub fn new(pstart: u64, pend: u64, vstart: u64, vend: u64) -> Self {
-        assert_eq!(vstart <= vend, true);
-        assert_eq!(pstart <= pend, true);
-        PointerRange {
+    pub fn new(pstart: u64, pend: u64, vstart: u64, vend: u64) -> Result<Self, MemError> {
+        if vstart > vend || pstart > pend {
+            return Err(MemError::invalid_format(format!(
+                "Invalid pointer range paddr={:08x}-{:08x} vaddr={:08x}-{:08x}",
+                pstart, pend, vstart, vend
+            )));
+        }
+        Ok(PointerRange {
             pstart,
             pend,
             vstart,
             vend,
-            pointer_index: PointerIndex::new(),
-        }
+        })
     }
 
     pub fn in_vrange(&self, val: u64) -> bool {
@@ -81,17 +57,93 @@ impl PointerRange {
     pub fn psize(&self) -> u64 {
         return self.pend - self.pstart;
     }
+}
+
+/// Pointers found in the address space indexed in both directions, answers "who points at
+/// this address" (`xrefs_to`) as well as "where does this pointer go" (`xrefs_from`).
+#[derive(Debug, Default, PartialEq, Clone, Eq, Serialize, Deserialize)]
+pub struct XrefIndex {
+    // sink -> the sources pointing at it, in ascending order
+    to: BTreeMap<u64, Vec<u64>>,
+    // source -> sink
+    from: BTreeMap<u64, u64>,
+}
+
+impl XrefIndex {
+    pub fn new() -> Self {
+        XrefIndex::default()
+    }
+
+    /// Record that the word at `source` points at `sink`.  A source only holds one value, so
+    /// adding it again replaces the previous sink.
+    pub fn add(&mut self, source: u64, sink: u64) {
+        if let Some(old_sink) = self.from.insert(source, sink) {
+            if old_sink == sink {
+                return;
+            }
+            self.remove_to(old_sink, source);
+        }
+        let sources = self.to.entry(sink).or_default();
+        if let Err(pos) = sources.binary_search(&source) {
+            sources.insert(pos, source);
+        }
+    }
+
+    fn remove_to(&mut self, sink: u64, source: u64) {
+        if let Some(sources) = self.to.get_mut(&sink) {
+            sources.retain(|s| *s != source);
+            if sources.is_empty() {
+                self.to.remove(&sink);
+            }
+        }
+    }
+
+    /// Add every pointer of `other`, e.g. the results of another search thread.
+    pub fn merge(&mut self, other: &XrefIndex) {
+        for (source, sink) in other.from.iter() {
+            self.add(*source, *sink);
+        }
+    }
+
+    /// The addresses holding a pointer to exactly `vaddr`.
+    pub fn xrefs_to(&self, vaddr: u64) -> &[u64] {
+        match self.to.get(&vaddr) {
+            Some(sources) => sources,
+            None => &[],
+        }
+    }
 
-    pub fn add_vpointer(&mut self, source: u64, sink: u64) -> bool {
-        let mut added = false;
-        if self.in_vrange(sink) {
-            let _dummy = self.pointer_index.add_sink(source, sink);
-            added = true;
+    /// The `(source, sink)` pairs for pointers into `start..end`, ordered by sink, which also
+    /// finds pointers into the middle of an object (e.g. a field of a table).
+    pub fn xrefs_to_range(&self, start: u64, end: u64) -> Vec<(u64, u64)> {
+        if start >= end {
+            return Vec::new();
         }
-        if self.in_vrange(source) {
-            let _dummy = self.pointer_index.add_source(source, sink);
-            added = true;
+        let mut xrefs = Vec::new();
+        for (sink, sources) in self.to.range(start..end) {
+            for source in sources.iter() {
+                xrefs.push((*source, *sink));
+            }
         }
-        return added;
+        xrefs
+    }
+
+    /// Where the pointer stored at `vaddr` points.
+    pub fn xrefs_from(&self, vaddr: u64) -> Option<u64> {
+        self.from.get(&vaddr).copied()
+    }
+
+    /// The `(source, sink)` pairs of every pointer, ordered by source.
+    pub fn pointers(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.from.iter().map(|(source, sink)| (*source, *sink))
+    }
+
+    /// The number of pointers in the index.
+    pub fn len(&self) -> usize {
+        self.from.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.from.is_empty()
     }
 }
@@ -1,3 +1,5 @@
+use crate::error::MemError;
+use crate::region::{MemState, MemType, Protection};
 use serde;
 use serde::Deserialize;
 use std::fmt::{Display, Formatter, Result as FmtResult};
@@ -11,6 +13,13 @@ pub struct RadareMemoryInfo {
     pub perm: String,
     pub paddr: u64,
     pub vaddr: u64,
+    // parsed from the `state=`, `type=` and `allocation=` attributes of the section name
+    #[serde(skip)]
+    pub state: MemState,
+    #[serde(skip)]
+    pub mem_type: MemType,
+    #[serde(skip)]
+    pub alloc_protection: Protection,
 }
 
 #[derive(Debug, Deserialize)]
@@ -29,32 +38,29 @@ impl Display for RadareMemoryInfo {
     }
 }
 
+fn parse_name_value(vstr: &str) -> Option<u64> {
+    let imm = vstr.split('=').nth(1)?;
+    u64::from_str_radix(imm.trim_start_matches("0x"), 16).ok()
+}
+
 pub fn parse_radare_name(info: &String) -> RadareMemoryInfo {
     let name_split_vector: Vec<&str> = info.split(' ').collect::<Vec<&str>>();
     let mut paddr: u64 = 0;
-    let mut _stype: u64 = 0;
-    let mut _alloc: u64 = 0;
-    let mut _state: u64 = 0;
+    let mut stype: u64 = 0;
+    let mut alloc: u64 = 0;
+    let mut state: u64 = 0;
     for vstr in name_split_vector.iter() {
         if vstr.starts_with("paddr") {
-            let imm = *vstr.split('=').collect::<Vec<&str>>().get(1).unwrap();
-            let v = u64::from_str_radix(imm.trim_start_matches("0x"), 16);
-            paddr = v.unwrap();
+            paddr = parse_name_value(vstr).unwrap_or(paddr);
         } else if vstr.starts_with("state") {
-            let imm = *vstr.split('=').collect::<Vec<&str>>().get(1).unwrap();
-            let v = u64::from_str_radix(imm.trim_start_matches("0x"), 16);
-            _state = v.unwrap();
+            state = parse_name_value(vstr).unwrap_or(state);
         } else if vstr.starts_with("allocation") {
-            let imm = *vstr.split('=').collect::<Vec<&str>>().get(1).unwrap();
-            let v = u64::from_str_radix(imm.trim_start_matches("0x"), 16);
-            _alloc = v.unwrap();
+            alloc = parse_name_value(vstr).unwrap_or(alloc);
         } else if vstr.starts_with("type") {
-            let imm = *vstr.split('=').collect::<Vec<&str>>().get(1).unwrap();
-            let v = u64::from_str_radix(imm.trim_start_matches("0x"), 16);
-            _stype = v.unwrap();
+            stype = parse_name_value(vstr).unwrap_or(stype);
         }
     }
-    let name = *name_split_vector.last().unwrap();
+    let name = name_split_vector.last().copied().unwrap_or_default();
     RadareMemoryInfo {
         name: name.to_string(),
         // size: info.size,
@@ -62,9 +68,9 @@ pub fn parse_radare_name(info: &String) -> RadareMemoryInfo {
         vsize: 0,
         // vaddr: info.address,
         paddr,
-        // stype: stype,
-        // state: state,
-        // alloc_protection: alloc,
+        state: MemState::from_windows(state),
+        mem_type: MemType::from_windows(stype),
+        alloc_protection: Protection::from_windows(alloc),
         // flags: info.flags.clone(),
         perm: "".to_string(),
         vaddr: 0,
@@ -72,8 +78,15 @@ pub fn parse_radare_name(info: &String) -> RadareMemoryInfo {
 }
 
 impl RadareMemoryInfos {
-    pub fn from_radare_json(path: &PathBuf) -> RadareMemoryInfos {
-        let text = std::fs::read_to_string(path).unwrap();
-        serde_json::from_str::<RadareMemoryInfos>(&text).unwrap()
+    pub fn from_radare_json(path: &PathBuf) -> Result<RadareMemoryInfos, MemError> {
+        let text = std::fs::read_to_string(path)?;
+        let mut infos = serde_json::from_str::<RadareMemoryInfos>(&text)?;
+        for info in infos.items.iter_mut() {
+            let attrs = parse_radare_name(&info.name);
+            info.state = attrs.state;
+            info.mem_type = attrs.mem_type;
+            info.alloc_protection = attrs.alloc_protection;
+        }
+        Ok(infos)
     }
 }
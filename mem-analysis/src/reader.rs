@@ -0,0 +1,288 @@
+use crate::data_interface::{DataInterface, ENDIAN};
+use binread::{BinRead, BinResult, Endian, ReadOptions};
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use std::io::{Error as IoError, ErrorKind, Read, Result as IoResult, Seek, SeekFrom};
+use std::ops::Range;
+
+/// The result of a virtual memory read.  Unmapped bytes are zero filled in `data` and their
+/// virtual address ranges are listed in `unmapped`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VirtualRead {
+    pub vaddr: u64,
+    pub data: Vec<u8>,
+    pub unmapped: Vec<Range<u64>>,
+}
+
+impl VirtualRead {
+    pub fn is_complete(&self) -> bool {
+        self.unmapped.is_empty()
+    }
+
+    pub fn mapped_len(&self) -> u64 {
+        let missing: u64 = self.unmapped.iter().map(|r| r.end - r.start).sum();
+        self.data.len() as u64 - missing
+    }
+}
+
+/// Reads from a virtual address space that may be split across many regions.  Implementors
+/// only have to expose the backed bytes of a single region, the provided methods stitch reads
+/// across contiguous regions and keep track of the holes.
+pub trait VirtualMemoryReader {
+    /// Up to `len` backed bytes at `vaddr`, fewer when the region that contains it ends first.
+    /// Bytes of a region past its file size (e.g. a partially dumped segment) are not backed.
+    fn region_slice(&self, vaddr: u64, len: u64) -> Option<&[u8]>;
+
+    /// The lowest region start above `vaddr`, used to skip over unmapped holes.
+    fn next_region_vaddr(&self, vaddr: u64) -> Option<u64>;
+
+    fn reader_endian(&self) -> ENDIAN;
+
+    fn reader_word_sz(&self) -> u8;
+
+    /// Fill `buf` with the memory at `vaddr` and return the unmapped parts, which are zeroed.
+    fn read_into(&self, vaddr: u64, buf: &mut [u8]) -> Vec<Range<u64>> {
+        let mut unmapped: Vec<Range<u64>> = Vec::new();
+        let mut mark_unmapped = |start: u64, end: u64| match unmapped.last_mut() {
+            Some(last) if last.end == start => last.end = end,
+            _ => unmapped.push(start..end),
+        };
+        let end = vaddr.saturating_add(buf.len() as u64);
+        let mut cursor = vaddr;
+        while cursor < end {
+            let pos = (cursor - vaddr) as usize;
+            let want = (end - cursor) as usize;
+            match self.region_slice(cursor, want as u64) {
+                Some(slice) if !slice.is_empty() => {
+                    let sz = want.min(slice.len());
+                    buf[pos..pos + sz].copy_from_slice(&slice[..sz]);
+                    cursor += sz as u64;
+                }
+                _ => {
+                    let hole_end = match self.next_region_vaddr(cursor) {
+                        Some(next) => next.min(end),
+                        None => end,
+                    };
+                    buf[pos..(hole_end - vaddr) as usize].fill(0);
+                    mark_unmapped(cursor, hole_end);
+                    cursor = hole_end;
+                }
+            }
+        }
+        if end - vaddr < buf.len() as u64 {
+            // the read wrapped the address space
+            buf[(end - vaddr) as usize..].fill(0);
+        }
+        unmapped
+    }
+
+    fn read_bytes(&self, vaddr: u64, len: u64) -> VirtualRead {
+        let mut data = vec![0u8; len as usize];
+        let unmapped = self.read_into(vaddr, &mut data);
+        VirtualRead {
+            vaddr,
+            data,
+            unmapped,
+        }
+    }
+
+    /// Read `len` bytes, failing if any of them are unmapped.
+    fn read_exact_vaddr(&self, vaddr: u64, len: u64) -> Option<Vec<u8>> {
+        let read = self.read_bytes(vaddr, len);
+        if !read.is_complete() {
+            return None;
+        }
+        Some(read.data)
+    }
+
+    fn read_u8_vaddr(&self, vaddr: u64) -> Option<u8> {
+        self.read_exact_vaddr(vaddr, 1).map(|b| b[0])
+    }
+
+    fn read_u16_vaddr(&self, vaddr: u64) -> Option<u16> {
+        let buf = self.read_exact_vaddr(vaddr, 2)?;
+        Some(match self.reader_endian() {
+            ENDIAN::BIG => BigEndian::read_u16(&buf),
+            ENDIAN::LITTLE => LittleEndian::read_u16(&buf),
+        })
+    }
+
+    fn read_u32_vaddr(&self, vaddr: u64) -> Option<u32> {
+        let buf = self.read_exact_vaddr(vaddr, 4)?;
+        Some(match self.reader_endian() {
+            ENDIAN::BIG => BigEndian::read_u32(&buf),
+            ENDIAN::LITTLE => LittleEndian::read_u32(&buf),
+        })
+    }
+
+    fn read_u64_vaddr(&self, vaddr: u64) -> Option<u64> {
+        let buf = self.read_exact_vaddr(vaddr, 8)?;
+        Some(match self.reader_endian() {
+            ENDIAN::BIG => BigEndian::read_u64(&buf),
+            ENDIAN::LITTLE => LittleEndian::read_u64(&buf),
+        })
+    }
+
+    fn read_i32_vaddr(&self, vaddr: u64) -> Option<i32> {
+        self.read_u32_vaddr(vaddr).map(|v| v as i32)
+    }
+
+    fn read_i64_vaddr(&self, vaddr: u64) -> Option<i64> {
+        self.read_u64_vaddr(vaddr).map(|v| v as i64)
+    }
+
+    /// Read a pointer sized value using the word size of the address space.
+    fn read_word_vaddr(&self, vaddr: u64) -> Option<u64> {
+        match self.reader_word_sz() {
+            1 => self.read_u8_vaddr(vaddr).map(|v| v as u64),
+            2 => self.read_u16_vaddr(vaddr).map(|v| v as u64),
+            4 => self.read_u32_vaddr(vaddr).map(|v| v as u64),
+            8 => self.read_u64_vaddr(vaddr),
+            _ => None,
+        }
+    }
+}
+
+impl VirtualMemoryReader for DataInterface {
+    fn region_slice(&self, vaddr: u64, len: u64) -> Option<&[u8]> {
+        let mr = self.mem_ranges.get_vaddr_range(vaddr)?;
+        let backed = mr.size.min(mr.vsize);
+        let offset = vaddr.checked_sub(mr.vaddr_start)?;
+        if offset >= backed {
+            return None;
+        }
+        // only what was asked for, a live process reads the bytes it hands out
+        self.buffer.get_shared_slice_from(
+            mr.paddr_start.checked_add(offset)?,
+            Some((backed - offset).min(len)),
+        )
+    }
+
+    fn next_region_vaddr(&self, vaddr: u64) -> Option<u64> {
+        let start = vaddr.checked_add(1)?;
+        self.mem_ranges
+            .vmem_ranges
+            .overlapping(&(start..u64::MAX))
+            .map(|(range, _)| range.start)
+            .find(|s| *s > vaddr)
+    }
+
+    fn reader_endian(&self) -> ENDIAN {
+        self.vmem_info.endian.clone()
+    }
+
+    fn reader_word_sz(&self) -> u8 {
+        self.vmem_info.word_sz
+    }
+}
+
+/// `Read` + `Seek` over a virtual address space, the position is the virtual address.  Reads
+/// stop at the first unmapped byte so a `BinRead` parse over a hole fails instead of seeing
+/// zeroes.
+#[derive(Debug)]
+pub struct VirtualCursor<'a, R: VirtualMemoryReader + ?Sized> {
+    reader: &'a R,
+    pos: u64,
+}
+
+impl<'a, R: VirtualMemoryReader + ?Sized> VirtualCursor<'a, R> {
+    pub fn new(reader: &'a R, vaddr: u64) -> Self {
+        VirtualCursor { reader, pos: vaddr }
+    }
+
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+}
+
+impl<R: VirtualMemoryReader + ?Sized> Read for VirtualCursor<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        let unmapped = self.reader.read_into(self.pos, buf);
+        let len = match unmapped.first() {
+            Some(hole) if hole.start == self.pos => {
+                return Err(IoError::new(
+                    ErrorKind::UnexpectedEof,
+                    format!("vaddr {:08x} is not mapped", self.pos),
+                ));
+            }
+            Some(hole) => (hole.start - self.pos) as usize,
+            None => buf.len(),
+        };
+        self.pos += len as u64;
+        Ok(len)
+    }
+}
+
+impl<R: VirtualMemoryReader + ?Sized> Seek for VirtualCursor<'_, R> {
+    fn seek(&mut self, pos: SeekFrom) -> IoResult<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(vaddr) => Some(vaddr),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+            SeekFrom::End(delta) => u64::MAX.checked_add_signed(delta),
+        };
+        match new_pos {
+            Some(p) => {
+                self.pos = p;
+                Ok(p)
+            }
+            None => Err(IoError::new(
+                ErrorKind::InvalidInput,
+                "seek outside of the address space",
+            )),
+        }
+    }
+}
+
+impl From<&ENDIAN> for Endian {
+    fn from(endian: &ENDIAN) -> Self {
+        match endian {
+            ENDIAN::BIG => Endian::Big,
+            ENDIAN::LITTLE => Endian::Little,
+        }
+    }
+}
+
+/// Arguments a `BinRead` type can be built from the address space word size, this is what
+/// lets `DataInterface::read_struct` handle both plain structs (`()`) and structs that
+/// import the word size (`#[br(import(word_sz: u8))]`, i.e. `(u8,)`).
+pub trait WordArgs {
+    fn from_word_sz(word_sz: u8) -> Self;
+}
+
+impl WordArgs for () {
+    fn from_word_sz(_word_sz: u8) {}
+}
+
+impl WordArgs for (u8,) {
+    fn from_word_sz(word_sz: u8) -> Self {
+        (word_sz,)
+    }
+}
+
+/// A pointer sized value, read as 1, 2, 4 or 8 bytes depending on the word size argument
+/// (`#[br(args(word_sz))]`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct VWord(pub u64);
+
+impl BinRead for VWord {
+    type Args = (u8,);
+
+    fn read_options<R: Read + Seek>(
+        reader: &mut R,
+        options: &ReadOptions,
+        args: Self::Args,
+    ) -> BinResult<Self> {
+        let value = match args.0 {
+            1 => u8::read_options(reader, options, ())? as u64,
+            2 => u16::read_options(reader, options, ())? as u64,
+            4 => u32::read_options(reader, options, ())? as u64,
+            8 => u64::read_options(reader, options, ())?,
+            word_sz => {
+                return Err(binread::Error::AssertFail {
+                    pos: reader.stream_position()?,
+                    message: format!("Unsupported word size: {}", word_sz),
+                })
+            }
+        };
+        Ok(VWord(value))
+    }
+}
@@ -0,0 +1,245 @@
+use crate::memory::MemRange;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::ops::{BitOr, BitOrAssign};
+use std::str::FromStr;
+
+// MEMORY_BASIC_INFORMATION values
+const MEM_COMMIT: u64 = 0x1000;
+const MEM_RESERVE: u64 = 0x2000;
+const MEM_FREE: u64 = 0x10000;
+const MEM_PRIVATE: u64 = 0x20000;
+const MEM_MAPPED: u64 = 0x40000;
+const MEM_IMAGE: u64 = 0x1000000;
+
+/// Name given to the memory of a thread stack, followed by the thread id.
+pub const STACK_NAME_PREFIX: &str = "Thread_Stack_";
+
+const PAGE_READONLY: u64 = 0x02;
+const PAGE_READWRITE: u64 = 0x04;
+const PAGE_WRITECOPY: u64 = 0x08;
+const PAGE_EXECUTE: u64 = 0x10;
+const PAGE_EXECUTE_READ: u64 = 0x20;
+const PAGE_EXECUTE_READWRITE: u64 = 0x40;
+const PAGE_EXECUTE_WRITECOPY: u64 = 0x80;
+const PAGE_GUARD: u64 = 0x100;
+
+/// Page protection as a set of flags.  `Display` renders the radare style string (e.g. `-rw-`)
+/// that `MemRange.perm` used to hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct Protection {
+    bits: u8,
+}
+
+impl Protection {
+    pub const NONE: Protection = Protection { bits: 0 };
+    pub const READ: Protection = Protection { bits: 1 };
+    pub const WRITE: Protection = Protection { bits: 2 };
+    pub const EXECUTE: Protection = Protection { bits: 4 };
+    pub const COPY_ON_WRITE: Protection = Protection { bits: 8 };
+    pub const GUARD: Protection = Protection { bits: 16 };
+
+    pub const fn union(self, other: Protection) -> Protection {
+        Protection {
+            bits: self.bits | other.bits,
+        }
+    }
+
+    pub fn contains(&self, other: Protection) -> bool {
+        self.bits & other.bits == other.bits
+    }
+
+    pub fn is_readable(&self) -> bool {
+        self.contains(Protection::READ)
+    }
+
+    pub fn is_writable(&self) -> bool {
+        self.contains(Protection::WRITE)
+    }
+
+    pub fn is_executable(&self) -> bool {
+        self.contains(Protection::EXECUTE)
+    }
+
+    /// Parse a radare (`-rw-`) or `/proc/<pid>/maps` (`rw-p`) permission string.  Only the
+    /// `r`, `w` and `x` characters are significant.
+    pub fn from_perm_str(perm: &str) -> Self {
+        let mut prot = Protection::NONE;
+        if perm.contains('r') {
+            prot |= Protection::READ;
+        }
+        if perm.contains('w') {
+            prot |= Protection::WRITE;
+        }
+        if perm.contains('x') {
+            prot |= Protection::EXECUTE;
+        }
+        prot
+    }
+
+    /// Convert a Windows `PAGE_*` protection value.
+    pub fn from_windows(protect: u64) -> Self {
+        let mut prot = match protect & 0xff {
+            PAGE_READONLY => Protection::READ,
+            PAGE_READWRITE => Protection::READ | Protection::WRITE,
+            PAGE_WRITECOPY => Protection::READ | Protection::WRITE | Protection::COPY_ON_WRITE,
+            PAGE_EXECUTE => Protection::EXECUTE,
+            PAGE_EXECUTE_READ => Protection::READ | Protection::EXECUTE,
+            PAGE_EXECUTE_READWRITE => Protection::READ | Protection::WRITE | Protection::EXECUTE,
+            PAGE_EXECUTE_WRITECOPY => {
+                Protection::READ
+                    | Protection::WRITE
+                    | Protection::EXECUTE
+                    | Protection::COPY_ON_WRITE
+            }
+            _ => Protection::NONE,
+        };
+        if protect & PAGE_GUARD != 0 {
+            prot |= Protection::GUARD;
+        }
+        prot
+    }
+}
+
+impl BitOr for Protection {
+    type Output = Protection;
+
+    fn bitor(self, rhs: Protection) -> Protection {
+        self.union(rhs)
+    }
+}
+
+impl BitOrAssign for Protection {
+    fn bitor_assign(&mut self, rhs: Protection) {
+        self.bits |= rhs.bits;
+    }
+}
+
+impl Display for Protection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "-{}{}{}",
+            if self.is_readable() { "r" } else { "-" },
+            if self.is_writable() { "w" } else { "-" },
+            if self.is_executable() { "x" } else { "-" }
+        )
+    }
+}
+
+/// `MEM_COMMIT`, `MEM_RESERVE` or `MEM_FREE`, `Unknown` when the dump format has no such notion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum MemState {
+    Commit,
+    Reserve,
+    Free,
+    #[default]
+    Unknown,
+}
+
+impl MemState {
+    pub fn from_windows(state: u64) -> Self {
+        match state {
+            MEM_COMMIT => MemState::Commit,
+            MEM_RESERVE => MemState::Reserve,
+            MEM_FREE => MemState::Free,
+            _ => MemState::Unknown,
+        }
+    }
+}
+
+/// `MEM_IMAGE`, `MEM_MAPPED` or `MEM_PRIVATE`, `Unknown` when the dump format has no such notion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum MemType {
+    Image,
+    Mapped,
+    Private,
+    #[default]
+    Unknown,
+}
+
+impl MemType {
+    pub fn from_windows(mem_type: u64) -> Self {
+        match mem_type {
+            MEM_IMAGE => MemType::Image,
+            MEM_MAPPED => MemType::Mapped,
+            MEM_PRIVATE => MemType::Private,
+            _ => MemType::Unknown,
+        }
+    }
+}
+
+/// Selects the memory ranges a search visits.  A range whose state or type is `Unknown` is
+/// not excluded by the state/type lists, so the filters still work for dumps without that
+/// information (ELF cores, radare sections without the attributes).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegionFilter {
+    pub required: Protection,
+    pub states: Option<Vec<MemState>>,
+    pub types: Option<Vec<MemType>>,
+}
+
+impl Default for RegionFilter {
+    fn default() -> Self {
+        RegionFilter::writable()
+    }
+}
+
+impl RegionFilter {
+    pub fn all() -> Self {
+        RegionFilter {
+            required: Protection::NONE,
+            states: None,
+            types: None,
+        }
+    }
+
+    /// Every writable range, which is what the pointer and lua_Page searches always scanned.
+    pub fn writable() -> Self {
+        RegionFilter {
+            required: Protection::WRITE,
+            states: None,
+            types: None,
+        }
+    }
+
+    /// Committed, private, read/write memory, where the Luau heaps live.
+    pub fn private_rw() -> Self {
+        RegionFilter {
+            required: Protection::READ | Protection::WRITE,
+            states: Some(vec![MemState::Commit]),
+            types: Some(vec![MemType::Private]),
+        }
+    }
+
+    pub fn matches(&self, mr: &MemRange) -> bool {
+        if !mr.perm.contains(self.required) {
+            return false;
+        }
+        let state_ok = match &self.states {
+            Some(states) => mr.state == MemState::Unknown || states.contains(&mr.state),
+            None => true,
+        };
+        let type_ok = match &self.types {
+            Some(types) => mr.mem_type == MemType::Unknown || types.contains(&mr.mem_type),
+            None => true,
+        };
+        state_ok && type_ok
+    }
+}
+
+impl FromStr for RegionFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "all" => Ok(RegionFilter::all()),
+            "writable" | "rw" => Ok(RegionFilter::writable()),
+            "private" | "private-rw" => Ok(RegionFilter::private_rw()),
+            _ => Err(format!(
+                "Unknown region filter: {} (expected all, writable or private)",
+                s
+            )),
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use crate::error::MemError;
+use crate::region::{MemState, MemType, Protection};
+use rangemap::RangeMap;
+use serde_json::Value;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::path::Path;
+
+use log::debug;
+
+/// One row of `vol -r json windows.vadinfo`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolatilityVadInfo {
+    pub start: u64,
+    // inclusive, like the `End VPN` column
+    pub end: u64,
+    pub tag: String,
+    pub protection: Protection,
+    pub private_memory: bool,
+    pub file: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VolatilityVadInfos {
+    pub items: Vec<VolatilityVadInfo>,
+}
+
+/// One row of `vol -r json windows.memmap`, a virtual page and where its bytes are in the
+/// memory image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolatilityMemmapEntry {
+    pub vaddr: u64,
+    pub physical: u64,
+    pub size: u64,
+    pub file_offset: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VolatilityMemmap {
+    pub items: Vec<VolatilityMemmapEntry>,
+}
+
+/// A contiguous run of resident pages.  The fields mirror `RadareMemoryInfo`, the `paddr` is
+/// the offset of the run in the memory image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolatilityRegionInfo {
+    pub name: String,
+    pub size: u64,
+    pub vsize: u64,
+    pub perm: Protection,
+    pub paddr: u64,
+    pub vaddr: u64,
+    pub state: MemState,
+    pub mem_type: MemType,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VolatilityRegionInfos {
+    pub items: Vec<VolatilityRegionInfo>,
+}
+
+impl Display for VolatilityRegionInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "{} perms={} paddr={:08x} size={:08x} vaddr={:08x} vsize={:08x}",
+            self.name, self.perm, self.paddr, self.size, self.vaddr, self.vsize
+        )
+    }
+}
+
+/// Convert a VAD protection name (e.g. `PAGE_EXECUTE_READWRITE`) into a `Protection`.
+pub fn vad_protection_to_perm(protection: &str) -> Protection {
+    let value = match protection {
+        "PAGE_NOACCESS" => 0x01,
+        "PAGE_READONLY" => 0x02,
+        "PAGE_READWRITE" => 0x04,
+        "PAGE_WRITECOPY" => 0x08,
+        "PAGE_EXECUTE" => 0x10,
+        "PAGE_EXECUTE_READ" => 0x20,
+        "PAGE_EXECUTE_READWRITE" => 0x40,
+        "PAGE_EXECUTE_WRITECOPY" => 0x80,
+        _ => 0,
+    };
+    Protection::from_windows(value)
+}
+
+// Volatility renders the tree of results as nested `__children` lists
+fn flatten_rows(rows: &[Value], out: &mut Vec<Value>) {
+    for row in rows {
+        out.push(row.clone());
+        if let Some(children) = row.get("__children").and_then(|c| c.as_array()) {
+            flatten_rows(children, out);
+        }
+    }
+}
+
+fn parse_rows(text: &str) -> Result<Vec<Value>, MemError> {
+    let value: Value = serde_json::from_str(text)?;
+    let rows = match value.as_array() {
+        Some(rows) => rows,
+        None => {
+            return Err(MemError::invalid_format(
+                "Volatility output is not a JSON list",
+            ))
+        }
+    };
+    let mut out = Vec::new();
+    flatten_rows(rows, &mut out);
+    Ok(out)
+}
+
+// numbers are plain JSON numbers unless the output was produced with a hex renderer
+fn row_u64(row: &Value, key: &str) -> Option<u64> {
+    match row.get(key)? {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => match s.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16).ok(),
+            None => s.parse::<u64>().ok(),
+        },
+        _ => None,
+    }
+}
+
+fn row_str(row: &Value, key: &str) -> Option<String> {
+    row.get(key)?.as_str().map(|s| s.to_string())
+}
+
+impl VolatilityVadInfos {
+    pub fn from_vadinfo_json(path: &Path) -> Result<VolatilityVadInfos, MemError> {
+        let text = std::fs::read_to_string(path)?;
+        VolatilityVadInfos::from_vadinfo_str(&text)
+    }
+
+    pub fn from_vadinfo_str(text: &str) -> Result<VolatilityVadInfos, MemError> {
+        let mut infos = VolatilityVadInfos::default();
+        for row in parse_rows(text)?.iter() {
+            let (start, end) = match (row_u64(row, "Start VPN"), row_u64(row, "End VPN")) {
+                (Some(s), Some(e)) if s <= e => (s, e),
+                _ => continue,
+            };
+            let file = row_str(row, "File").filter(|f| !f.is_empty() && f != "N/A");
+            infos.items.push(VolatilityVadInfo {
+                start,
+                end,
+                tag: row_str(row, "Tag").unwrap_or_default(),
+                protection: vad_protection_to_perm(&row_str(row, "Protection").unwrap_or_default()),
+                private_memory: row_u64(row, "PrivateMemory").unwrap_or(0) != 0,
+                file,
+            });
+        }
+        debug!(
+            "Loaded {} VADs from the Volatility output.",
+            infos.items.len()
+        );
+        Ok(infos)
+    }
+}
+
+impl VolatilityMemmap {
+    pub fn from_memmap_json(path: &Path) -> Result<VolatilityMemmap, MemError> {
+        let text = std::fs::read_to_string(path)?;
+        VolatilityMemmap::from_memmap_str(&text)
+    }
+
+    pub fn from_memmap_str(text: &str) -> Result<VolatilityMemmap, MemError> {
+        let mut memmap = VolatilityMemmap::default();
+        for row in parse_rows(text)?.iter() {
+            let (vaddr, physical, size) = match (
+                row_u64(row, "Virtual"),
+                row_u64(row, "Physical"),
+                row_u64(row, "Size"),
+            ) {
+                (Some(v), Some(p), Some(s)) if s > 0 => (v, p, s),
+                _ => continue,
+            };
+            memmap.items.push(VolatilityMemmapEntry {
+                vaddr,
+                physical,
+                size,
+                // raw images have no separate file offset, the physical address is the offset
+                file_offset: row_u64(row, "Offset in File").unwrap_or(physical),
+            });
+        }
+        debug!(
+            "Loaded {} page mappings from the Volatility output.",
+            memmap.items.len()
+        );
+        Ok(memmap)
+    }
+}
+
+fn vad_mem_type(vad: &VolatilityVadInfo) -> MemType {
+    if vad.private_memory {
+        return MemType::Private;
+    }
+    match &vad.file {
+        Some(file) => {
+            let lower = file.to_lowercase();
+            if lower.ends_with(".dll") || lower.ends_with(".exe") || lower.ends_with(".sys") {
+                MemType::Image
+            } else {
+                MemType::Mapped
+            }
+        }
+        None => MemType::Mapped,
+    }
+}
+
+impl VolatilityRegionInfos {
+    /// Merge the resident pages of `memmap` into runs that are contiguous both virtually and
+    /// in the image, and label them with the VAD that contains them.  Without VADs the runs
+    /// are treated as readable and writable so the searches still visit them.
+    pub fn from_volatility(
+        memmap: &VolatilityMemmap,
+        o_vads: Option<&VolatilityVadInfos>,
+    ) -> VolatilityRegionInfos {
+        let mut vads: RangeMap<u64, usize> = RangeMap::new();
+        if let Some(vad_infos) = o_vads {
+            for (idx, vad) in vad_infos.items.iter().enumerate() {
+                vads.insert(vad.start..vad.end.saturating_add(1), idx);
+            }
+        }
+
+        let mut pages = memmap.items.clone();
+        pages.sort_by_key(|p| p.vaddr);
+
+        // (vaddr, file_offset, size, vad index)
+        let mut runs: Vec<(u64, u64, u64, Option<usize>)> = Vec::new();
+        for page in pages.iter() {
+            let vad = vads.get(&page.vaddr).copied();
+            if let Some(last) = runs.last_mut() {
+                if last.0 + last.2 == page.vaddr
+                    && last.1 + last.2 == page.file_offset
+                    && last.3 == vad
+                {
+                    last.2 += page.size;
+                    continue;
+                }
+            }
+            runs.push((page.vaddr, page.file_offset, page.size, vad));
+        }
+
+        let mut infos = VolatilityRegionInfos::default();
+        for (section, (vaddr, paddr, size, vad_idx)) in runs.into_iter().enumerate() {
+            let o_vad = vad_idx.and_then(|idx| o_vads.map(|v| &v.items[idx]));
+            let (perm, mem_type, o_file) = match o_vad {
+                Some(vad) => (vad.protection, vad_mem_type(vad), vad.file.clone()),
+                None => (Protection::READ | Protection::WRITE, MemType::Unknown, None),
+            };
+            let name = match o_file {
+                Some(file) => file.rsplit(['\\', '/']).next().unwrap_or(&file).to_string(),
+                None => format!("Memory_Section_{}", section),
+            };
+            infos.items.push(VolatilityRegionInfo {
+                name,
+                size,
+                vsize: size,
+                perm,
+                paddr,
+                vaddr,
+                // only resident pages are listed by memmap
+                state: MemState::Commit,
+                mem_type,
+            });
+        }
+        infos
+    }
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use common::{scratch_dir, scratch_file};
+use mem_analysis::buffer::{DataBuffer, MappedFile};
+use mem_analysis::memory::BackendType;
+
+fn contents() -> Vec<u8> {
+    (0..0x3000u32).map(|v| (v % 253) as u8).collect()
+}
+
+#[test]
+fn file_backend_maps_the_dump() {
+    let path = scratch_file("buffer", "mapped.bin", &contents());
+    let db = DataBuffer::from_pathbuf(&path, true).unwrap();
+    assert_eq!(db.backend, Some(BackendType::File));
+    assert_eq!(db.size, 0x3000);
+    assert!(db.data.is_none());
+    let mapped = db.mmap.as_ref().unwrap();
+    assert_eq!(mapped.len(), 0x3000);
+    assert_eq!(db.get_shared_buffer().unwrap(), &contents()[..]);
+    assert_eq!(
+        db.get_shared_slice_from(0x2ff0, Some(0x10)).unwrap(),
+        &contents()[0x2ff0..]
+    );
+    assert!(db.get_shared_slice_from(0x2ff0, Some(0x11)).is_none());
+
+    let file = std::fs::File::open(&path).unwrap();
+    let again = MappedFile::open(&file).unwrap();
+    assert_eq!(&again[..], &mapped[..]);
+    // equality is identity of the mapping, not of the bytes
+    assert_ne!(&again, mapped.as_ref());
+    assert_eq!(mapped.as_ref(), mapped.as_ref());
+}
+
+#[test]
+fn empty_files_are_not_mapped() {
+    let path = scratch_file("buffer", "empty.bin", &[]);
+    let db = DataBuffer::from_pathbuf(&path, true).unwrap();
+    assert_eq!(db.backend, Some(BackendType::File));
+    assert_eq!(db.size, 0);
+    assert!(db.mmap.is_none());
+    assert_eq!(db.get_shared_buffer(), Some(&[][..]));
+    assert!(db.get_shared_slice_from(0, Some(1)).is_none());
+}
+
+#[test]
+fn backends_load_the_dump_on_request() {
+    let path = scratch_file("buffer", "full.bin", &contents());
+    let db = DataBuffer::from_pathbuf_with_backend(&path, true, BackendType::FullBuffer).unwrap();
+    assert_eq!(db.backend, Some(BackendType::FullBuffer));
+    assert!(db.mmap.is_none());
+    assert_eq!(db.data.as_deref(), Some(&contents()[..]));
+
+    let mut db = DataBuffer::from_pathbuf(&path, false).unwrap();
+    assert_eq!(db.size, 0);
+    assert!(db.get_shared_buffer().is_none());
+    db.map_data().unwrap();
+    assert_eq!(db.size, 0x3000);
+    assert!(db.mmap.is_some());
+
+    let missing = scratch_dir("buffer").join("missing.bin");
+    assert!(DataBuffer::from_pathbuf(&missing, true).is_err());
+    assert!(DataBuffer::new().map_data().is_err());
+}
+
+#[test]
+fn in_memory_loaders_set_the_backend_and_size() {
+    let db = DataBuffer::from_slice(&contents());
+    assert_eq!(db.backend, Some(BackendType::SliceBuffer));
+    assert_eq!(db.size, 0x3000);
+    assert_eq!(db.get_shared_buffer().unwrap(), &contents()[..]);
+
+    let db = DataBuffer::from_vec(vec![1, 2, 3]);
+    assert_eq!(db.backend, Some(BackendType::FullBuffer));
+    assert_eq!(db.size, 3);
+
+    // loading replaces whatever the buffer held before
+    let path = scratch_file("buffer", "replaced.bin", &contents());
+    let mut db = DataBuffer::from_pathbuf(&path, true).unwrap();
+    db.load_data_vec(vec![0u8; 0x10]);
+    assert_eq!(db.backend, Some(BackendType::FullBuffer));
+    assert_eq!(db.size, 0x10);
+    assert!(db.mmap.is_none());
+    db.load_data_slice(&[7u8; 0x20]);
+    assert_eq!(db.backend, Some(BackendType::SliceBuffer));
+    assert_eq!(db.get_shared_buffer(), Some(&[7u8; 0x20][..]));
+}
+
+#[test]
+fn path_and_file_loaders_honor_the_backend() {
+    let path = scratch_file("buffer", "loaded.bin", &contents());
+
+    let mut db = DataBuffer::new();
+    db.load_data_path(&path).unwrap();
+    assert_eq!(db.backend, Some(BackendType::File));
+    assert_eq!(db.size, 0x3000);
+    assert!(db.mmap.is_some());
+    assert_eq!(db.filename.as_deref(), path.to_str());
+
+    // an in-memory backend reads the file instead of mapping it
+    let mut db = DataBuffer::from_vec(Vec::new());
+    db.load_data_path(&path).unwrap();
+    assert_eq!(db.backend, Some(BackendType::FullBuffer));
+    assert_eq!(db.data.as_ref().map(|d| d.len()), Some(0x3000));
+
+    let file = std::fs::File::open(&path).unwrap();
+    let mut db = DataBuffer::new();
+    db.load_data_file(&file).unwrap();
+    assert_eq!(db.backend, Some(BackendType::File));
+    assert_eq!(db.get_shared_buffer().unwrap(), &contents()[..]);
+
+    let mut db = DataBuffer::from_slice(&[0u8; 4]);
+    db.load_data_file(&file).unwrap();
+    assert_eq!(db.backend, Some(BackendType::FullBuffer));
+    assert_eq!(db.size, 0x3000);
+    assert_eq!(db.get_shared_buffer().unwrap(), &contents()[..]);
+
+    let mut db = DataBuffer::new();
+    assert!(db
+        .load_data_path(&scratch_dir("buffer").join("missing.bin"))
+        .is_err());
+}
@@ -0,0 +1,72 @@
+use mem_analysis::builder::AddressSpaceBuilder;
+use mem_analysis::data_interface::ENDIAN;
+use mem_analysis::reader::VirtualMemoryReader;
+use mem_analysis::region::MemType;
+
+#[test]
+fn overlapping_regions_are_rejected() {
+    let r = AddressSpaceBuilder::new()
+        .region(0x10000, "-rw-", "heap", &[0u8; 0x2000])
+        .region(0x11000, "-r--", "data", &[0u8; 0x10])
+        .build();
+    let msg = r.unwrap_err().to_string();
+    assert!(msg.contains("heap"), "{}", msg);
+    assert!(msg.contains("data"), "{}", msg);
+
+    // touching regions are fine
+    AddressSpaceBuilder::new()
+        .region(0x10000, "-rw-", "heap", &[0u8; 0x1000])
+        .region(0x11000, "-r--", "data", &[0u8; 0x10])
+        .build()
+        .unwrap();
+}
+
+#[test]
+fn regions_are_page_aligned_in_the_buffer() {
+    let di = AddressSpaceBuilder::new()
+        .region(0x30000, "-rw-", "third", &[3u8; 0x10])
+        .region(0x10000, "-rw-", "first", &[1u8; 0x1001])
+        .region_with_type(0x20000, "-r-x", "second", &[2u8; 0x20], MemType::Image)
+        .build()
+        .unwrap();
+
+    let paddrs: Vec<(String, u64, u64)> = [0x10000, 0x20000, 0x30000]
+        .iter()
+        .map(|v| {
+            let mr = di.mem_ranges.get_vaddr_range(*v).unwrap();
+            (mr.name.clone(), mr.paddr_start, mr.size)
+        })
+        .collect();
+    assert_eq!(
+        paddrs,
+        vec![
+            ("first".to_string(), 0, 0x1001),
+            ("second".to_string(), 0x2000, 0x20),
+            ("third".to_string(), 0x3000, 0x10),
+        ]
+    );
+    let second = di.mem_ranges.get_vaddr_range(0x20000).unwrap();
+    assert_eq!(second.mem_type, MemType::Image);
+    assert!(!second.perm.is_writable());
+
+    // the padding is not mapped
+    assert_eq!(di.read_u8_vaddr(0x11000), Some(1));
+    assert_eq!(di.read_exact_vaddr(0x11001, 1), None);
+    assert_eq!(di.read_exact_vaddr(0x2001f, 1), Some(vec![2u8]));
+    assert_eq!(di.read_exact_vaddr(0x2001f, 2), None);
+}
+
+#[test]
+fn word_size_and_endianness_are_applied() {
+    let mut bytes = vec![0u8; 0x10];
+    bytes[..4].copy_from_slice(&0x1122_3344u32.to_be_bytes());
+    let di = AddressSpaceBuilder::new()
+        .word_sz(4)
+        .alignment(4)
+        .endian(ENDIAN::BIG)
+        .region(0x1000, "-rw-", "be", &bytes)
+        .build()
+        .unwrap();
+    assert_eq!(di.vmem_info.word_sz, 4);
+    assert_eq!(di.read_u32_vaddr(0x1000), Some(0x1122_3344));
+}
@@ -0,0 +1,41 @@
+// helpers shared by the integration tests, not every test uses all of them
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+
+pub fn put16(b: &mut [u8], o: usize, v: u16) {
+    b[o..o + 2].copy_from_slice(&v.to_le_bytes());
+}
+
+pub fn put32(b: &mut [u8], o: usize, v: u32) {
+    b[o..o + 4].copy_from_slice(&v.to_le_bytes());
+}
+
+pub fn put64(b: &mut [u8], o: usize, v: u64) {
+    b[o..o + 8].copy_from_slice(&v.to_le_bytes());
+}
+
+/// Append a LiME range header for `start..=end` followed by `data`.
+pub fn lime_range(out: &mut Vec<u8>, start: u64, end: u64, data: &[u8]) {
+    out.extend_from_slice(b"EMiL");
+    out.extend_from_slice(&1u32.to_le_bytes());
+    out.extend_from_slice(&start.to_le_bytes());
+    out.extend_from_slice(&end.to_le_bytes());
+    out.extend_from_slice(&[0u8; 8]);
+    out.extend_from_slice(data);
+}
+
+/// A directory of the temp dir for the test `name`, unique to this test run.
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("mem-analysis-test-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Write `contents` to `file` in the scratch directory of the test `name`.
+pub fn scratch_file(name: &str, file: &str, contents: &[u8]) -> PathBuf {
+    let path = scratch_dir(name).join(file);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use common::scratch_dir;
+use mem_analysis::compressed::{decompressed_path, Compression, CACHE_DIR_ENV};
+use std::io::Write;
+use std::path::Path;
+
+const SEEKABLE_MAGIC: u32 = 0x8f92_eab1;
+const SKIPPABLE_MAGIC: u32 = 0x184d_2a5e;
+
+fn sample_dump() -> Vec<u8> {
+    (0..0x30000u32)
+        .map(|v| (v.wrapping_mul(2654435761) >> 24) as u8)
+        .collect()
+}
+
+// independent zstd frames followed by the seek table, like `zstd --seekable` writes them
+fn seekable_zstd(data: &[u8], frame_size: usize) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut table = Vec::new();
+    for frame in data.chunks(frame_size) {
+        let compressed = zstd::bulk::compress(frame, 3).unwrap();
+        table.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+        table.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        out.extend_from_slice(&compressed);
+    }
+    let frames = data.chunks(frame_size).count() as u32;
+    out.extend_from_slice(&SKIPPABLE_MAGIC.to_le_bytes());
+    out.extend_from_slice(&(table.len() as u32 + 9).to_le_bytes());
+    out.extend_from_slice(&table);
+    out.extend_from_slice(&frames.to_le_bytes());
+    out.push(0);
+    out.extend_from_slice(&SEEKABLE_MAGIC.to_le_bytes());
+    out
+}
+
+fn decode(dir: &Path, name: &str, contents: &[u8]) -> Vec<u8> {
+    std::env::set_var(CACHE_DIR_ENV, dir.join("cache"));
+    let path = dir.join(name);
+    std::fs::File::create(&path)
+        .unwrap()
+        .write_all(contents)
+        .unwrap();
+    let decoded = decompressed_path(&path).unwrap();
+    assert_ne!(decoded, path);
+    // a second run reuses the cached copy
+    assert_eq!(decompressed_path(&path).unwrap(), decoded);
+    std::fs::read(decoded).unwrap()
+}
+
+#[test]
+fn compressed_inputs_decode_to_the_dump() {
+    let dir = scratch_dir("compressed");
+    let dump = sample_dump();
+
+    let seekable = seekable_zstd(&dump, 0x8000);
+    assert_eq!(Compression::from_magic(&seekable), Some(Compression::Zstd));
+    assert_eq!(decode(&dir, "seekable.zst", &seekable), dump);
+
+    let zstd = zstd::stream::encode_all(&dump[..], 3).unwrap();
+    assert_eq!(decode(&dir, "stream.zst", &zstd), dump);
+
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+    gz.write_all(&dump).unwrap();
+    let gz = gz.finish().unwrap();
+    assert_eq!(Compression::from_magic(&gz), Some(Compression::Gzip));
+    assert_eq!(decode(&dir, "dump.gz", &gz), dump);
+
+    let mut xz = xz2::write::XzEncoder::new(Vec::new(), 1);
+    xz.write_all(&dump).unwrap();
+    let xz = xz.finish().unwrap();
+    assert_eq!(Compression::from_magic(&xz), Some(Compression::Xz));
+    assert_eq!(decode(&dir, "dump.xz", &xz), dump);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn uncompressed_files_are_used_as_is() {
+    let dir = scratch_dir("uncompressed");
+    let path = dir.join("dump.raw");
+    std::fs::write(&path, sample_dump()).unwrap();
+    assert_eq!(Compression::from_path(&path).unwrap(), None);
+    assert_eq!(decompressed_path(&path).unwrap(), path);
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use common::{put16, put32, put64};
+use mem_analysis::elfcore::ElfCoreRegionInfos;
+use mem_analysis::region::MemType;
+
+const NT_FILE: u32 = 0x4649_4c45;
+const LOAD_VADDR: u64 = 0x7f00_0000_0000;
+
+// a little endian ELF64 core with a PT_LOAD segment of 0x10 bytes and an NT_FILE note that
+// names it, the note claims `count` entries but holds one
+fn core_with_nt_file(count: u64) -> Vec<u8> {
+    let mut desc = vec![0u8; 16 + 24];
+    put64(&mut desc, 0, count);
+    put64(&mut desc, 8, 0x1000);
+    put64(&mut desc, 16, LOAD_VADDR);
+    put64(&mut desc, 24, LOAD_VADDR + 0x1000);
+    desc.extend_from_slice(b"/usr/lib/libx.so\0");
+
+    let note_off = 64 + 2 * 56;
+    let note_len = 12 + 8 + desc.len();
+    let load_off = note_off + note_len;
+    let mut core = vec![0u8; load_off + 0x10];
+    core[0..4].copy_from_slice(b"\x7fELF");
+    core[4] = 2;
+    core[5] = 1;
+    put16(&mut core, 16, 4);
+    put16(&mut core, 18, 62);
+    put64(&mut core, 32, 64);
+    put16(&mut core, 54, 56);
+    put16(&mut core, 56, 2);
+
+    // PT_NOTE
+    put32(&mut core, 64, 4);
+    put64(&mut core, 64 + 8, note_off as u64);
+    put64(&mut core, 64 + 32, note_len as u64);
+    // PT_LOAD, readable
+    put32(&mut core, 120, 1);
+    put32(&mut core, 120 + 4, 4);
+    put64(&mut core, 120 + 8, load_off as u64);
+    put64(&mut core, 120 + 16, LOAD_VADDR);
+    put64(&mut core, 120 + 32, 0x10);
+    put64(&mut core, 120 + 40, 0x1000);
+
+    put32(&mut core, note_off, 5);
+    put32(&mut core, note_off + 4, desc.len() as u32);
+    put32(&mut core, note_off + 8, NT_FILE);
+    core[note_off + 12..note_off + 17].copy_from_slice(b"CORE\0");
+    core[note_off + 20..note_off + 20 + desc.len()].copy_from_slice(&desc);
+    core
+}
+
+#[test]
+fn nt_file_names_the_mapping() {
+    let infos = ElfCoreRegionInfos::from_elf_core_bytes(&core_with_nt_file(1)).unwrap();
+    assert_eq!(infos.items.len(), 1);
+    let item = &infos.items[0];
+    assert_eq!(item.vaddr, LOAD_VADDR);
+    assert_eq!(item.size, 0x10);
+    assert_eq!(item.vsize, 0x1000);
+    assert_eq!(item.name, "/usr/lib/libx.so");
+    assert_eq!(item.mem_type, MemType::Mapped);
+}
+
+#[test]
+fn corrupt_nt_file_count_is_ignored() {
+    let infos = ElfCoreRegionInfos::from_elf_core_bytes(&core_with_nt_file(u64::MAX)).unwrap();
+    assert_eq!(infos.items.len(), 1);
+    assert_eq!(infos.items[0].mem_type, MemType::Private);
+}
+
+#[test]
+fn program_headers_past_the_end_are_an_error() {
+    let mut core = core_with_nt_file(1);
+    core[32..40].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert!(ElfCoreRegionInfos::from_elf_core_bytes(&core).is_err());
+}
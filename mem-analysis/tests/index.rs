@@ -0,0 +1,97 @@
+mod common;
+
+use common::{lime_range, scratch_file};
+use mem_analysis::data_interface::DataInterface;
+use mem_analysis::index::{default_index_path, AddressSpaceIndex, DumpFingerprint};
+use mem_analysis::reader::VirtualMemoryReader;
+use std::cell::Cell;
+use std::path::Path;
+
+// two ranges of two pages, the first word of each points into the other one
+fn capture() -> Vec<u8> {
+    let mut first = vec![0x11u8; 0x2000];
+    first[..8].copy_from_slice(&0x10_1800u64.to_le_bytes());
+    let mut second = vec![0x22u8; 0x2000];
+    second[..8].copy_from_slice(&0x1800u64.to_le_bytes());
+    let mut image = Vec::new();
+    lime_range(&mut image, 0x1000, 0x2fff, &first);
+    lime_range(&mut image, 0x10_1000, 0x10_2fff, &second);
+    image
+}
+
+fn load(dump: &Path, source: &str, builds: &Cell<u32>) -> DataInterface {
+    let index_path = default_index_path(dump);
+    DataInterface::from_index_or_build(dump, &index_path, source, || {
+        builds.set(builds.get() + 1);
+        DataInterface::from_dump_file(dump)
+    })
+    .unwrap()
+}
+
+#[test]
+fn fingerprints_cover_every_byte() {
+    let mut image = capture();
+    let path = scratch_file("index", "fingerprint.lime", &image);
+    let fingerprint = DumpFingerprint::from_path(&path).unwrap();
+    assert_eq!(fingerprint.size, image.len() as u64);
+    assert_eq!(DumpFingerprint::from_path(&path).unwrap(), fingerprint);
+
+    // an edit in the middle that keeps the size
+    image[0x1234] ^= 0xff;
+    std::fs::write(&path, &image).unwrap();
+    let edited = DumpFingerprint::from_path(&path).unwrap();
+    assert_eq!(edited.size, fingerprint.size);
+    assert_ne!(edited.digest, fingerprint.digest);
+}
+
+#[test]
+fn saved_indexes_rebuild_the_address_space() {
+    let path = scratch_file("index", "capture.lime", &capture());
+    let builds = Cell::new(0);
+    let built = load(&path, "headers", &builds);
+    assert_eq!(builds.get(), 1);
+    assert!(default_index_path(&path).exists());
+
+    let loaded = load(&path, "headers", &builds);
+    assert_eq!(builds.get(), 1);
+    assert_eq!(loaded.mem_ranges.count(), built.mem_ranges.count());
+    assert_eq!(
+        loaded.mem_ranges.get_mem_ranges(),
+        built.mem_ranges.get_mem_ranges()
+    );
+    assert_eq!(loaded.mem_ranges.diagnostics, built.mem_ranges.diagnostics);
+    assert_eq!(loaded.read_u64_vaddr(0x1000), Some(0x10_1800));
+    assert_eq!(loaded.read_u8_vaddr(0x10_2fff), Some(0x22));
+    // the second page of each range is only known to the rebuilt page lookup
+    for vaddr in [0x1800, 0x2ff8, 0x10_1800, 0x10_2000] {
+        assert!(loaded.is_vaddr_ptr(vaddr), "{:x}", vaddr);
+    }
+    assert!(!loaded.is_vaddr_ptr(0x3000));
+    assert!(!loaded.is_vaddr_ptr(0x10_0ff8));
+
+    // other section sources and edited dumps are built again
+    load(&path, "r2:other", &builds);
+    assert_eq!(builds.get(), 2);
+    drop((built, loaded));
+    let mut image = capture();
+    image[0x40] = 0x33;
+    std::fs::write(&path, &image).unwrap();
+    let rebuilt = load(&path, "r2:other", &builds);
+    assert_eq!(builds.get(), 3);
+    assert_eq!(rebuilt.read_u8_vaddr(0x1020), Some(0x33));
+}
+
+#[test]
+fn indexes_of_another_version_are_not_reused() {
+    let path = scratch_file("index", "versioned.lime", &capture());
+    let builds = Cell::new(0);
+    let di = load(&path, "headers", &builds);
+    let fingerprint = DumpFingerprint::from_path(&path).unwrap();
+    let mut index = AddressSpaceIndex::from_data_interface(&di, fingerprint.clone(), "headers");
+    assert!(index.matches(&fingerprint, "headers"));
+    index.version -= 1;
+    assert!(!index.matches(&fingerprint, "headers"));
+    index.save(&default_index_path(&path)).unwrap();
+    load(&path, "headers", &builds);
+    assert_eq!(builds.get(), 2);
+}
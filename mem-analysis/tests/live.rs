@@ -0,0 +1,51 @@
+#![cfg(target_os = "linux")]
+mod common;
+
+use common::scratch_file;
+use mem_analysis::live::LiveProcessMemory;
+use memmap2::MmapOptions;
+
+#[test]
+fn reads_a_span_of_this_process() {
+    let data: Vec<u8> = (0..0x30000u32).map(|v| (v % 251) as u8).collect();
+    let vaddr = data.as_ptr() as u64;
+    let live = LiveProcessMemory::attach(std::process::id()).unwrap();
+
+    let word = live.get_slice(vaddr + 0x100, Some(8)).unwrap();
+    assert_eq!(word, &data[0x100..0x108]);
+    // crosses the blocks read from /proc/<pid>/mem
+    let span = live.get_slice(vaddr + 0xfff0, Some(0x20010)).unwrap();
+    assert_eq!(span, &data[0xfff0..0x30000]);
+    // a cached block is handed out again
+    let again = live.get_slice(vaddr + 0x100, Some(8)).unwrap();
+    assert_eq!(again, &data[0x100..0x108]);
+}
+
+#[test]
+fn unmapped_addresses_are_not_read() {
+    let live = LiveProcessMemory::attach(std::process::id()).unwrap();
+    assert!(live.get_slice(0, Some(8)).is_none());
+    let last = live.maps.iter().map(|m| m.end).max().unwrap();
+    assert!(live.get_slice(last, Some(8)).is_none());
+}
+
+#[test]
+fn unreadable_blocks_cut_reads_short() {
+    // a file mapping past the end of its file, the pages after the first 64 KiB block can't
+    // be faulted in and fail to read from /proc/<pid>/mem
+    let path = scratch_file("live", "short.bin", &[0x5a; 0x10000]);
+    let file = std::fs::File::open(path).unwrap();
+    let map = unsafe { MmapOptions::new().len(0x30000).map(&file).unwrap() };
+    let vaddr = map.as_ptr() as u64;
+    let live = LiveProcessMemory::attach(std::process::id()).unwrap();
+
+    assert!(live.get_slice(vaddr, Some(0x20000)).is_none());
+    assert!(live.get_slice(vaddr + 0x10000, Some(8)).is_none());
+    let readable = live.get_partial_slice(vaddr, 0x20000).unwrap();
+    assert_eq!(readable.len(), 0x10000);
+    assert!(readable.iter().all(|b| *b == 0x5a));
+    assert_eq!(live.get_slice(vaddr, None).map(|b| b.len()), Some(0x10000));
+    // the unreadable block is remembered and nothing before it is left to return
+    assert!(live.get_partial_slice(vaddr + 0x18000, 8).is_none());
+    assert_eq!(live.get_slice(vaddr + 0xfff8, Some(8)).unwrap(), &[0x5a; 8]);
+}
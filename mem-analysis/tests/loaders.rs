@@ -0,0 +1,238 @@
+mod common;
+
+use common::{lime_range, put16, put32, put64, scratch_file};
+use mem_analysis::buffer::DataBuffer;
+use mem_analysis::data_interface::DataInterface;
+use mem_analysis::diagnostics::DiagnosticKind;
+use mem_analysis::pagetable::PhysicalRuns;
+use mem_analysis::reader::VirtualMemoryReader;
+use mem_analysis::region::{MemState, MemType};
+
+const HEAP_VADDR: u64 = 0x10000;
+const IMAGE_VADDR: u64 = 0x1_4000_0000;
+
+// an x64 minidump with a rw heap and a read only image mapping of `game.exe` at `image_vaddr`,
+// described by the SystemInfo, MemoryInfoList, ModuleList and Memory64List streams
+fn minidump(image_vaddr: u64) -> Vec<u8> {
+    let dir_off = 32;
+    let sysinfo_off = dir_off + 4 * 12;
+    let infos_off = sysinfo_off + 56;
+    let modules_off = infos_off + 16 + 2 * 48;
+    let name_off = modules_off + 4 + 108;
+    let name: Vec<u16> = "C:\\game\\game.exe".encode_utf16().collect();
+    let mem_off = (name_off + 4 + 2 * name.len() + 2 + 7) & !7;
+    let data_off = mem_off + 16 + 2 * 16;
+    let mut dump = vec![0u8; data_off + 0x2000 + 0x1000];
+
+    dump[0..4].copy_from_slice(b"MDMP");
+    put32(&mut dump, 4, 0xa793);
+    put32(&mut dump, 8, 4);
+    put32(&mut dump, 12, dir_off as u32);
+    let streams = [
+        (7, 56, sysinfo_off),
+        (16, 16 + 2 * 48, infos_off),
+        (4, 4 + 108, modules_off),
+        (9, 16 + 2 * 16, mem_off),
+    ];
+    for (idx, (kind, size, rva)) in streams.iter().enumerate() {
+        let o = dir_off + idx * 12;
+        put32(&mut dump, o, *kind);
+        put32(&mut dump, o + 4, *size as u32);
+        put32(&mut dump, o + 8, *rva as u32);
+    }
+
+    // PROCESSOR_ARCHITECTURE_AMD64
+    put16(&mut dump, sysinfo_off, 9);
+
+    put32(&mut dump, infos_off, 16);
+    put32(&mut dump, infos_off + 4, 48);
+    put64(&mut dump, infos_off + 8, 2);
+    // base, protection, size, state, type
+    let infos = [
+        (HEAP_VADDR, 0x04, 0x2000, 0x1000, 0x20000),
+        (image_vaddr, 0x02, 0x1000, 0x1000, 0x100_0000),
+    ];
+    for (idx, (base, protect, size, state, ty)) in infos.iter().enumerate() {
+        let o = infos_off + 16 + idx * 48;
+        put64(&mut dump, o, *base);
+        put64(&mut dump, o + 8, *base);
+        put32(&mut dump, o + 16, *protect);
+        put64(&mut dump, o + 24, *size);
+        put32(&mut dump, o + 32, *state);
+        put32(&mut dump, o + 36, *protect);
+        put32(&mut dump, o + 40, *ty);
+    }
+
+    put32(&mut dump, modules_off, 1);
+    put64(&mut dump, modules_off + 4, image_vaddr);
+    put32(&mut dump, modules_off + 12, 0x1000);
+    put32(&mut dump, modules_off + 24, name_off as u32);
+    put32(&mut dump, name_off, 2 * name.len() as u32);
+    for (idx, c) in name.iter().enumerate() {
+        put16(&mut dump, name_off + 4 + 2 * idx, *c);
+    }
+
+    put64(&mut dump, mem_off, 2);
+    put64(&mut dump, mem_off + 8, data_off as u64);
+    put64(&mut dump, mem_off + 16, HEAP_VADDR);
+    put64(&mut dump, mem_off + 24, 0x2000);
+    put64(&mut dump, mem_off + 32, image_vaddr);
+    put64(&mut dump, mem_off + 40, 0x1000);
+
+    put64(&mut dump, data_off + 0x1ff8, IMAGE_VADDR + 0x10);
+    dump[data_off + 0x2000..data_off + 0x2002].copy_from_slice(b"MZ");
+    dump
+}
+
+#[test]
+fn minidump_regions_and_attributes() {
+    let path = scratch_file("loaders", "game.dmp", &minidump(IMAGE_VADDR));
+    let di = DataInterface::from_dump_file(&path).unwrap();
+    assert_eq!(di.vmem_info.word_sz, 8);
+    assert_eq!(di.mem_ranges.count(), 2);
+
+    let heap = di.mem_ranges.get_vaddr_range(HEAP_VADDR).unwrap();
+    assert_eq!(heap.name, "Memory_Section_0");
+    assert_eq!(heap.vsize, 0x2000);
+    assert!(heap.perm.is_writable());
+    assert_eq!(heap.state, MemState::Commit);
+    assert_eq!(heap.mem_type, MemType::Private);
+
+    let image = di.mem_ranges.get_vaddr_range(IMAGE_VADDR).unwrap();
+    assert_eq!(image.name, "game.exe");
+    assert!(!image.perm.is_writable());
+    assert_eq!(image.mem_type, MemType::Image);
+
+    assert_eq!(
+        di.read_u64_vaddr(HEAP_VADDR + 0x1ff8),
+        Some(IMAGE_VADDR + 0x10)
+    );
+    assert_eq!(di.read_exact_vaddr(IMAGE_VADDR, 2), Some(b"MZ".to_vec()));
+}
+
+#[test]
+fn minidump_ranges_that_wrap_the_address_space_are_skipped() {
+    let image_vaddr = u64::MAX - 0x7ff;
+    let path = scratch_file("loaders", "wrapped.dmp", &minidump(image_vaddr));
+    let di = DataInterface::from_dump_file(&path).unwrap();
+    assert_eq!(di.mem_ranges.count(), 1);
+    assert!(di.mem_ranges.get_vaddr_range(HEAP_VADDR).is_some());
+    let diagnostics = &di.mem_ranges.diagnostics;
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].kind, DiagnosticKind::AddressOverflow);
+    assert_eq!(diagnostics[0].vaddr, image_vaddr);
+    // the module that wraps is not used for the name
+    assert_eq!(diagnostics[0].name, "Memory_Section_1");
+}
+
+#[test]
+fn lime_ranges_keep_the_bytes_of_a_truncated_capture() {
+    let mut image = Vec::new();
+    lime_range(&mut image, 0x1000, 0x1fff, &[0x11u8; 0x1000]);
+    // the capture stops half way through the second range
+    lime_range(&mut image, 0x10_0000, 0x10_0fff, &[0x22u8; 0x800]);
+    let path = scratch_file("loaders", "capture.lime", &image);
+    let di = DataInterface::from_dump_file(&path).unwrap();
+    assert_eq!(di.mem_ranges.count(), 2);
+
+    let first = di.mem_ranges.get_vaddr_range(0x1000).unwrap();
+    assert_eq!((first.paddr_start, first.size), (32, 0x1000));
+    let second = di.mem_ranges.get_vaddr_range(0x10_0000).unwrap();
+    assert_eq!((second.size, second.vsize), (0x800, 0x1000));
+
+    assert_eq!(di.read_u8_vaddr(0x1fff), Some(0x11));
+    assert_eq!(di.read_u8_vaddr(0x10_07ff), Some(0x22));
+    assert_eq!(di.read_u8_vaddr(0x10_0800), None);
+}
+
+#[test]
+fn volatility_runs_are_merged_and_named_after_their_vad() {
+    let mut image = vec![0u8; 0x4000];
+    image[0x1000..0x3000].fill(0x33);
+    image[0x3000..0x4000].fill(0x44);
+    let memmap = r#"[
+        {"Virtual": 65536, "Physical": 4096, "Size": 4096, "Offset in File": 4096,
+         "__children": [
+            {"Virtual": 69632, "Physical": 8192, "Size": 4096, "Offset in File": 8192}
+         ]},
+        {"Virtual": "0x7ff00000", "Physical": "0x3000", "Size": "0x1000"}
+    ]"#;
+    let vadinfo = r#"[
+        {"Start VPN": 65536, "End VPN": 73727, "Tag": "VadS", "Protection": "PAGE_READWRITE",
+         "PrivateMemory": 1, "File": "N/A"},
+        {"Start VPN": 2146435072, "End VPN": 2146439167, "Tag": "Vad",
+         "Protection": "PAGE_EXECUTE_READ", "PrivateMemory": 0,
+         "File": "\\Windows\\System32\\ntdll.dll"}
+    ]"#;
+    let image_path = scratch_file("loaders", "volatility.raw", &image);
+    let memmap_path = scratch_file("loaders", "memmap.json", memmap.as_bytes());
+    let vadinfo_path = scratch_file("loaders", "vadinfo.json", vadinfo.as_bytes());
+    let di =
+        DataInterface::from_volatility(&image_path, &memmap_path, Some(&vadinfo_path)).unwrap();
+    assert_eq!(di.mem_ranges.count(), 2);
+
+    let heap = di.mem_ranges.get_vaddr_range(0x10000).unwrap();
+    assert_eq!((heap.paddr_start, heap.size), (0x1000, 0x2000));
+    assert!(heap.perm.is_writable());
+    assert_eq!(heap.mem_type, MemType::Private);
+
+    let ntdll = di.mem_ranges.get_vaddr_range(0x7ff0_0000).unwrap();
+    assert_eq!(ntdll.name, "ntdll.dll");
+    assert_eq!(ntdll.mem_type, MemType::Image);
+    assert!(!ntdll.perm.is_writable());
+
+    assert_eq!(di.read_u8_vaddr(0x11fff), Some(0x33));
+    assert_eq!(di.read_u8_vaddr(0x7ff0_0000), Some(0x44));
+}
+
+const PRESENT: u64 = 1;
+const WRITABLE: u64 = 2;
+const USER: u64 = 4;
+const NO_EXECUTE: u64 = 1 << 63;
+
+#[test]
+fn page_tables_translate_user_pages() {
+    // PML4 at 0x1000, PDPT at 0x2000, PD at 0x3000 and a page table at 0x4000 mapping
+    // 0x400000 and 0x401000, the supervisor page at 0x402000 is left out
+    let mut image = vec![0u8; 0x8000];
+    put64(&mut image, 0x1000, 0x2000 | PRESENT | WRITABLE | USER);
+    put64(&mut image, 0x2000, 0x3000 | PRESENT | WRITABLE | USER);
+    put64(
+        &mut image,
+        0x3000 + 2 * 8,
+        0x4000 | PRESENT | WRITABLE | USER,
+    );
+    put64(
+        &mut image,
+        0x4000,
+        0x5000 | PRESENT | WRITABLE | USER | NO_EXECUTE,
+    );
+    put64(&mut image, 0x4008, 0x6000 | PRESENT | USER);
+    put64(&mut image, 0x4010, 0x7000 | PRESENT | WRITABLE);
+    image[0x5000..0x6000].fill(0x55);
+    image[0x6000..0x7000].fill(0x66);
+
+    let runs = PhysicalRuns::identity(image.len() as u64);
+    let di = DataInterface::from_physical_runs(DataBuffer::from_vec(image), &runs, 0x1000).unwrap();
+    assert_eq!(di.mem_ranges.count(), 2);
+    assert_eq!(di.vmem_info.word_sz, 8);
+
+    let data = di.mem_ranges.get_vaddr_range(0x40_0000).unwrap();
+    assert_eq!((data.paddr_start, data.size), (0x5000, 0x1000));
+    assert!(data.perm.is_writable());
+    assert!(!data.perm.is_executable());
+    let code = di.mem_ranges.get_vaddr_range(0x40_1000).unwrap();
+    assert!(!code.perm.is_writable());
+    assert!(code.perm.is_executable());
+
+    assert_eq!(di.read_u8_vaddr(0x40_0fff), Some(0x55));
+    assert_eq!(di.read_u8_vaddr(0x40_1000), Some(0x66));
+    assert_eq!(di.read_u8_vaddr(0x40_2000), None);
+
+    assert!(DataInterface::from_physical_runs(
+        DataBuffer::from_vec(vec![0u8; 0x2000]),
+        &runs,
+        0x1000
+    )
+    .is_err());
+}
@@ -0,0 +1,100 @@
+use mem_analysis::pagelookup::PageLookup;
+use mem_analysis::pointers::{PointerRange, XrefIndex};
+
+fn ptr_range(vstart: u64, vend: u64) -> PointerRange {
+    PointerRange::new(0, vend - vstart, vstart, vend).unwrap()
+}
+
+#[test]
+fn page_lookup_maps_pages_to_ranges() {
+    let mut lookup = PageLookup::new(0x1000);
+    assert!(lookup.is_empty());
+    lookup.insert(&ptr_range(0x10000, 0x12800));
+    lookup.insert(&ptr_range(0x40000, 0x41000));
+
+    assert_eq!(lookup.len(), 4);
+    assert!(lookup.contains(0x10000));
+    // the partial last page of a range is mapped as a whole
+    assert!(lookup.contains(0x12fff));
+    assert!(!lookup.contains(0x13000));
+    assert!(!lookup.contains(0xffff));
+    assert!(!lookup.contains(0x41000));
+    assert_eq!(lookup.get(0x12fff).unwrap().vstart, 0x10000);
+    assert_eq!(lookup.get(0x40800).unwrap().vstart, 0x40000);
+    assert!(lookup.get(0x20000).is_none());
+
+    lookup.clear();
+    assert!(lookup.is_empty());
+    assert!(!lookup.contains(0x10000));
+}
+
+#[test]
+fn page_lookup_shared_pages_belong_to_the_last_range() {
+    let mut lookup = PageLookup::new(0x1000);
+    lookup.insert(&ptr_range(0x10000, 0x14000));
+    lookup.insert(&ptr_range(0x11800, 0x12800));
+    assert_eq!(lookup.get(0x10fff).unwrap().vstart, 0x10000);
+    assert_eq!(lookup.get(0x11000).unwrap().vstart, 0x11800);
+    assert_eq!(lookup.get(0x12fff).unwrap().vstart, 0x11800);
+    assert_eq!(lookup.get(0x13000).unwrap().vstart, 0x10000);
+    assert_eq!(lookup.len(), 4);
+}
+
+#[test]
+fn page_lookup_ignores_empty_ranges_and_handles_high_pages() {
+    let mut lookup = PageLookup::new(0x1000);
+    lookup.insert(&ptr_range(0x10000, 0x10000));
+    assert!(lookup.is_empty());
+
+    // above the 48 bit range of the page bitmap
+    let high = 0xffff_8000_0000_0000;
+    lookup.insert(&ptr_range(high, high + 0x2000));
+    assert!(lookup.contains(high + 0x1fff));
+    assert!(!lookup.contains(high + 0x2000));
+    assert!(!lookup.contains(high - 1));
+
+    let top = u64::MAX - 0x1fff;
+    lookup.insert(&ptr_range(top, u64::MAX));
+    assert!(lookup.contains(u64::MAX - 1));
+    assert_eq!(lookup.get(u64::MAX - 0x800).unwrap().vstart, top);
+    assert_eq!(lookup.len(), 4);
+}
+
+#[test]
+fn xrefs_are_indexed_in_both_directions() {
+    let mut xrefs = XrefIndex::new();
+    xrefs.add(0x1000, 0x5000);
+    xrefs.add(0x1008, 0x5000);
+    xrefs.add(0x1010, 0x5010);
+    assert_eq!(xrefs.len(), 3);
+    assert_eq!(xrefs.xrefs_to(0x5000), &[0x1000, 0x1008]);
+    assert_eq!(xrefs.xrefs_from(0x1010), Some(0x5010));
+    assert_eq!(xrefs.xrefs_from(0x5000), None);
+    assert_eq!(
+        xrefs.xrefs_to_range(0x5000, 0x5020),
+        vec![(0x1000, 0x5000), (0x1008, 0x5000), (0x1010, 0x5010)]
+    );
+    assert!(xrefs.xrefs_to_range(0x5020, 0x5000).is_empty());
+
+    // a source holds one value, adding it again moves it
+    xrefs.add(0x1000, 0x6000);
+    assert_eq!(xrefs.xrefs_to(0x5000), &[0x1008]);
+    assert_eq!(xrefs.xrefs_to(0x6000), &[0x1000]);
+    assert_eq!(xrefs.len(), 3);
+}
+
+#[test]
+fn xrefs_merge() {
+    let mut a = XrefIndex::new();
+    a.add(0x1000, 0x5000);
+    let mut b = XrefIndex::new();
+    b.add(0x2000, 0x5000);
+    b.add(0x1000, 0x7000);
+    a.merge(&b);
+    assert_eq!(
+        a.pointers().collect::<Vec<_>>(),
+        vec![(0x1000, 0x7000), (0x2000, 0x5000)]
+    );
+    assert_eq!(a.xrefs_to(0x5000), &[0x2000]);
+    assert!(XrefIndex::default().is_empty());
+}
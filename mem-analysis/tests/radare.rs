@@ -0,0 +1,75 @@
+mod common;
+
+use common::scratch_file;
+use mem_analysis::memory::MemRange;
+use mem_analysis::radare::{parse_radare_name, RadareMemoryInfos};
+use mem_analysis::region::{MemState, MemType, Protection};
+
+#[test]
+fn section_names_carry_the_region_attributes() {
+    let info = parse_radare_name(
+        &"paddr=0x1a2b0 state=0x1000 allocation=0x40 type=0x1000000 game.exe".to_string(),
+    );
+    assert_eq!(info.name, "game.exe");
+    assert_eq!(info.paddr, 0x1a2b0);
+    assert_eq!(info.state, MemState::Commit);
+    assert_eq!(info.mem_type, MemType::Image);
+    assert_eq!(
+        info.alloc_protection,
+        Protection::READ | Protection::WRITE | Protection::EXECUTE
+    );
+
+    let info = parse_radare_name(&"state=0x2000 type=0x20000 Memory_Section_4".to_string());
+    assert_eq!(info.name, "Memory_Section_4");
+    assert_eq!(info.paddr, 0);
+    assert_eq!(info.state, MemState::Reserve);
+    assert_eq!(info.mem_type, MemType::Private);
+    assert_eq!(info.alloc_protection, Protection::NONE);
+}
+
+#[test]
+fn missing_or_malformed_attributes_are_unknown() {
+    let info = parse_radare_name(&"state=zz type= heap".to_string());
+    assert_eq!(info.name, "heap");
+    assert_eq!(info.state, MemState::Unknown);
+    assert_eq!(info.mem_type, MemType::Unknown);
+
+    let info = parse_radare_name(&"stack".to_string());
+    assert_eq!(info.name, "stack");
+    assert_eq!(info.state, MemState::Unknown);
+}
+
+#[test]
+fn sections_json_is_parsed_into_mem_ranges() {
+    let json = r#"[
+        {"name": "paddr=0x400 state=0x1000 allocation=0x4 type=0x20000 heap",
+         "size": 4096, "vsize": 8192, "perm": "-rw-", "paddr": 1024, "vaddr": 65536},
+        {"name": "game.exe", "size": 512, "vsize": 512, "perm": "-r-x", "paddr": 5120,
+         "vaddr": 5368709120}
+    ]"#;
+    let path = scratch_file("radare", "sections.json", json.as_bytes());
+    let infos = RadareMemoryInfos::from_radare_json(&path).unwrap();
+    assert_eq!(infos.items.len(), 2);
+
+    let heap = MemRange::from_radare_info(&infos.items[0]);
+    assert_eq!((heap.vaddr_start, heap.paddr_start), (0x10000, 0x400));
+    assert_eq!((heap.size, heap.vsize), (0x1000, 0x2000));
+    assert_eq!(heap.perm, Protection::READ | Protection::WRITE);
+    assert_eq!(heap.perm_str(), "-rw-");
+    assert_eq!(heap.state, MemState::Commit);
+    assert_eq!(heap.mem_type, MemType::Private);
+    assert_eq!(heap.alloc_protection, Protection::READ | Protection::WRITE);
+
+    let image = MemRange::from_radare_info(&infos.items[1]);
+    assert_eq!(image.perm_str(), "-r-x");
+    assert_eq!(image.state, MemState::Unknown);
+    assert_eq!(image.mem_type, MemType::Unknown);
+}
+
+#[test]
+fn unreadable_sections_json_is_an_error() {
+    let path = scratch_file("radare", "broken.json", br#"[{"name": "heap", "size": 1}]"#);
+    assert!(RadareMemoryInfos::from_radare_json(&path).is_err());
+    let missing = path.with_file_name("missing.json");
+    assert!(RadareMemoryInfos::from_radare_json(&missing).is_err());
+}
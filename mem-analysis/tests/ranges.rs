@@ -0,0 +1,89 @@
+use mem_analysis::buffer::DataBuffer;
+use mem_analysis::builder::AddressSpaceBuilder;
+use mem_analysis::data_interface::DataInterface;
+use mem_analysis::memory::{MemRange, MemRanges, Memory};
+use mem_analysis::region::Protection;
+
+const TOP: u64 = u64::MAX - 0x1fff;
+
+fn range(vaddr: u64, paddr: u64, vsize: u64, size: u64) -> MemRange {
+    MemRange::new(
+        Protection::from_perm_str("rw-"),
+        "range".to_string(),
+        vaddr,
+        paddr,
+        vsize,
+        size,
+        None,
+        None,
+    )
+}
+
+#[test]
+fn mappings_past_the_end_of_the_address_space_are_an_error() {
+    let mut buffer = DataBuffer::from_slice(&[0u8; 0x100]);
+    assert!(buffer.add_vaddr_mapping(u64::MAX - 0x10, 0, 0x100).is_err());
+    assert!(buffer.add_vaddr_mapping(0x1000, u64::MAX, 0x100).is_err());
+    assert_eq!(buffer.get_paddr_from_vaddr(u64::MAX - 0x8), None);
+    buffer.add_vaddr_mapping(0x1000, 0, 0x100).unwrap();
+    assert_eq!(buffer.get_paddr_from_vaddr(0x1010), Some(0x10));
+}
+
+#[test]
+fn range_lookups_at_the_top_of_the_address_space() {
+    let mr = range(u64::MAX - 0x10, u64::MAX - 0x10, u64::MAX, u64::MAX);
+    assert!(mr.vaddr_in_range(u64::MAX));
+    assert!(!mr.vaddr_in_range(0x1000));
+    assert!(mr.paddr_in_range(u64::MAX));
+    assert!(!mr.paddr_in_range(0x1000));
+    assert_eq!(mr.get_paddr_from_vaddr(u64::MAX), Some(u64::MAX));
+    assert_eq!(mr.get_vaddr_from_paddr(0x1000), None);
+}
+
+#[test]
+fn reads_at_the_top_of_the_address_space() {
+    let di = AddressSpaceBuilder::new()
+        .region(TOP, "-rw-", "top", &[0x41u8; 0x1000])
+        .build()
+        .unwrap();
+    assert_eq!(di.get_vaddr_end(TOP), Some(TOP + 0x1000));
+    assert!(di.can_read_buffer_at(TOP, 0x10));
+    assert!(!di.can_read_buffer_at(TOP + 0x10, u64::MAX));
+    assert_eq!(di.shared_slice_vaddr(TOP + 0x10, u64::MAX), None);
+    assert_eq!(di.shared_slice_paddr(0x10, u64::MAX), None);
+    assert_eq!(di.shared_slice_vaddr(TOP, 4), Some(&[0x41u8; 4][..]));
+    assert_eq!(di.shared_buffer_vaddr(TOP).map(|b| b.len()), Some(0x1000));
+    assert!(!di.can_read_buffer(&[0u8; 0x10], u64::MAX, 2));
+}
+
+#[test]
+fn empty_ranges_have_no_buffer() {
+    let mut mem_ranges = MemRanges::new();
+    mem_ranges.add_mem_range(range(0x1000, 0, 0, 0x10));
+    let buffer = DataBuffer::from_slice(&[0u8; 0x10]);
+    let di = DataInterface::new_from_mem_ranges(buffer, mem_ranges, None);
+    assert_eq!(di.shared_buffer_vaddr(0x1000), None);
+}
+
+#[test]
+fn slices_inside_a_range_stop_at_its_backed_end() {
+    let mut mem_ranges = MemRanges::new();
+    // only the first 0x80 of the 0x100 bytes are in the dump
+    mem_ranges.add_mem_range(range(0x1000, 0x10, 0x100, 0x80));
+    let bytes: Vec<u8> = (0..0x200u32).map(|b| b as u8).collect();
+    let di = DataInterface::new_from_mem_ranges(DataBuffer::from_slice(&bytes), mem_ranges, None);
+    assert_eq!(
+        di.shared_buffer_vaddr_at(0x1000, 4),
+        Some(&bytes[0x10..0x14])
+    );
+    assert_eq!(
+        di.shared_buffer_vaddr_at(0x1040, 0x100),
+        Some(&bytes[0x50..0x90])
+    );
+    assert_eq!(di.shared_buffer_vaddr_at(0x1080, 4), None);
+    assert_eq!(di.shared_buffer_vaddr_at(0x2000, 4), None);
+    assert_eq!(
+        di.shared_buffer_vaddr_at(0x1000, u64::MAX),
+        di.shared_buffer_vaddr(0x1000)
+    );
+}
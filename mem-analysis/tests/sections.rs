@@ -0,0 +1,60 @@
+use mem_analysis::diagnostics::DiagnosticKind;
+use mem_analysis::memory::{MemRange, MemRanges};
+use mem_analysis::region::Protection;
+
+fn section(name: &str, vaddr: u64, paddr: u64, size: u64) -> MemRange {
+    MemRange::new(
+        Protection::from_perm_str("rw-"),
+        name.to_string(),
+        vaddr,
+        paddr,
+        size,
+        size,
+        None,
+        None,
+    )
+}
+
+#[test]
+fn overlapping_sections_are_reported() {
+    let mut mrs = MemRanges::new();
+    mrs.add_mem_range(section("first", 0x1000, 0, 0x2000));
+    mrs.add_mem_range(section("second", 0x2000, 0x2000, 0x1000));
+    assert_eq!(
+        mrs.diagnostics[0].kind,
+        DiagnosticKind::VaddrOverlap {
+            other: "first".to_string(),
+            other_vaddr: 0x1000
+        }
+    );
+    assert_eq!(mrs.get_vaddr_range(0x2800).unwrap().name, "second");
+}
+
+#[test]
+fn sections_past_the_end_of_the_address_space_are_skipped() {
+    let mut mrs = MemRanges::new();
+    mrs.add_mem_range(section("huge_vaddr", u64::MAX - 0x10, 0, 0x1000));
+    mrs.add_mem_range(section("huge_paddr", 0x1000, u64::MAX, 0x1000));
+    mrs.add_mem_range(section("huge_size", 0x1000, 0, u64::MAX));
+    mrs.add_mem_range(section("fine", 0x4000, 0x1000, 0x1000));
+
+    assert_eq!(mrs.count(), 1);
+    let diagnostics = mrs.validate(None);
+    let skipped: Vec<&str> = diagnostics
+        .iter()
+        .filter(|d| d.kind == DiagnosticKind::AddressOverflow)
+        .map(|d| d.name.as_str())
+        .collect();
+    assert_eq!(skipped, vec!["huge_vaddr", "huge_paddr", "huge_size"]);
+}
+
+#[test]
+fn sections_past_the_end_of_the_file_are_reported() {
+    let mut mrs = MemRanges::new();
+    mrs.add_mem_range(section("tail", 0x1000, 0x800, 0x1000));
+    let diagnostics = mrs.validate(Some(0x1000));
+    assert_eq!(
+        diagnostics[0].kind,
+        DiagnosticKind::PaddrPastEof { file_size: 0x1000 }
+    );
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use binread::BinRead;
+use common::{put16, put32, put64};
+use mem_analysis::builder::AddressSpaceBuilder;
+use mem_analysis::data_interface::{DataInterface, ENDIAN};
+use mem_analysis::reader::VWord;
+
+#[derive(Debug, BinRead, PartialEq, Eq)]
+#[br(import(word_sz: u8))]
+struct Header {
+    magic: u32,
+    #[br(args(word_sz))]
+    next: VWord,
+    count: u16,
+}
+
+#[derive(Debug, BinRead, PartialEq, Eq)]
+struct Pair {
+    lo: u16,
+    hi: u16,
+}
+
+// two contiguous regions at 0x10000 and 0x11000, followed by a hole at 0x12000
+fn address_space(first: &[u8], second: &[u8], word_sz: u8, endian: ENDIAN) -> DataInterface {
+    AddressSpaceBuilder::new()
+        .word_sz(word_sz)
+        .alignment(word_sz)
+        .endian(endian)
+        .region(0x10000, "-rw-", "first", first)
+        .region(0x11000, "-rw-", "second", second)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn structs_are_read_little_endian() {
+    let mut first = vec![0u8; 0x1000];
+    put32(&mut first, 0x10, 0x4c55_4155);
+    put64(&mut first, 0x14, 0x1122_3344_5566_7788);
+    put16(&mut first, 0x1c, 3);
+    for idx in 0..3 {
+        put16(&mut first, 0x20 + idx * 4, idx as u16);
+        put16(&mut first, 0x22 + idx * 4, 0x100 + idx as u16);
+    }
+    let di = address_space(&first, &[0u8; 0x1000], 8, ENDIAN::LITTLE);
+
+    let header: Header = di.read_struct(0x10010).unwrap();
+    assert_eq!(
+        header,
+        Header {
+            magic: 0x4c55_4155,
+            next: VWord(0x1122_3344_5566_7788),
+            count: 3,
+        }
+    );
+    let pairs: Vec<Pair> = di.read_array(0x10020, 3).unwrap();
+    assert_eq!(pairs[2], Pair { lo: 2, hi: 0x102 });
+    assert_eq!(di.read_array::<Pair>(0x10020, 0), Some(Vec::new()));
+}
+
+#[test]
+fn structs_are_read_big_endian_with_the_word_size() {
+    let mut first = vec![0u8; 0x1000];
+    first[0x10..0x1a].copy_from_slice(&[0x4c, 0x55, 0x41, 0x55, 0xde, 0xad, 0xbe, 0xef, 0, 7]);
+    let di = address_space(&first, &[0u8; 0x1000], 4, ENDIAN::BIG);
+
+    let header: Header = di.read_struct(0x10010).unwrap();
+    assert_eq!(header.magic, 0x4c55_4155);
+    assert_eq!(header.next, VWord(0xdead_beef));
+    assert_eq!(header.count, 7);
+    let pair: Pair = di.read_struct(0x10014).unwrap();
+    assert_eq!(
+        pair,
+        Pair {
+            lo: 0xdead,
+            hi: 0xbeef
+        }
+    );
+}
+
+#[test]
+fn reads_span_contiguous_ranges_but_not_holes() {
+    let mut first = vec![0u8; 0x1000];
+    let mut second = vec![0u8; 0x1000];
+    put32(&mut first, 0xffc, 0x1111_1111);
+    put64(&mut second, 0, 0x2222_2222_2222_2222);
+    put16(&mut second, 8, 9);
+    put32(&mut second, 0xff8, 0x3333_3333);
+    let di = address_space(&first, &second, 8, ENDIAN::LITTLE);
+
+    // the header starts in the first range and ends in the second one
+    let header: Header = di.read_struct(0x10ffc).unwrap();
+    assert_eq!(header.next, VWord(0x2222_2222_2222_2222));
+    assert_eq!(header.count, 9);
+
+    // the last bytes of the second range are followed by unmapped memory
+    assert!(di.read_struct::<Header>(0x11ff8).is_none());
+    assert!(di.read_struct::<Pair>(0x11ffe).is_none());
+    assert!(di.read_struct::<Pair>(0x12000).is_none());
+    assert_eq!(di.read_array::<Pair>(0x11ff8, 2).map(|p| p.len()), Some(2));
+    assert!(di.read_array::<Pair>(0x11ff8, 3).is_none());
+}